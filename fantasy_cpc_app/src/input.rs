@@ -0,0 +1,480 @@
+use crate::{
+    joystick::{Joystick, JoystickButton, JOYSTICK_COUNT},
+    mouse::Mouse,
+};
+
+/// The keys of the machine's keyboard, named like the keys of winit
+/// so that the names scripts use stay the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Escape,
+    Return,
+    Tab,
+    Back,
+    Space,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Right,
+    Up,
+    Down,
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    Comma,
+    Period,
+    Slash,
+    Backslash,
+    Semicolon,
+    Apostrophe,
+    Grave,
+    Minus,
+    Equals,
+    LBracket,
+    RBracket,
+}
+
+impl Key {
+    /// Every key, in the order of the keyboard matrix
+    pub const ALL: [Key; KEY_COUNT] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+        Key::Key0,
+        Key::Key1,
+        Key::Key2,
+        Key::Key3,
+        Key::Key4,
+        Key::Key5,
+        Key::Key6,
+        Key::Key7,
+        Key::Key8,
+        Key::Key9,
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+        Key::Escape,
+        Key::Return,
+        Key::Tab,
+        Key::Back,
+        Key::Space,
+        Key::Insert,
+        Key::Delete,
+        Key::Home,
+        Key::End,
+        Key::PageUp,
+        Key::PageDown,
+        Key::Left,
+        Key::Right,
+        Key::Up,
+        Key::Down,
+        Key::LShift,
+        Key::RShift,
+        Key::LControl,
+        Key::RControl,
+        Key::LAlt,
+        Key::RAlt,
+        Key::Comma,
+        Key::Period,
+        Key::Slash,
+        Key::Backslash,
+        Key::Semicolon,
+        Key::Apostrophe,
+        Key::Grave,
+        Key::Minus,
+        Key::Equals,
+        Key::LBracket,
+        Key::RBracket,
+    ];
+
+    /// Finds a key by its name, ignoring the case: "Left", "space", "A"...
+    /// Digits can also be named "0" to "9".
+    pub fn from_name(name: &str) -> Option<Key> {
+        Key::ALL.into_iter().find(|key| {
+            let key_name = format!("{:?}", key);
+            key_name.eq_ignore_ascii_case(name) || key_name.strip_prefix("Key") == Some(name)
+        })
+    }
+}
+
+/// Number of keys of the keyboard matrix
+pub const KEY_COUNT: usize = 80;
+
+/// A character typed during the frame, in the order they were typed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextChar {
+    Char(char),
+    /// BACKSPACE, kept apart from the characters like winit does
+    Back,
+}
+
+/// State of one key of the keyboard matrix during a frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyState {
+    pub held: bool,
+    pub pressed: bool,
+    pub released: bool,
+    /// Pressed, or repeated by the keyboard because it's held
+    pub repeated: bool,
+}
+
+/// Everything the user did during a frame: the keyboard matrix, the characters typed,
+/// the mouse and the joysticks. The system fills it from the host's window, tests and scripted
+/// sessions can build one with press_key(), type_text()... and next_frame().
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputState {
+    keys: [KeyState; KEY_COUNT],
+    text: Vec<TextChar>,
+    mouse: Mouse,
+    joysticks: [Joystick; JOYSTICK_COUNT],
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputState {
+    /// No key down, nothing typed, a mouse that doesn't move.
+    pub fn new() -> InputState {
+        InputState {
+            keys: [KeyState::default(); KEY_COUNT],
+            text: Vec::new(),
+            mouse: Mouse::default(),
+            joysticks: [Joystick::default(); JOYSTICK_COUNT],
+        }
+    }
+
+    /// True on the frame the key went down.
+    pub fn key_pressed(&self, key: Key) -> bool {
+        self.keys[key as usize].pressed
+    }
+
+    /// True on the frame the key went down, and when the keyboard repeats it,
+    /// for keys that move a cursor.
+    pub fn key_pressed_os(&self, key: Key) -> bool {
+        self.keys[key as usize].repeated
+    }
+
+    /// True on the frame the key went up.
+    pub fn key_released(&self, key: Key) -> bool {
+        self.keys[key as usize].released
+    }
+
+    /// True while the key is down.
+    pub fn key_held(&self, key: Key) -> bool {
+        self.keys[key as usize].held
+    }
+
+    pub fn held_shift(&self) -> bool {
+        self.key_held(Key::LShift) || self.key_held(Key::RShift)
+    }
+
+    pub fn held_control(&self) -> bool {
+        self.key_held(Key::LControl) || self.key_held(Key::RControl)
+    }
+
+    pub fn held_alt(&self) -> bool {
+        self.key_held(Key::LAlt) || self.key_held(Key::RAlt)
+    }
+
+    /// The characters typed during the frame, the first typed first.
+    pub fn text(&self) -> impl Iterator<Item = TextChar> + '_ {
+        self.text.iter().copied()
+    }
+
+    pub fn get_mouse(&self) -> &Mouse {
+        &self.mouse
+    }
+
+    /// The joystick of a player, 0 for the first one.
+    pub fn get_joystick(&self, player: usize) -> &Joystick {
+        &self.joysticks[player]
+    }
+
+    /// Sets everything about a key at once, for the system that reads the host's keyboard.
+    pub fn set_key(&mut self, key: Key, state: KeyState) {
+        self.keys[key as usize] = state;
+    }
+
+    pub fn press_key(&mut self, key: Key) {
+        let state = &mut self.keys[key as usize];
+        state.pressed = !state.held;
+        state.held = true;
+        state.repeated = true;
+    }
+
+    /// The keyboard repeats a key that is held.
+    pub fn repeat_key(&mut self, key: Key) {
+        self.keys[key as usize].repeated = true;
+    }
+
+    pub fn release_key(&mut self, key: Key) {
+        let state = &mut self.keys[key as usize];
+        state.released = state.held;
+        state.held = false;
+    }
+
+    pub fn type_char(&mut self, text_char: TextChar) {
+        self.text.push(text_char);
+    }
+
+    /// Types every character of the text, '\u{8}' is BACKSPACE.
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\u{8}' => self.type_char(TextChar::Back),
+                c => self.type_char(TextChar::Char(c)),
+            }
+        }
+    }
+
+    pub fn set_mouse(&mut self, mouse: Mouse) {
+        self.mouse = mouse;
+    }
+
+    pub fn set_joystick(&mut self, player: usize, joystick: Joystick) {
+        self.joysticks[player] = joystick;
+    }
+
+    pub fn press_joystick(&mut self, player: usize, button: JoystickButton) {
+        let joystick = &mut self.joysticks[player];
+        joystick.pressed[button as usize] = !joystick.held[button as usize];
+        joystick.held[button as usize] = true;
+    }
+
+    pub fn release_joystick(&mut self, player: usize, button: JoystickButton) {
+        let joystick = &mut self.joysticks[player];
+        joystick.released[button as usize] = joystick.held[button as usize];
+        joystick.held[button as usize] = false;
+    }
+
+    /// Starts a new frame: the keys stay held, what happened during the last frame is forgotten.
+    pub fn next_frame(&mut self) {
+        for state in self.keys.iter_mut() {
+            state.pressed = false;
+            state.released = false;
+            state.repeated = false;
+        }
+        self.text.clear();
+        self.mouse.delta = (0.0, 0.0);
+        self.mouse.wheel = 0.0;
+        self.mouse.pressed = [false; 3];
+        self.mouse.released = [false; 3];
+        for joystick in self.joysticks.iter_mut() {
+            joystick.update(joystick.held);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_goes_down_and_up() {
+        let mut inputs = InputState::new();
+        inputs.press_key(Key::A);
+        assert!(inputs.key_pressed(Key::A) && inputs.key_held(Key::A));
+        assert!(inputs.key_pressed_os(Key::A));
+        assert!(!inputs.key_released(Key::A));
+
+        inputs.next_frame();
+        assert!(inputs.key_held(Key::A));
+        assert!(!inputs.key_pressed(Key::A) && !inputs.key_pressed_os(Key::A));
+
+        inputs.release_key(Key::A);
+        assert!(inputs.key_released(Key::A) && !inputs.key_held(Key::A));
+
+        inputs.next_frame();
+        assert_eq!(inputs, InputState::new());
+    }
+
+    #[test]
+    fn held_key_is_repeated_but_not_pressed_again() {
+        let mut inputs = InputState::new();
+        inputs.press_key(Key::Left);
+        inputs.next_frame();
+
+        inputs.repeat_key(Key::Left);
+        assert!(inputs.key_pressed_os(Key::Left));
+        assert!(!inputs.key_pressed(Key::Left));
+
+        inputs.next_frame();
+        inputs.press_key(Key::Left);
+        assert!(!inputs.key_pressed(Key::Left));
+    }
+
+    #[test]
+    fn releasing_a_key_that_isnt_held_does_nothing() {
+        let mut inputs = InputState::new();
+        inputs.release_key(Key::Space);
+        assert!(!inputs.key_released(Key::Space));
+    }
+
+    #[test]
+    fn modifiers_are_held_on_either_side() {
+        let mut inputs = InputState::new();
+        assert!(!inputs.held_control());
+        inputs.press_key(Key::RControl);
+        inputs.press_key(Key::LShift);
+        assert!(inputs.held_control() && inputs.held_shift() && !inputs.held_alt());
+    }
+
+    #[test]
+    fn text_lasts_one_frame() {
+        let mut inputs = InputState::new();
+        inputs.type_text("hé\u{8}");
+        let text: Vec<TextChar> = inputs.text().collect();
+        assert_eq!(
+            text,
+            vec![TextChar::Char('h'), TextChar::Char('é'), TextChar::Back]
+        );
+
+        inputs.next_frame();
+        assert_eq!(inputs.text().count(), 0);
+    }
+
+    #[test]
+    fn mouse_movement_lasts_one_frame() {
+        let mut inputs = InputState::new();
+        inputs.set_mouse(Mouse {
+            text_position: Some((3, 4)),
+            delta: (2.0, -1.0),
+            wheel: 1.0,
+            held: [true, false, false],
+            pressed: [true, false, false],
+            ..Mouse::default()
+        });
+
+        inputs.next_frame();
+        let mouse = inputs.get_mouse();
+        assert_eq!(mouse.text_position, Some((3, 4)));
+        assert_eq!((mouse.delta, mouse.wheel), ((0.0, 0.0), 0.0));
+        assert_eq!(
+            (mouse.held, mouse.pressed),
+            ([true, false, false], [false; 3])
+        );
+    }
+
+    #[test]
+    fn joystick_edges() {
+        let mut inputs = InputState::new();
+        inputs.press_joystick(1, JoystickButton::Fire1);
+        let joystick = inputs.get_joystick(1);
+        assert!(joystick.is_pressed(JoystickButton::Fire1));
+        assert!(joystick.is_held(JoystickButton::Fire1));
+        assert!(!inputs.get_joystick(0).is_held(JoystickButton::Fire1));
+
+        inputs.next_frame();
+        let joystick = inputs.get_joystick(1);
+        assert!(joystick.is_held(JoystickButton::Fire1));
+        assert!(!joystick.is_pressed(JoystickButton::Fire1));
+
+        inputs.release_joystick(1, JoystickButton::Fire1);
+        assert!(inputs.get_joystick(1).is_released(JoystickButton::Fire1));
+
+        inputs.next_frame();
+        assert_eq!(*inputs.get_joystick(1), Joystick::default());
+    }
+
+    #[test]
+    fn keys_by_name() {
+        assert_eq!(Key::from_name("space"), Some(Key::Space));
+        assert_eq!(Key::from_name("LEFT"), Some(Key::Left));
+        assert_eq!(Key::from_name("7"), Some(Key::Key7));
+        assert_eq!(Key::from_name("Key7"), Some(Key::Key7));
+        assert_eq!(Key::from_name("nope"), None);
+        for (index, key) in Key::ALL.into_iter().enumerate() {
+            assert_eq!(key as usize, index);
+        }
+    }
+}
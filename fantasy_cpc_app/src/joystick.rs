@@ -0,0 +1,194 @@
+use crate::input::{InputState, Key};
+
+/// Number of joysticks of the machine, one per player
+pub const JOYSTICK_COUNT: usize = 2;
+
+/// Number of buttons of a joystick, directions included
+pub const JOYSTICK_BUTTON_COUNT: usize = 6;
+
+/// Buttons of a joystick like the CPC's: four directions and two fire buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JoystickButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    Fire1,
+    Fire2,
+}
+
+impl JoystickButton {
+    pub const ALL: [JoystickButton; JOYSTICK_BUTTON_COUNT] = [
+        JoystickButton::Up,
+        JoystickButton::Down,
+        JoystickButton::Left,
+        JoystickButton::Right,
+        JoystickButton::Fire1,
+        JoystickButton::Fire2,
+    ];
+
+    /// Name of the button in the shell, "up", "fire1"...
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            JoystickButton::Up => "up",
+            JoystickButton::Down => "down",
+            JoystickButton::Left => "left",
+            JoystickButton::Right => "right",
+            JoystickButton::Fire1 => "fire1",
+            JoystickButton::Fire2 => "fire2",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<JoystickButton> {
+        JoystickButton::ALL
+            .into_iter()
+            .find(|button| button.get_name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// What a joystick did during the last frame. Like on the CPC, a direction is a button:
+/// up and left can be held together for a diagonal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Joystick {
+    pub held: [bool; JOYSTICK_BUTTON_COUNT],
+    pub pressed: [bool; JOYSTICK_BUTTON_COUNT],
+    pub released: [bool; JOYSTICK_BUTTON_COUNT],
+}
+
+impl Joystick {
+    pub fn is_held(&self, button: JoystickButton) -> bool {
+        self.held[button as usize]
+    }
+
+    /// True on the frame the button went down.
+    pub fn is_pressed(&self, button: JoystickButton) -> bool {
+        self.pressed[button as usize]
+    }
+
+    /// True on the frame the button went up.
+    pub fn is_released(&self, button: JoystickButton) -> bool {
+        self.released[button as usize]
+    }
+
+    /// -1 when pushed left, 1 when pushed right, 0 when centered or both are held.
+    pub fn get_x(&self) -> i8 {
+        self.is_held(JoystickButton::Right) as i8 - self.is_held(JoystickButton::Left) as i8
+    }
+
+    /// -1 when pushed up, 1 when pushed down, 0 when centered or both are held.
+    pub fn get_y(&self) -> i8 {
+        self.is_held(JoystickButton::Down) as i8 - self.is_held(JoystickButton::Up) as i8
+    }
+
+    /// Starts a new frame with the buttons held now, the pressed and released buttons
+    /// are found by comparing with the last frame.
+    pub fn update(&mut self, held: [bool; JOYSTICK_BUTTON_COUNT]) {
+        for (button, held) in held.into_iter().enumerate() {
+            self.pressed[button] = held && !self.held[button];
+            self.released[button] = !held && self.held[button];
+            self.held[button] = held;
+        }
+    }
+}
+
+/// Keys of the keyboard that move a joystick, one for each button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JoystickMapping {
+    keys: [Key; JOYSTICK_BUTTON_COUNT],
+}
+
+impl JoystickMapping {
+    /// The arrows, SPACE and RIGHT SHIFT for the first player,
+    /// W S A D, Q and E for the second one.
+    pub fn new(player: usize) -> JoystickMapping {
+        let keys = match player {
+            0 => [
+                Key::Up,
+                Key::Down,
+                Key::Left,
+                Key::Right,
+                Key::Space,
+                Key::RShift,
+            ],
+            _ => [Key::W, Key::S, Key::A, Key::D, Key::Q, Key::E],
+        };
+        JoystickMapping { keys }
+    }
+
+    pub fn get_key(&self, button: JoystickButton) -> Key {
+        self.keys[button as usize]
+    }
+
+    pub fn set_key(&mut self, button: JoystickButton, key: Key) {
+        self.keys[button as usize] = key;
+    }
+
+    /// The buttons held on the joystick, according to the keys held.
+    pub fn read(&self, inputs: &InputState) -> [bool; JOYSTICK_BUTTON_COUNT] {
+        self.keys.map(|key| inputs.key_held(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(buttons: &[JoystickButton]) -> [bool; JOYSTICK_BUTTON_COUNT] {
+        JoystickButton::ALL.map(|button| buttons.contains(&button))
+    }
+
+    #[test]
+    fn update_finds_the_edges() {
+        let mut joystick = Joystick::default();
+        joystick.update(held(&[JoystickButton::Up, JoystickButton::Fire2]));
+        assert!(joystick.is_pressed(JoystickButton::Up));
+        assert!(joystick.is_pressed(JoystickButton::Fire2));
+
+        joystick.update(held(&[JoystickButton::Up]));
+        assert!(joystick.is_held(JoystickButton::Up));
+        assert!(!joystick.is_pressed(JoystickButton::Up));
+        assert!(joystick.is_released(JoystickButton::Fire2));
+    }
+
+    #[test]
+    fn directions() {
+        let mut joystick = Joystick::default();
+        assert_eq!((joystick.get_x(), joystick.get_y()), (0, 0));
+        joystick.update(held(&[JoystickButton::Up, JoystickButton::Left]));
+        assert_eq!((joystick.get_x(), joystick.get_y()), (-1, -1));
+        joystick.update(held(&[JoystickButton::Left, JoystickButton::Right]));
+        assert_eq!(joystick.get_x(), 0);
+    }
+
+    #[test]
+    fn mapping_reads_the_keys() {
+        let mut inputs = InputState::new();
+        inputs.press_key(Key::W);
+        inputs.press_key(Key::Space);
+        assert_eq!(
+            JoystickMapping::new(0).read(&inputs),
+            held(&[JoystickButton::Fire1])
+        );
+        assert_eq!(
+            JoystickMapping::new(1).read(&inputs),
+            held(&[JoystickButton::Up])
+        );
+
+        let mut mapping = JoystickMapping::new(0);
+        mapping.set_key(JoystickButton::Fire1, Key::Return);
+        assert_eq!(mapping.get_key(JoystickButton::Fire1), Key::Return);
+        assert_eq!(mapping.read(&inputs), held(&[]));
+    }
+
+    #[test]
+    fn buttons_by_name() {
+        for button in JoystickButton::ALL {
+            assert_eq!(JoystickButton::from_name(button.get_name()), Some(button));
+        }
+        assert_eq!(
+            JoystickButton::from_name("FIRE1"),
+            Some(JoystickButton::Fire1)
+        );
+        assert_eq!(JoystickButton::from_name("fire3"), None);
+    }
+}
@@ -1,8 +1,15 @@
 use fantasy_cpc_clock::Clock;
 use fantasy_cpc_display_controller::DisplayController;
-use winit::event::VirtualKeyCode::Escape;
-use winit::event_loop::ControlFlow;
-use winit_input_helper::WinitInputHelper;
+use fantasy_cpc_vfs::FileSystemHandle;
+
+use input::{InputState, Key};
+use joystick::JoystickButton;
+use mouse::MouseMode;
+
+pub mod input;
+pub mod joystick;
+pub mod mouse;
+pub mod ui;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum AppStatus {
@@ -16,6 +23,8 @@ pub struct FantasyCppAppDefaultParams {
     status: AppStatus,
     initialized: bool,
     autoescape_enabled: bool,
+    file_system: Option<FileSystemHandle>,
+    mouse_mode: MouseMode,
 }
 
 impl FantasyCppAppDefaultParams {
@@ -25,6 +34,8 @@ impl FantasyCppAppDefaultParams {
             status: AppStatus::Stopped,
             initialized: false,
             autoescape_enabled,
+            file_system: None,
+            mouse_mode: MouseMode::Pointer,
         }
     }
 
@@ -57,6 +68,25 @@ impl FantasyCppAppDefaultParams {
     pub fn get_enable_autoescape(&self) -> bool {
         self.autoescape_enabled
     }
+
+    /// The machine's file system, for apps to load and save their data.
+    /// Set by the system when the app is registered, None before that.
+    pub fn get_file_system(&self) -> Option<FileSystemHandle> {
+        self.file_system.clone()
+    }
+
+    pub fn set_file_system(&mut self, file_system: FileSystemHandle) {
+        self.file_system = Some(file_system);
+    }
+
+    pub fn get_mouse_mode(&self) -> MouseMode {
+        self.mouse_mode
+    }
+
+    /// Applied by the system while the app is in the foreground.
+    pub fn set_mouse_mode(&mut self, mouse_mode: MouseMode) {
+        self.mouse_mode = mouse_mode;
+    }
 }
 
 pub trait FantasyCpcApp {
@@ -66,15 +96,24 @@ pub trait FantasyCpcApp {
 
     fn update_app(
         &mut self,
-        inputs: Option<&WinitInputHelper>,
+        inputs: Option<&InputState>,
         clock: &Clock,
     ) -> Option<AppResponse>;
 
     fn draw_app(&mut self, clock: &Clock, display_controller: &mut DisplayController);
 
+    /// Gives the app a file to open the next time it starts, like a program or a game.
+    /// Apps that don't work on files ignore it.
+    fn open_file(&mut self, _path: &str) {}
+
+    /// Called when the app is stopped by the system, from the shell, by a reboot or by ESC
+    /// when autoescape is enabled.
+    /// Apps that hold a program or a connection of the host end it here.
+    fn stop_app(&mut self) {}
+
     fn exec_app(
         &mut self,
-        inputs: Option<&WinitInputHelper>,
+        inputs: Option<&InputState>,
         system_clock: &Clock,
         display_controller: &mut DisplayController,
     ) -> Option<AppResponse> {
@@ -101,15 +140,16 @@ pub trait FantasyCpcApp {
 
     fn update(
         &mut self,
-        inputs: Option<&WinitInputHelper>,
+        inputs: Option<&InputState>,
         system_clock: &Clock,
     ) -> Option<AppResponse> {
         // Implementing default behaviour when ESCAPE key is pressed in app
         // Applied only if enable_auto_escape is set to true in app.
         if inputs.is_some()
             && self.get_app_params().get_enable_autoescape()
-            && inputs.unwrap().key_released(Escape)
+            && inputs.unwrap().key_released(Key::Escape)
         {
+            self.stop_app();
             self.get_app_params().change_status(AppStatus::Stopped);
             self.get_app_params().set_initialized(false);
         }
@@ -122,10 +162,52 @@ pub trait FantasyCpcApp {
     }
 }
 
+/// Settings of the CRT shader that apps can change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CrtParameter {
+    /// Shader mode, from 0 to 2
+    Mode(usize),
+    /// Screen curvature level, from 0 (flat) to 6
+    Distortion(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RebootKind {
+    /// Stops the apps and resets the display, apps keep their data until they are started again
+    Warm,
+    /// Like switching the computer off and on, every app and setting starts from scratch
+    Cold,
+}
+
+/// Requests sent by apps to the system, executed by the main loop after the apps are updated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SystemCommand {
+    /// Starts the app with that name if needed and gives it the focus
+    LaunchApp(String),
+    /// Restarts the app named first on the file given second, and gives it the focus
+    LaunchAppWithFile(String, String),
+    /// Stops the app with that name
+    StopApp(String),
+    /// Keeps the app with that name updated without inputs or display, starting it if needed
+    BackgroundApp(String),
+    /// Gives the focus back to an app that was sent to the background
+    ForegroundApp(String),
+    /// Prints the apps and their status to the terminal
+    ListApps,
+    SetCrtParameter(CrtParameter),
+    /// Sets the key that moves a button of the joystick of a player, 0 for the first one
+    MapJoystick(usize, JoystickButton, Key),
+    /// Prints the keys of the joysticks to the terminal
+    ListJoystickMappings,
+    Reboot(RebootKind),
+    Quit,
+    /// Displays a message in the shell's terminal
+    PrintToTerminal(String),
+}
+
 #[derive(Clone)]
 pub struct AppResponse {
-    pub event: Option<ControlFlow>,
-    pub message: Option<String>,
+    pub commands: Vec<SystemCommand>,
 }
 
 impl Default for AppResponse {
@@ -137,16 +219,79 @@ impl Default for AppResponse {
 impl AppResponse {
     pub fn new() -> AppResponse {
         AppResponse {
-            event: None,
-            message: None,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn push_command(&mut self, command: SystemCommand) {
+        self.commands.push(command);
+    }
+
+    pub fn get_commands(&self) -> &[SystemCommand] {
+        &self.commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestApp {
+        app_params: FantasyCppAppDefaultParams,
+        stopped: usize,
+    }
+
+    impl FantasyCpcApp for TestApp {
+        fn get_app_params(&mut self) -> &mut FantasyCppAppDefaultParams {
+            &mut self.app_params
         }
+
+        fn init_app(&mut self, _clock: &Clock, _display_controller: &mut DisplayController) {}
+
+        fn update_app(
+            &mut self,
+            _inputs: Option<&InputState>,
+            _clock: &Clock,
+        ) -> Option<AppResponse> {
+            None
+        }
+
+        fn draw_app(&mut self, _clock: &Clock, _display_controller: &mut DisplayController) {}
+
+        fn stop_app(&mut self) {
+            self.stopped += 1;
+        }
+    }
+
+    fn run_app_with_escape(autoescape_enabled: bool) -> TestApp {
+        let mut app = TestApp {
+            app_params: FantasyCppAppDefaultParams::new(String::from("test"), autoescape_enabled),
+            stopped: 0,
+        };
+        app.get_app_params().change_status(AppStatus::Running);
+
+        let clock = Clock::new();
+        let mut display_controller = DisplayController::new();
+        let mut inputs = InputState::new();
+        inputs.press_key(Key::Escape);
+        inputs.next_frame();
+        inputs.release_key(Key::Escape);
+        app.exec_app(Some(&inputs), &clock, &mut display_controller);
+        app
     }
 
-    pub fn set_message(&mut self, string: String) {
-        self.message = Some(string);
+    #[test]
+    fn autoescape_stops_the_app() {
+        let mut app = run_app_with_escape(true);
+        assert_eq!(app.stopped, 1);
+        assert_eq!(*app.get_app_params().get_status(), AppStatus::Stopped);
+        assert!(!app.get_app_params().get_initialized());
     }
 
-    pub fn get_message(&self) -> &Option<String> {
-        &self.message
+    #[test]
+    fn escape_is_left_to_the_app_without_autoescape() {
+        let mut app = run_app_with_escape(false);
+        assert_eq!(app.stopped, 0);
+        assert_eq!(*app.get_app_params().get_status(), AppStatus::Running);
     }
 }
@@ -0,0 +1,63 @@
+/// How the app in the foreground wants the mouse.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MouseMode {
+    /// The machine's pointer follows the mouse over the screen
+    #[default]
+    Pointer,
+    /// No pointer, the position and buttons are still given
+    Hidden,
+    /// The mouse is captured by the window, only its movements are given, for games
+    /// that turn the camera with the mouse. There's no position in this mode.
+    Relative,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl MouseButton {
+    fn index(&self) -> usize {
+        match self {
+            MouseButton::Left => 0,
+            MouseButton::Right => 1,
+            MouseButton::Middle => 2,
+        }
+    }
+}
+
+/// What the mouse did during the last frame, as seen by the machine.
+/// Positions are mapped from the window through the upscaling and the CRT distortion,
+/// so they match what is under the pointer on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mouse {
+    /// Pixel of the frame under the pointer, overscan included, None outside of the screen
+    pub frame_position: Option<(usize, usize)>,
+    /// Character of the text layer under the pointer, None over the borders
+    pub text_position: Option<(usize, usize)>,
+    /// Movement since the last frame, in frame pixels, or in raw mouse units in relative mode
+    pub delta: (f32, f32),
+    /// Wheel movement since the last frame, positive when turned away from the user
+    pub wheel: f32,
+    pub held: [bool; 3],
+    pub pressed: [bool; 3],
+    pub released: [bool; 3],
+}
+
+impl Mouse {
+    pub fn is_held(&self, button: MouseButton) -> bool {
+        self.held[button.index()]
+    }
+
+    /// True on the frame the button went down.
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        self.pressed[button.index()]
+    }
+
+    /// True on the frame the button went up.
+    pub fn is_released(&self, button: MouseButton) -> bool {
+        self.released[button.index()]
+    }
+}
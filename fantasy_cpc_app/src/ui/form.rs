@@ -0,0 +1,215 @@
+use std::any::Any;
+
+use fantasy_cpc_display_controller::text_layer::text_layer::TextLayer;
+
+use super::widget::{Response, Theme, Widget};
+use crate::input::{InputState, Key};
+
+/// Something the app has to act on, sent by the widget with that id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UiEvent {
+    pub id: &'static str,
+    /// Changed or Activated
+    pub response: Response,
+}
+
+/// A group of widgets, one of them has the focus and receives the keys.
+/// When it doesn't use them, TAB and DOWN give the focus to the next widget,
+/// SHIFT+TAB and UP to the previous one.
+pub struct Form {
+    widgets: Vec<(&'static str, Box<dyn Widget>)>,
+    focus: Option<usize>,
+    theme: Theme,
+}
+
+impl Form {
+    pub fn new(theme: Theme) -> Form {
+        Form {
+            widgets: Vec::new(),
+            focus: None,
+            theme,
+        }
+    }
+
+    /// Adds a widget, the first one that can have the focus gets it.
+    pub fn add<W: Widget>(&mut self, id: &'static str, widget: W) {
+        if self.focus.is_none() && widget.is_focusable() {
+            self.focus = Some(self.widgets.len());
+        }
+        self.widgets.push((id, Box::new(widget)));
+    }
+
+    pub fn get<W: Widget>(&self, id: &str) -> Option<&W> {
+        let (_, widget) = self
+            .widgets
+            .iter()
+            .find(|(widget_id, _)| *widget_id == id)?;
+        (widget.as_ref() as &dyn Any).downcast_ref::<W>()
+    }
+
+    pub fn get_mut<W: Widget>(&mut self, id: &str) -> Option<&mut W> {
+        let (_, widget) = self
+            .widgets
+            .iter_mut()
+            .find(|(widget_id, _)| *widget_id == id)?;
+        (widget.as_mut() as &mut dyn Any).downcast_mut::<W>()
+    }
+
+    pub fn get_theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn get_focused_id(&self) -> Option<&'static str> {
+        self.focus.map(|index| self.widgets[index].0)
+    }
+
+    /// Gives the focus to the widget with that id, if it can have it.
+    pub fn set_focus(&mut self, id: &str) {
+        if let Some(index) = self
+            .widgets
+            .iter()
+            .position(|(widget_id, widget)| *widget_id == id && widget.is_focusable())
+        {
+            self.focus = Some(index);
+        }
+    }
+
+    /// Moves the focus to the next widget that can have it, going around the form.
+    fn move_focus(&mut self, forward: bool) {
+        let Some(focus) = self.focus else {
+            return;
+        };
+
+        let count = self.widgets.len();
+        for step in 1..count {
+            let index = match forward {
+                true => (focus + step) % count,
+                false => (focus + count - step) % count,
+            };
+            if self.widgets[index].1.is_focusable() {
+                self.focus = Some(index);
+                return;
+            }
+        }
+    }
+
+    /// Gives the keys to the focused widget, returns what the app has to act on.
+    pub fn update(&mut self, inputs: &InputState) -> Option<UiEvent> {
+        let focus = self.focus?;
+        let (id, widget) = &mut self.widgets[focus];
+        let id = *id;
+
+        match widget.handle_input(inputs) {
+            Response::Ignored => (),
+            Response::Consumed => return None,
+            response => return Some(UiEvent { id, response }),
+        }
+
+        let tab = inputs.key_pressed_os(Key::Tab);
+        if inputs.key_pressed_os(Key::Down) || (tab && !inputs.held_shift()) {
+            self.move_focus(true);
+        } else if inputs.key_pressed_os(Key::Up) || (tab && inputs.held_shift()) {
+            self.move_focus(false);
+        }
+        None
+    }
+
+    /// Draws the widgets in the order they were added, over what's already in the text layer.
+    pub fn draw(&self, text_layer: &mut TextLayer) {
+        for (index, (_, widget)) in self.widgets.iter().enumerate() {
+            widget.draw(text_layer, &self.theme, self.focus == Some(index));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::widgets::{Button, Checkbox, Label};
+
+    fn key(key: Key) -> InputState {
+        let mut inputs = InputState::new();
+        inputs.press_key(key);
+        inputs
+    }
+
+    /// A label, two buttons, a label, a check box
+    fn form() -> Form {
+        let mut form = Form::new(Theme::default());
+        form.add("title", Label::new(0, 0, "Options"));
+        form.add("ok", Button::new(0, 1, "OK"));
+        form.add("cancel", Button::new(0, 2, "Cancel"));
+        form.add("help", Label::new(0, 3, "Help"));
+        form.add("sound", Checkbox::new(0, 4, "Sound", true));
+        form
+    }
+
+    #[test]
+    fn first_focusable_widget_gets_the_focus() {
+        assert_eq!(form().get_focused_id(), Some("ok"));
+        assert_eq!(Form::new(Theme::default()).get_focused_id(), None);
+    }
+
+    #[test]
+    fn focus_skips_labels_and_goes_around() {
+        let mut form = form();
+        form.update(&key(Key::Down));
+        assert_eq!(form.get_focused_id(), Some("cancel"));
+        form.update(&key(Key::Tab));
+        assert_eq!(form.get_focused_id(), Some("sound"));
+        form.update(&key(Key::Down));
+        assert_eq!(form.get_focused_id(), Some("ok"));
+
+        let mut shift_tab = key(Key::Tab);
+        shift_tab.press_key(Key::LShift);
+        form.update(&shift_tab);
+        assert_eq!(form.get_focused_id(), Some("sound"));
+        form.update(&key(Key::Up));
+        assert_eq!(form.get_focused_id(), Some("cancel"));
+    }
+
+    #[test]
+    fn set_focus_only_on_focusable_widgets() {
+        let mut form = form();
+        form.set_focus("help");
+        assert_eq!(form.get_focused_id(), Some("ok"));
+        form.set_focus("sound");
+        assert_eq!(form.get_focused_id(), Some("sound"));
+    }
+
+    #[test]
+    fn events_come_from_the_focused_widget() {
+        let mut form = form();
+        let event = form.update(&key(Key::Return));
+        assert_eq!(
+            event,
+            Some(UiEvent {
+                id: "ok",
+                response: Response::Activated
+            })
+        );
+
+        form.set_focus("sound");
+        let event = form.update(&key(Key::Space));
+        assert_eq!(event.map(|event| event.response), Some(Response::Changed));
+        assert_eq!(
+            form.get::<Checkbox>("sound").map(Checkbox::get_checked),
+            Some(false)
+        );
+        assert!(form.get::<Button>("sound").is_none());
+
+        form.get_mut::<Checkbox>("sound").unwrap().set_checked(true);
+        assert!(form.get::<Checkbox>("sound").unwrap().get_checked());
+    }
+
+    #[test]
+    fn theme_can_change() {
+        let mut form = form();
+        form.set_theme(Theme::new(1, 2, 3));
+        assert_eq!(form.get_theme().highlight_color, 3);
+    }
+}
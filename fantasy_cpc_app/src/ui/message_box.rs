@@ -0,0 +1,184 @@
+use fantasy_cpc_display_controller::{
+    config::{TEXT_COLUMNS, TEXT_ROWS},
+    text_layer::text_layer::TextLayer,
+};
+
+use super::widget::{draw_box, put_str, Response, Theme, Widget};
+use crate::input::{InputState, Key};
+
+/// A framed message in the middle of the screen with a row of buttons,
+/// like "Quit the game ?" with "Yes" and "No". LEFT, RIGHT and TAB choose a button,
+/// ENTER activates it and get_selected() tells which one.
+/// The app keeps it apart from its form and gives it the keys while it's shown.
+pub struct MessageBox {
+    title: String,
+    lines: Vec<String>,
+    buttons: Vec<String>,
+    selected: usize,
+}
+
+impl MessageBox {
+    pub fn new(title: &str, message: &str, buttons: &[&str]) -> MessageBox {
+        MessageBox {
+            title: title.to_string(),
+            lines: wrap(message, TEXT_COLUMNS - 4),
+            buttons: buttons.iter().map(|button| button.to_string()).collect(),
+            selected: 0,
+        }
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn set_selected(&mut self, selected: usize) {
+        self.selected = selected.min(self.buttons.len().saturating_sub(1));
+    }
+
+    /// Text of the buttons, as drawn: "< Yes > < No >"
+    fn get_buttons_width(&self) -> usize {
+        self.buttons
+            .iter()
+            .map(|button| button.chars().count() + 5)
+            .sum::<usize>()
+            .saturating_sub(1)
+    }
+
+    /// Position and size of the frame, centered on the screen
+    fn get_frame(&self) -> (usize, usize, usize, usize) {
+        let longest = self
+            .lines
+            .iter()
+            .map(|line| line.chars().count())
+            .chain([self.title.chars().count() + 2, self.get_buttons_width()])
+            .max()
+            .unwrap_or(0);
+        let width = (longest + 4).min(TEXT_COLUMNS);
+        let height = (self.lines.len() + 4).min(TEXT_ROWS);
+        (
+            (TEXT_COLUMNS - width) / 2,
+            (TEXT_ROWS - height) / 2,
+            width,
+            height,
+        )
+    }
+}
+
+impl Widget for MessageBox {
+    fn handle_input(&mut self, inputs: &InputState) -> Response {
+        if self.buttons.is_empty() {
+            return Response::Ignored;
+        }
+        if inputs.key_pressed(Key::Return) {
+            return Response::Activated;
+        }
+
+        let previous = self.selected;
+        if inputs.key_pressed_os(Key::Left) {
+            self.selected = self.selected.saturating_sub(1);
+        } else if inputs.key_pressed_os(Key::Right) || inputs.key_pressed_os(Key::Tab) {
+            self.selected = (self.selected + 1) % self.buttons.len();
+        }
+
+        match self.selected == previous {
+            true => Response::Consumed,
+            false => Response::Changed,
+        }
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, focused: bool) {
+        let (x, y, width, height) = self.get_frame();
+        draw_box(text_layer, (x, y, width, height), &self.title, theme);
+
+        for (row, line) in self.lines.iter().enumerate() {
+            put_str(text_layer, x + 2, y + 1 + row, line, theme, false);
+        }
+
+        let mut column = x + (width - self.get_buttons_width()) / 2;
+        for (index, button) in self.buttons.iter().enumerate() {
+            let text = format!("< {} >", button);
+            let selected = focused && index == self.selected;
+            put_str(text_layer, column, y + height - 2, &text, theme, selected);
+            column += text.chars().count() + 1;
+        }
+    }
+}
+
+/// Cuts the message in lines of at most width characters, between words when possible.
+fn wrap(message: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in message.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            while !word.is_empty() {
+                let line_length = line.chars().count();
+                let needed = if line.is_empty() { 0 } else { 1 } + word.len();
+                if line_length + needed <= width {
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.extend(word.drain(..));
+                } else if line.is_empty() {
+                    line.extend(word.drain(..width));
+                } else {
+                    lines.push(std::mem::take(&mut line));
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key) -> InputState {
+        let mut inputs = InputState::new();
+        inputs.press_key(key);
+        inputs
+    }
+
+    #[test]
+    fn buttons_are_chosen_with_the_arrows() {
+        let mut message_box = MessageBox::new("Quit", "Really ?", &["Yes", "No"]);
+        assert_eq!(
+            message_box.handle_input(&key(Key::Left)),
+            Response::Consumed
+        );
+        assert_eq!(
+            message_box.handle_input(&key(Key::Right)),
+            Response::Changed
+        );
+        assert_eq!(message_box.get_selected(), 1);
+        assert_eq!(message_box.handle_input(&key(Key::Tab)), Response::Changed);
+        assert_eq!(message_box.get_selected(), 0);
+        message_box.set_selected(5);
+        assert_eq!(message_box.get_selected(), 1);
+        assert_eq!(
+            message_box.handle_input(&key(Key::Return)),
+            Response::Activated
+        );
+    }
+
+    #[test]
+    fn frame_is_centered() {
+        let message_box = MessageBox::new("Quit", "Really ?", &["Yes", "No"]);
+        // "< Yes > < No >" is the widest line
+        let (x, y, width, height) = message_box.get_frame();
+        assert_eq!((width, height), (14 + 4, 5));
+        assert_eq!(
+            (x, y),
+            ((TEXT_COLUMNS - width) / 2, (TEXT_ROWS - height) / 2)
+        );
+    }
+
+    #[test]
+    fn message_is_wrapped_between_words() {
+        assert_eq!(wrap("one two three", 7), vec!["one two", "three"]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("one\ntwo", 20), vec!["one", "two"]);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod form;
+pub mod message_box;
+pub mod widget;
+pub mod widgets;
@@ -0,0 +1,137 @@
+use std::any::Any;
+
+use fantasy_cpc_display_controller::{
+    color_palettes::{BLACK, YELLOW},
+    config::{TEXT_COLUMNS, TEXT_ROWS},
+    text_layer::text_layer::{TextLayer, TextLayerChar},
+};
+
+use crate::input::InputState;
+
+/// Colors of the widgets, the widget that has the focus is drawn in reverse video.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub color: usize,
+    pub bkg_color: usize,
+    /// Color of the values: the ticks of a check box, the bar of a slider...
+    pub highlight_color: usize,
+}
+
+impl Theme {
+    pub const fn new(color: usize, bkg_color: usize, highlight_color: usize) -> Theme {
+        Theme {
+            color,
+            bkg_color,
+            highlight_color,
+        }
+    }
+
+    /// A character in the colors of the theme, reversed when focused.
+    pub fn get_char(&self, c: char, focused: bool) -> TextLayerChar {
+        TextLayerChar {
+            c,
+            color: self.color,
+            bkg_color: self.bkg_color,
+            swap: focused,
+            blink: false,
+            shadowed: false,
+        }
+    }
+
+    /// A character in the highlight color.
+    pub fn get_highlight_char(&self, c: char, focused: bool) -> TextLayerChar {
+        TextLayerChar {
+            color: self.highlight_color,
+            ..self.get_char(c, focused)
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::new(YELLOW, BLACK, YELLOW)
+    }
+}
+
+/// What a widget did with the keys it received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Response {
+    /// The keys aren't for this widget, the form can use them to move the focus
+    Ignored,
+    /// The widget used the keys, nothing for the app to do
+    Consumed,
+    /// The value of the widget changed: a check box was ticked, a slider moved...
+    Changed,
+    /// The widget was activated with ENTER: a button pressed, an item chosen...
+    Activated,
+}
+
+/// A piece of user interface drawn in the text layer, at a position in columns and rows.
+/// Widgets are usually put in a Form, that gives the keys to the one that has the focus.
+pub trait Widget: Any {
+    /// Handles the keys when the widget has the focus.
+    fn handle_input(&mut self, inputs: &InputState) -> Response;
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, focused: bool);
+
+    /// Labels, progress bars and other widgets that only show something can't get the focus.
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+/// Writes a character, nothing is written outside of the screen.
+pub fn put_char(text_layer: &mut TextLayer, x: usize, y: usize, tlchar: TextLayerChar) {
+    if x < TEXT_COLUMNS && y < TEXT_ROWS {
+        text_layer.insert_text_layer_char_xy(x, y, tlchar);
+    }
+}
+
+/// Writes a string on one row, cut at the edge of the screen.
+pub fn put_str(
+    text_layer: &mut TextLayer,
+    x: usize,
+    y: usize,
+    text: &str,
+    theme: &Theme,
+    focused: bool,
+) {
+    for (index, c) in text.chars().enumerate() {
+        put_char(text_layer, x + index, y, theme.get_char(c, focused));
+    }
+}
+
+/// Draws a box with rounded corners, its title in the top side, and fills the inside.
+/// width and height include the border.
+pub fn draw_box(
+    text_layer: &mut TextLayer,
+    (x, y, width, height): (usize, usize, usize, usize),
+    title: &str,
+    theme: &Theme,
+) {
+    if width < 2 || height < 2 {
+        return;
+    }
+
+    let right = x + width - 1;
+    let bottom = y + height - 1;
+    for row in y..=bottom {
+        for column in x..=right {
+            let c = match (column, row) {
+                (column, row) if column == x && row == y => '\u{256D}',
+                (column, row) if column == right && row == y => '\u{256E}',
+                (column, row) if column == x && row == bottom => '\u{2570}',
+                (column, row) if column == right && row == bottom => '\u{256F}',
+                (_, row) if row == y || row == bottom => '\u{2500}',
+                (column, _) if column == x || column == right => '\u{2502}',
+                _ => ' ',
+            };
+            put_char(text_layer, column, row, theme.get_char(c, false));
+        }
+    }
+
+    if !title.is_empty() {
+        let title: String = title.chars().take(width.saturating_sub(4)).collect();
+        put_str(text_layer, x + 2, y, &title, theme, true);
+    }
+}
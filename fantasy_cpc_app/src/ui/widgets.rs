@@ -0,0 +1,784 @@
+use fantasy_cpc_display_controller::text_layer::text_layer::TextLayer;
+
+use super::widget::{draw_box, put_char, put_str, Response, Theme, Widget};
+use crate::input::{InputState, Key, TextChar};
+
+/// Text that can't get the focus, a title or an explanation.
+pub struct Label {
+    x: usize,
+    y: usize,
+    text: String,
+}
+
+impl Label {
+    pub fn new(x: usize, y: usize, text: &str) -> Label {
+        Label {
+            x,
+            y,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+    }
+}
+
+impl Widget for Label {
+    fn handle_input(&mut self, _inputs: &InputState) -> Response {
+        Response::Ignored
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, _focused: bool) {
+        put_str(text_layer, self.x, self.y, &self.text, theme, false);
+    }
+
+    fn is_focusable(&self) -> bool {
+        false
+    }
+}
+
+/// "< OK >", activated with ENTER or SPACE.
+pub struct Button {
+    x: usize,
+    y: usize,
+    label: String,
+}
+
+impl Button {
+    pub fn new(x: usize, y: usize, label: &str) -> Button {
+        Button {
+            x,
+            y,
+            label: label.to_string(),
+        }
+    }
+}
+
+impl Widget for Button {
+    fn handle_input(&mut self, inputs: &InputState) -> Response {
+        if inputs.key_pressed(Key::Return) || inputs.key_pressed(Key::Space) {
+            return Response::Activated;
+        }
+        Response::Ignored
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, focused: bool) {
+        let text = format!("< {} >", self.label);
+        put_str(text_layer, self.x, self.y, &text, theme, focused);
+    }
+}
+
+/// "[x] label", ticked and unticked with ENTER or SPACE.
+pub struct Checkbox {
+    x: usize,
+    y: usize,
+    label: String,
+    checked: bool,
+}
+
+impl Checkbox {
+    pub fn new(x: usize, y: usize, label: &str, checked: bool) -> Checkbox {
+        Checkbox {
+            x,
+            y,
+            label: label.to_string(),
+            checked,
+        }
+    }
+
+    pub fn get_checked(&self) -> bool {
+        self.checked
+    }
+
+    pub fn set_checked(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+}
+
+impl Widget for Checkbox {
+    fn handle_input(&mut self, inputs: &InputState) -> Response {
+        if inputs.key_pressed(Key::Return) || inputs.key_pressed(Key::Space) {
+            self.checked = !self.checked;
+            return Response::Changed;
+        }
+        Response::Ignored
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, focused: bool) {
+        let tick = if self.checked { 'x' } else { ' ' };
+        put_char(text_layer, self.x, self.y, theme.get_char('[', focused));
+        put_char(
+            text_layer,
+            self.x + 1,
+            self.y,
+            theme.get_highlight_char(tick, focused),
+        );
+        put_char(text_layer, self.x + 2, self.y, theme.get_char(']', focused));
+        put_str(text_layer, self.x + 4, self.y, &self.label, theme, focused);
+    }
+}
+
+/// "label ████▒▒▒▒▒▒ 12.5", a value between min and max changed with LEFT and RIGHT.
+pub struct Slider {
+    x: usize,
+    y: usize,
+    label: String,
+    /// Columns taken by the label, so that the bars of several sliders line up
+    label_width: usize,
+    bar_width: usize,
+    value: f32,
+    min: f32,
+    max: f32,
+    step: f32,
+    /// Number of decimals shown after the bar
+    decimals: usize,
+}
+
+impl Slider {
+    pub fn new(x: usize, y: usize, label: &str, min: f32, max: f32, step: f32) -> Slider {
+        Slider {
+            x,
+            y,
+            label: label.to_string(),
+            label_width: label.chars().count(),
+            bar_width: 10,
+            value: min,
+            min,
+            max,
+            step,
+            decimals: 0,
+        }
+    }
+
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn set_value(&mut self, value: f32) {
+        self.value = value.clamp(self.min, self.max);
+    }
+
+    pub fn set_label_width(&mut self, label_width: usize) {
+        self.label_width = label_width;
+    }
+
+    pub fn set_bar_width(&mut self, bar_width: usize) {
+        self.bar_width = bar_width;
+    }
+
+    pub fn set_decimals(&mut self, decimals: usize) {
+        self.decimals = decimals;
+    }
+
+    pub fn get_position(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    /// Columns taken by the label, the bar and the widest value, to put something after it.
+    pub fn get_width(&self) -> usize {
+        let value_width = [self.min, self.max]
+            .iter()
+            .map(|value| self.format_value(*value).chars().count())
+            .max()
+            .unwrap_or(0);
+        self.label_width + 1 + self.bar_width + 1 + value_width
+    }
+
+    fn format_value(&self, value: f32) -> String {
+        format!("{:.*}", self.decimals, value)
+    }
+}
+
+impl Widget for Slider {
+    fn handle_input(&mut self, inputs: &InputState) -> Response {
+        let previous = self.value;
+
+        if inputs.key_pressed_os(Key::Left) {
+            self.set_value(self.value - self.step);
+        } else if inputs.key_pressed_os(Key::Right) {
+            self.set_value(self.value + self.step);
+        } else if inputs.key_pressed(Key::Home) {
+            self.value = self.min;
+        } else if inputs.key_pressed(Key::End) {
+            self.value = self.max;
+        } else {
+            return Response::Ignored;
+        }
+
+        match self.value == previous {
+            true => Response::Consumed,
+            false => Response::Changed,
+        }
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, focused: bool) {
+        let label = format!("{:<width$}", self.label, width = self.label_width);
+        put_str(text_layer, self.x, self.y, &label, theme, focused);
+
+        let bar_x = self.x + self.label_width + 1;
+        let range = self.max - self.min;
+        let ratio = match range > 0.0 {
+            true => (self.value - self.min) / range,
+            false => 1.0,
+        };
+        let filled = (ratio * self.bar_width as f32).round() as usize;
+        for index in 0..self.bar_width {
+            let c = if index < filled {
+                '\u{2588}'
+            } else {
+                '\u{2592}'
+            };
+            put_char(
+                text_layer,
+                bar_x + index,
+                self.y,
+                theme.get_highlight_char(c, false),
+            );
+        }
+
+        let value = self.format_value(self.value);
+        put_str(
+            text_layer,
+            bar_x + self.bar_width + 1,
+            self.y,
+            &value,
+            theme,
+            focused,
+        );
+    }
+}
+
+/// A one line text input, scrolled when the text is longer than the field.
+/// ENTER activates it, to validate a name or run a search.
+pub struct TextField {
+    x: usize,
+    y: usize,
+    width: usize,
+    max_length: usize,
+    text: Vec<char>,
+    cursor: usize,
+}
+
+impl TextField {
+    pub fn new(x: usize, y: usize, width: usize, max_length: usize) -> TextField {
+        TextField {
+            x,
+            y,
+            width,
+            max_length,
+            text: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    pub fn get_text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().take(self.max_length).collect();
+        self.cursor = self.text.len();
+    }
+
+    /// Index of the first character shown, so that the cursor is always visible.
+    fn get_scroll(&self) -> usize {
+        (self.cursor + 1).saturating_sub(self.width)
+    }
+}
+
+impl Widget for TextField {
+    fn handle_input(&mut self, inputs: &InputState) -> Response {
+        let mut response = Response::Ignored;
+
+        for text_char in inputs.text() {
+            match text_char {
+                TextChar::Char(c) if c.is_control() => (),
+                TextChar::Char(c) => {
+                    // A full field keeps the characters from reaching the form
+                    if response == Response::Ignored {
+                        response = Response::Consumed;
+                    }
+                    if self.text.len() < self.max_length {
+                        self.text.insert(self.cursor, c);
+                        self.cursor += 1;
+                        response = Response::Changed;
+                    }
+                }
+                TextChar::Back => {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                        self.text.remove(self.cursor);
+                        response = Response::Changed;
+                    }
+                }
+            }
+        }
+        if response != Response::Ignored {
+            return response;
+        }
+
+        if inputs.key_pressed_os(Key::Delete) && self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+            return Response::Changed;
+        }
+        if inputs.key_pressed(Key::Return) {
+            return Response::Activated;
+        }
+
+        if inputs.key_pressed_os(Key::Left) {
+            self.cursor = self.cursor.saturating_sub(1);
+        } else if inputs.key_pressed_os(Key::Right) {
+            self.cursor = (self.cursor + 1).min(self.text.len());
+        } else if inputs.key_pressed(Key::Home) {
+            self.cursor = 0;
+        } else if inputs.key_pressed(Key::End) {
+            self.cursor = self.text.len();
+        } else {
+            return Response::Ignored;
+        }
+        Response::Consumed
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, focused: bool) {
+        let scroll = self.get_scroll();
+        for column in 0..self.width {
+            let index = scroll + column;
+            let c = self.text.get(index).copied().unwrap_or('_');
+            let mut tlchar = theme.get_char(c, focused);
+            if focused && index == self.cursor {
+                tlchar.swap = false;
+                tlchar.blink = true;
+            }
+            put_char(text_layer, self.x + column, self.y, tlchar);
+        }
+    }
+}
+
+/// A scrollable list of items, UP, DOWN, PAGE UP and PAGE DOWN move the selection,
+/// ENTER activates the selected item.
+pub struct ListBox {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl ListBox {
+    pub fn new(x: usize, y: usize, width: usize, height: usize, items: Vec<String>) -> ListBox {
+        ListBox {
+            x,
+            y,
+            width,
+            height,
+            items,
+            selected: 0,
+        }
+    }
+
+    pub fn get_items(&self) -> &Vec<String> {
+        &self.items
+    }
+
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn get_selected_item(&self) -> Option<&String> {
+        self.items.get(self.selected)
+    }
+
+    pub fn set_selected(&mut self, selected: usize) {
+        self.selected = selected.min(self.items.len().saturating_sub(1));
+    }
+
+    /// Index of the first item shown, so that the selected one is always visible.
+    fn get_scroll(&self) -> usize {
+        (self.selected + 1).saturating_sub(self.height)
+    }
+}
+
+impl Widget for ListBox {
+    fn handle_input(&mut self, inputs: &InputState) -> Response {
+        if self.items.is_empty() {
+            return Response::Ignored;
+        }
+        if inputs.key_pressed(Key::Return) {
+            return Response::Activated;
+        }
+
+        let last = self.items.len() - 1;
+        let previous = self.selected;
+        if inputs.key_pressed_os(Key::Up) && self.selected > 0 {
+            self.selected -= 1;
+        } else if inputs.key_pressed_os(Key::Down) && self.selected < last {
+            self.selected += 1;
+        } else if inputs.key_pressed_os(Key::PageUp) {
+            self.selected = self.selected.saturating_sub(self.height);
+        } else if inputs.key_pressed_os(Key::PageDown) {
+            self.selected = (self.selected + self.height).min(last);
+        } else if inputs.key_pressed(Key::Home) {
+            self.selected = 0;
+        } else if inputs.key_pressed(Key::End) {
+            self.selected = last;
+        }
+
+        // UP on the first item and DOWN on the last let the form move the focus
+        match self.selected == previous {
+            true => Response::Ignored,
+            false => Response::Changed,
+        }
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, focused: bool) {
+        let scroll = self.get_scroll();
+        for row in 0..self.height {
+            let index = scroll + row;
+            let item = self.items.get(index).map(String::as_str).unwrap_or("");
+            let line: String = format!("{:<width$}", item, width = self.width)
+                .chars()
+                .take(self.width)
+                .collect();
+
+            let selected = index == self.selected && index < self.items.len();
+            for (column, c) in line.chars().enumerate() {
+                let tlchar = match (selected, focused) {
+                    (true, true) => theme.get_char(c, true),
+                    (true, false) => theme.get_highlight_char(c, false),
+                    _ => theme.get_char(c, false),
+                };
+                put_char(text_layer, self.x + column, self.y + row, tlchar);
+            }
+        }
+    }
+}
+
+/// A framed and titled list of choices, like the pause menu of a game.
+/// UP and DOWN go around the list, ENTER activates the selected choice.
+pub struct Menu {
+    x: usize,
+    y: usize,
+    title: String,
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(x: usize, y: usize, title: &str, items: &[&str]) -> Menu {
+        Menu {
+            x,
+            y,
+            title: title.to_string(),
+            items: items.iter().map(|item| item.to_string()).collect(),
+            selected: 0,
+        }
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn set_selected(&mut self, selected: usize) {
+        self.selected = selected.min(self.items.len().saturating_sub(1));
+    }
+
+    /// Width and height of the frame
+    pub fn get_size(&self) -> (usize, usize) {
+        let longest = self
+            .items
+            .iter()
+            .map(|item| item.chars().count())
+            .chain(std::iter::once(self.title.chars().count()))
+            .max()
+            .unwrap_or(0);
+        (longest + 4, self.items.len() + 2)
+    }
+}
+
+impl Widget for Menu {
+    fn handle_input(&mut self, inputs: &InputState) -> Response {
+        if self.items.is_empty() {
+            return Response::Ignored;
+        }
+        if inputs.key_pressed(Key::Return) {
+            return Response::Activated;
+        }
+
+        if inputs.key_pressed_os(Key::Up) {
+            self.selected = match self.selected {
+                0 => self.items.len() - 1,
+                selected => selected - 1,
+            };
+        } else if inputs.key_pressed_os(Key::Down) {
+            self.selected = (self.selected + 1) % self.items.len();
+        } else {
+            return Response::Ignored;
+        }
+        Response::Changed
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, focused: bool) {
+        let (width, height) = self.get_size();
+        draw_box(
+            text_layer,
+            (self.x, self.y, width, height),
+            &self.title,
+            theme,
+        );
+
+        for (index, item) in self.items.iter().enumerate() {
+            let line = format!(" {:<width$} ", item, width = width - 4);
+            let selected = index == self.selected;
+            for (column, c) in line.chars().enumerate() {
+                let tlchar = match (selected, focused) {
+                    (true, true) => theme.get_char(c, true),
+                    (true, false) => theme.get_highlight_char(c, false),
+                    _ => theme.get_char(c, false),
+                };
+                put_char(text_layer, self.x + 1 + column, self.y + 1 + index, tlchar);
+            }
+        }
+    }
+}
+
+/// "███████▒▒▒  70%", shows how far a long task went, between 0.0 and 1.0.
+pub struct ProgressBar {
+    x: usize,
+    y: usize,
+    width: usize,
+    value: f32,
+}
+
+impl ProgressBar {
+    pub fn new(x: usize, y: usize, width: usize) -> ProgressBar {
+        ProgressBar {
+            x,
+            y,
+            width,
+            value: 0.0,
+        }
+    }
+
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn set_value(&mut self, value: f32) {
+        self.value = value.clamp(0.0, 1.0);
+    }
+}
+
+impl Widget for ProgressBar {
+    fn handle_input(&mut self, _inputs: &InputState) -> Response {
+        Response::Ignored
+    }
+
+    fn draw(&self, text_layer: &mut TextLayer, theme: &Theme, _focused: bool) {
+        let filled = (self.value * self.width as f32).round() as usize;
+        for index in 0..self.width {
+            let c = if index < filled {
+                '\u{2588}'
+            } else {
+                '\u{2592}'
+            };
+            put_char(
+                text_layer,
+                self.x + index,
+                self.y,
+                theme.get_highlight_char(c, false),
+            );
+        }
+
+        let percent = format!("{:>4}%", (self.value * 100.0).round() as usize);
+        put_str(
+            text_layer,
+            self.x + self.width,
+            self.y,
+            &percent,
+            theme,
+            false,
+        );
+    }
+
+    fn is_focusable(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Inputs of a frame where only that key was pressed.
+    fn key(key: Key) -> InputState {
+        let mut inputs = InputState::new();
+        inputs.press_key(key);
+        inputs
+    }
+
+    fn text(text: &str) -> InputState {
+        let mut inputs = InputState::new();
+        inputs.type_text(text);
+        inputs
+    }
+
+    fn items(count: usize) -> Vec<String> {
+        (0..count).map(|index| format!("item {}", index)).collect()
+    }
+
+    #[test]
+    fn checkbox_toggles() {
+        let mut checkbox = Checkbox::new(0, 0, "Sound", false);
+        assert_eq!(checkbox.handle_input(&key(Key::Space)), Response::Changed);
+        assert!(checkbox.get_checked());
+        assert_eq!(checkbox.handle_input(&key(Key::Return)), Response::Changed);
+        assert!(!checkbox.get_checked());
+        assert_eq!(checkbox.handle_input(&key(Key::Down)), Response::Ignored);
+    }
+
+    #[test]
+    fn slider_stays_between_min_and_max() {
+        let mut slider = Slider::new(0, 0, "Volume", 0.0, 10.0, 4.0);
+        assert_eq!(slider.handle_input(&key(Key::Left)), Response::Consumed);
+        assert_eq!(slider.handle_input(&key(Key::Right)), Response::Changed);
+        assert_eq!(slider.handle_input(&key(Key::Right)), Response::Changed);
+        assert_eq!(slider.handle_input(&key(Key::Right)), Response::Changed);
+        assert_eq!(slider.get_value(), 10.0);
+        assert_eq!(slider.handle_input(&key(Key::Home)), Response::Changed);
+        assert_eq!(slider.get_value(), 0.0);
+        assert_eq!(slider.handle_input(&key(Key::Up)), Response::Ignored);
+    }
+
+    #[test]
+    fn slider_width_fits_the_widest_value() {
+        let mut slider = Slider::new(5, 5, "Team A", 1.0, 30.0, 1.0);
+        assert_eq!(slider.get_width(), 6 + 1 + 10 + 1 + 2);
+        slider.set_bar_width(4);
+        slider.set_decimals(1);
+        assert_eq!(slider.get_width(), 6 + 1 + 4 + 1 + 4);
+    }
+
+    #[test]
+    fn text_field_stops_at_max_length() {
+        let mut field = TextField::new(0, 0, 4, 6);
+        assert_eq!(field.handle_input(&text("abcdefgh")), Response::Changed);
+        assert_eq!(field.get_text(), "abcdef");
+        assert_eq!(field.handle_input(&text("x")), Response::Consumed);
+
+        // Inserting in the middle of a full field changes nothing either
+        field.handle_input(&key(Key::Home));
+        assert_eq!(field.handle_input(&text("x")), Response::Consumed);
+        assert_eq!(field.get_text(), "abcdef");
+
+        field.set_text("0123456789");
+        assert_eq!(field.get_text(), "012345");
+    }
+
+    #[test]
+    fn text_field_edits_at_the_cursor() {
+        let mut field = TextField::new(0, 0, 10, 20);
+        field.set_text("helo");
+        field.handle_input(&key(Key::Left));
+        field.handle_input(&text("l"));
+        assert_eq!(field.get_text(), "hello");
+
+        field.handle_input(&key(Key::Home));
+        assert_eq!(field.handle_input(&key(Key::Delete)), Response::Changed);
+        assert_eq!(field.handle_input(&text("\u{8}")), Response::Ignored);
+        assert_eq!(field.get_text(), "ello");
+        assert_eq!(field.handle_input(&key(Key::Return)), Response::Activated);
+    }
+
+    #[test]
+    fn text_field_scrolls_to_the_cursor() {
+        let mut field = TextField::new(0, 0, 4, 20);
+        field.set_text("abc");
+        assert_eq!(field.get_scroll(), 0);
+        field.set_text("abcdefgh");
+        // The cursor after the last character is in the last column
+        assert_eq!(field.get_scroll(), 5);
+        field.handle_input(&key(Key::Home));
+        assert_eq!(field.get_scroll(), 0);
+    }
+
+    #[test]
+    fn list_box_pages_stop_at_the_ends() {
+        let mut list = ListBox::new(0, 0, 10, 4, items(10));
+        assert_eq!(list.handle_input(&key(Key::PageUp)), Response::Ignored);
+        assert_eq!(list.handle_input(&key(Key::PageDown)), Response::Changed);
+        assert_eq!(list.get_selected(), 4);
+        list.handle_input(&key(Key::PageDown));
+        list.handle_input(&key(Key::PageDown));
+        assert_eq!(list.get_selected(), 9);
+        assert_eq!(list.get_scroll(), 6);
+        assert_eq!(list.handle_input(&key(Key::PageDown)), Response::Ignored);
+
+        list.handle_input(&key(Key::Up));
+        list.handle_input(&key(Key::PageUp));
+        assert_eq!(list.get_selected(), 4);
+        list.handle_input(&key(Key::PageUp));
+        assert_eq!(list.get_selected(), 0);
+    }
+
+    #[test]
+    fn list_box_lets_the_focus_go_at_the_ends() {
+        let mut list = ListBox::new(0, 0, 10, 4, items(2));
+        assert_eq!(list.handle_input(&key(Key::Up)), Response::Ignored);
+        assert_eq!(list.handle_input(&key(Key::Down)), Response::Changed);
+        assert_eq!(list.handle_input(&key(Key::Down)), Response::Ignored);
+        assert_eq!(list.get_selected_item(), Some(&String::from("item 1")));
+        assert_eq!(list.handle_input(&key(Key::Return)), Response::Activated);
+    }
+
+    #[test]
+    fn list_box_selection_follows_the_items() {
+        let mut list = ListBox::new(0, 0, 10, 4, items(5));
+        list.set_selected(10);
+        assert_eq!(list.get_selected(), 4);
+        list.set_items(items(2));
+        assert_eq!(list.get_selected(), 1);
+        list.set_items(Vec::new());
+        assert_eq!(list.get_selected_item(), None);
+        assert_eq!(list.handle_input(&key(Key::Return)), Response::Ignored);
+    }
+
+    #[test]
+    fn menu_goes_around() {
+        let mut menu = Menu::new(0, 0, "Pause", &["Resume", "Options", "Quit"]);
+        assert_eq!(menu.get_size(), (11, 5));
+        assert_eq!(menu.handle_input(&key(Key::Up)), Response::Changed);
+        assert_eq!(menu.get_selected(), 2);
+        assert_eq!(menu.handle_input(&key(Key::Down)), Response::Changed);
+        assert_eq!(menu.get_selected(), 0);
+        assert_eq!(menu.handle_input(&key(Key::Return)), Response::Activated);
+    }
+
+    #[test]
+    fn progress_bar_is_clamped() {
+        let mut progress_bar = ProgressBar::new(0, 0, 10);
+        progress_bar.set_value(1.5);
+        assert_eq!(progress_bar.get_value(), 1.0);
+        progress_bar.set_value(-1.0);
+        assert_eq!(progress_bar.get_value(), 0.0);
+        assert!(!progress_bar.is_focusable());
+        assert_eq!(
+            progress_bar.handle_input(&key(Key::Return)),
+            Response::Ignored
+        );
+    }
+
+    #[test]
+    fn label_ignores_the_keys() {
+        let mut label = Label::new(0, 0, "Title");
+        label.set_text("Other title");
+        assert!(!label.is_focusable());
+        assert_eq!(label.handle_input(&key(Key::Return)), Response::Ignored);
+    }
+}
@@ -0,0 +1,76 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fantasy_cpc_display_controller::{
+    color_palettes::{BLACK, BLUE, WHITE, YELLOW},
+    config::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
+    DisplayController,
+};
+
+const OUTPUT_FRAME_SIZE: usize = VIRTUAL_WIDTH * VIRTUAL_HEIGHT * 4;
+
+/// A display controller with a screen full of text, like the shell after a long listing.
+fn text_screen() -> DisplayController {
+    let mut dc = DisplayController::new();
+    let text_layer = dc.get_text_layer_mut();
+    let len = text_layer.get_len();
+    for index in 0..len {
+        let c = char::from(b' ' + (index % 95) as u8);
+        text_layer.insert_char(index, c, Some(YELLOW), Some(BLUE), false, false, false);
+    }
+    dc
+}
+
+fn render_benchmark(c: &mut Criterion) {
+    let mut output_frame = vec![0u8; OUTPUT_FRAME_SIZE];
+
+    let mut dc = text_screen();
+    c.bench_function("render static text screen", |b| {
+        b.iter(|| {
+            dc.clear(BLUE);
+            dc.render(black_box(&mut output_frame));
+        })
+    });
+
+    let mut dc = text_screen();
+    c.bench_function("render text screen, all lines dirty", |b| {
+        b.iter(|| {
+            dc.clear(BLUE);
+            dc.invalidate_output_frame();
+            dc.render(black_box(&mut output_frame));
+        })
+    });
+
+    let mut dc = DisplayController::new();
+    let mut color = BLACK;
+    c.bench_function("render changing full frame", |b| {
+        b.iter(|| {
+            color = if color == BLACK { WHITE } else { BLACK };
+            dc.clear(color);
+            dc.render(black_box(&mut output_frame));
+        })
+    });
+
+    let mut dc = text_screen();
+    for line in (0..VIRTUAL_HEIGHT).step_by(8) {
+        dc.add_raster_interrupt(
+            line,
+            Box::new(|line, state| state.set_ink(BLUE, (0, 0, (line % 256) as u8))),
+        );
+    }
+    c.bench_function("render text screen with raster bars", |b| {
+        b.iter(|| {
+            dc.clear(BLUE);
+            dc.render(black_box(&mut output_frame));
+        })
+    });
+
+    let mut dc = text_screen();
+    c.bench_function("render_to_output_frame only", |b| {
+        b.iter(|| {
+            dc.invalidate_output_frame();
+            dc.render_to_output_frame(black_box(&mut output_frame));
+        })
+    });
+}
+
+criterion_group!(benches, render_benchmark);
+criterion_main!(benches);
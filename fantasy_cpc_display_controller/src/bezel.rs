@@ -0,0 +1,182 @@
+use crate::{
+    config::{OVERSCAN_H, OVERSCAN_V, VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
+    DisplayController,
+};
+
+/// Number of masked pixels on each line of the default rounded corners, from the top line down.
+const DEFAULT_CORNER: [usize; 10] = [10, 8, 6, 5, 4, 3, 2, 2, 1, 1];
+
+/// The screen's bezel: the overscan border drawn around the picture and the mask
+/// (rounded corners and/or custom bitmap) that blacks out the pixels hidden behind the
+/// plastic of the monitor.
+/// The mask is precomputed into spans of masked pixels for each line every time a setting
+/// changes, so rendering a frame doesn't have to test every pixel.
+pub struct Bezel {
+    enabled: bool,
+    overscan_h: usize,
+    overscan_v: usize,
+    corner: Vec<usize>,
+    custom_mask: Option<Vec<bool>>,
+    masked_spans: Vec<Vec<(usize, usize)>>,
+}
+
+impl Default for Bezel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bezel {
+    /// The default bezel: OVERSCAN_H and OVERSCAN_V borders and small rounded corners.
+    pub fn new() -> Bezel {
+        let mut bezel = Bezel {
+            enabled: true,
+            overscan_h: OVERSCAN_H,
+            overscan_v: OVERSCAN_V,
+            corner: DEFAULT_CORNER.to_vec(),
+            custom_mask: None,
+            masked_spans: Vec::new(),
+        };
+        bezel.update_mask();
+        bezel
+    }
+
+    /// A disabled bezel, for apps that want to draw on the full frame.
+    pub fn none() -> Bezel {
+        let mut bezel = Bezel::new();
+        bezel.set_enabled(false);
+        bezel
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// When disabled, neither the overscan nor the mask are drawn.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.update_mask();
+    }
+
+    /// Width of the left and right borders and height of the top and bottom borders, in pixels.
+    pub fn get_overscan(&self) -> (usize, usize) {
+        if self.enabled {
+            (self.overscan_h, self.overscan_v)
+        } else {
+            (0, 0)
+        }
+    }
+
+    pub fn set_overscan(&mut self, horizontal: usize, vertical: usize) {
+        self.overscan_h = horizontal.min(VIRTUAL_WIDTH / 2);
+        self.overscan_v = vertical.min(VIRTUAL_HEIGHT / 2);
+    }
+
+    /// Position and size of the picture inside the overscan, in frame coordinates.
+    pub fn get_inner_area(&self) -> (usize, usize, usize, usize) {
+        let (overscan_h, overscan_v) = self.get_overscan();
+        (
+            overscan_h,
+            overscan_v,
+            VIRTUAL_WIDTH - 2 * overscan_h,
+            VIRTUAL_HEIGHT - 2 * overscan_v,
+        )
+    }
+
+    /// Replaces the corners with quarter circles of the specified radius, 0 for square corners.
+    pub fn set_corner_radius(&mut self, radius: usize) {
+        let radius = radius.min(VIRTUAL_HEIGHT / 2);
+        self.corner = (0..radius)
+            .map(|line| {
+                let dy = (radius - line) as f32 - 0.5;
+                let dx = (radius as f32 * radius as f32 - dy * dy).max(0.0).sqrt();
+                (radius as f32 - dx).round() as usize
+            })
+            .collect();
+        self.update_mask();
+    }
+
+    /// Masks every pixel set to true in the bitmap, on top of the corners.
+    /// The bitmap is read line by line and should be VIRTUAL_WIDTH * VIRTUAL_HEIGHT long,
+    /// missing pixels are not masked.
+    pub fn set_custom_mask(&mut self, mask: Option<Vec<bool>>) {
+        self.custom_mask = mask;
+        self.update_mask();
+    }
+
+    /// True if the pixel at x, y in frame coordinates is hidden by the bezel.
+    pub fn is_masked(&self, x: usize, y: usize) -> bool {
+        match self.masked_spans.get(y) {
+            Some(spans) => spans.iter().any(|(start, end)| (*start..*end).contains(&x)),
+            None => false,
+        }
+    }
+
+    /// Spans (start, end excluded) of masked pixels on the line.
+    pub fn get_masked_spans(&self, line: usize) -> &[(usize, usize)] {
+        &self.masked_spans[line]
+    }
+
+    fn update_mask(&mut self) {
+        self.masked_spans = vec![Vec::new(); VIRTUAL_HEIGHT];
+
+        if !self.enabled {
+            return;
+        }
+
+        for (line, spans) in self.masked_spans.iter_mut().enumerate() {
+            let mut masked_line = [false; VIRTUAL_WIDTH];
+
+            let corner_line = if line < self.corner.len() {
+                Some(line)
+            } else if line >= VIRTUAL_HEIGHT - self.corner.len() {
+                Some(VIRTUAL_HEIGHT - line - 1)
+            } else {
+                None
+            };
+
+            if let Some(corner_line) = corner_line {
+                let inset = self.corner[corner_line].min(VIRTUAL_WIDTH / 2);
+                masked_line[..inset].fill(true);
+                masked_line[VIRTUAL_WIDTH - inset..].fill(true);
+            }
+
+            if let Some(mask) = &self.custom_mask {
+                for (x, masked) in masked_line.iter_mut().enumerate() {
+                    *masked |= mask.get(line * VIRTUAL_WIDTH + x).copied().unwrap_or(false);
+                }
+            }
+
+            let mut span_start: Option<usize> = None;
+            for (x, masked) in masked_line.iter().enumerate() {
+                match (*masked, span_start) {
+                    (true, None) => span_start = Some(x),
+                    (false, Some(start)) => {
+                        spans.push((start, x));
+                        span_start = None;
+                    }
+                    _ => (),
+                }
+            }
+            if let Some(start) = span_start {
+                spans.push((start, VIRTUAL_WIDTH));
+            }
+        }
+    }
+}
+
+impl DisplayController {
+    pub fn get_bezel(&self) -> &Bezel {
+        &self.bezel
+    }
+
+    pub fn get_bezel_mut(&mut self) -> &mut Bezel {
+        self.invalidate_output_frame();
+        &mut self.bezel
+    }
+
+    pub fn set_bezel(&mut self, bezel: Bezel) {
+        self.invalidate_output_frame();
+        self.bezel = bezel;
+    }
+}
@@ -66,7 +66,9 @@ pub const MAUVE_RGB: (u8, u8, u8) =  (117, 70, 101);
 pub const DARKPEACH_RGB: (u8, u8, u8) =  (255, 110, 89);
 pub const PEACH_RGB: (u8, u8, u8) =  (255, 157, 129);
 
-pub static mut COLOR_PALETTE: [(u8, u8, u8); PALETE_SIZE] = [BLACK_RGB, DARK_BLUE_RGB, DARK_PURPLE_RGB, DARK_GREEN_RGB, BROWN_RGB, DARK_GREY_RGB, LIGHT_GREY_RGB, WHITE_RGB, 
+pub const DEFAULT_COLOR_PALETTE: [(u8, u8, u8); PALETE_SIZE] = [BLACK_RGB, DARK_BLUE_RGB, DARK_PURPLE_RGB, DARK_GREEN_RGB, BROWN_RGB, DARK_GREY_RGB, LIGHT_GREY_RGB, WHITE_RGB, 
 RED_RGB, ORANGE_RGB, YELLOW_RGB, GREEN_RGB, BLUE_RGB, LAVENDER_RGB, PINK_RGB, LIGHT_PEACH_RGB, 
 BROWNISH_BLACK_RGB, DARKER_BLUE_RGB, DARKER_PURPLE_RGB, BLUE_GREEN_RGB, DARK_BROWN_RGB, DARKER_GREY_RGB, MEDIUM_GREY_RGB, LIGHT_YELLOW_RGB, 
-DARK_RED_RGB, DARK_ORANGE_RGB, LIME_GREEN_RGB, MEDIUM_GREEN_RGB, TRUE_BLUE_RGB, MAUVE_RGB, DARKPEACH_RGB, PEACH_RGB];
\ No newline at end of file
+DARK_RED_RGB, DARK_ORANGE_RGB, LIME_GREEN_RGB, MEDIUM_GREEN_RGB, TRUE_BLUE_RGB, MAUVE_RGB, DARKPEACH_RGB, PEACH_RGB];
+
+pub static mut COLOR_PALETTE: [(u8, u8, u8); PALETE_SIZE] = DEFAULT_COLOR_PALETTE;
@@ -1,23 +1,30 @@
+use bezel::Bezel;
 use color_palettes::*;
 use config::*;
 use fantasy_cpc_clock::Clock;
+use mouse_pointer::MousePointer;
 use rand::Rng;
+use output_frame::OutputFrameCache;
+use raster::{RasterInterrupt, RasterLine};
 use sprite::Sprite;
 use std::{
     ops::{Bound, RangeBounds},
 };
+use viewport::Viewport;
 
 use crate::text_layer::text_layer::TextLayer;
 
+pub mod bezel;
 pub mod shapes_2d;
 pub mod sprite_layer;
 pub mod color_palettes;
 pub mod config;
+pub mod mouse_pointer;
+pub mod output_frame;
+pub mod raster;
 pub mod sprite;
 pub mod text_layer;
-const SUB_PIXEL_COUNT: usize = 4;
-const RENDERED_LINE_LENGTH: usize = VIRTUAL_WIDTH * SUB_PIXEL_COUNT;
-const ROUNDED_CORNER: [usize; 10] = [10, 8, 6, 5, 4, 3, 2, 2, 1, 1];
+pub mod viewport;
 
 /// Contains a list of u8 values corresponding to values from a color palette.
 /// So just one u8 per pixel, R G and B values are retrieved from the palette, No Alpha.
@@ -26,10 +33,20 @@ const ROUNDED_CORNER: [usize; 10] = [10, 8, 6, 5, 4, 3, 2, 2, 1, 1];
 pub struct DisplayController {
     frame: Box<[usize]>,
     overscan: [usize; VIRTUAL_HEIGHT],
+    bezel: Bezel,
     brightness: u8,
     line_scroll_list: [isize; VIRTUAL_HEIGHT],
+    column_scroll_list: [isize; VIRTUAL_WIDTH],
+    vertical_scroll: isize,
     text_layer: TextLayer,
     sprites: Vec<Sprite>,
+    mouse_pointer: MousePointer,
+    viewport: Viewport,
+    viewport_stack: Vec<Viewport>,
+    raster_interrupts: Vec<RasterInterrupt>,
+    raster_lines: [RasterLine; VIRTUAL_HEIGHT],
+    raster_palettes: Vec<[(u8, u8, u8); PALETE_SIZE]>,
+    output_cache: OutputFrameCache,
     clock: Clock,
 }
 
@@ -46,14 +63,50 @@ impl DisplayController {
         DisplayController {
             frame: Box::new([0; VIRTUAL_WIDTH * VIRTUAL_HEIGHT]),
             overscan: [WHITE; VIRTUAL_HEIGHT],
+            bezel: Bezel::new(),
             line_scroll_list: [0; VIRTUAL_HEIGHT],
+            column_scroll_list: [0; VIRTUAL_WIDTH],
+            vertical_scroll: 0,
             brightness: 255,
             text_layer: TextLayer::new(),
             sprites: Vec::new(),
+            mouse_pointer: MousePointer::default(),
+            viewport: Viewport::full_frame(),
+            viewport_stack: Vec::new(),
+            raster_interrupts: Vec::new(),
+            raster_lines: [RasterLine::new(); VIRTUAL_HEIGHT],
+            raster_palettes: vec![unsafe { COLOR_PALETTE }],
+            output_cache: OutputFrameCache::new(),
             clock: Clock::new(),
         }
     }
 
+    /// Puts the display controller back in its power-on state: frame, text layer, sprites,
+    /// scrolls, brightness, overscan, bezel, viewports and raster interrupts are reset and
+    /// the color palette goes back to the default one.
+    /// The mouse pointer stays as it is, it follows the host's mouse.
+    pub fn reset(&mut self) {
+        unsafe {
+            COLOR_PALETTE = DEFAULT_COLOR_PALETTE;
+        }
+        let mouse_pointer = std::mem::take(&mut self.mouse_pointer);
+        *self = DisplayController::new();
+        self.mouse_pointer = mouse_pointer;
+    }
+
+    /// Removes what an app leaves behind for the next frames: raster interrupts, overscan
+    /// colors, viewports and scrolls. Called when another app takes the focus, the frame,
+    /// text and sprites are redrawn by the app anyway.
+    pub fn reset_effects(&mut self) {
+        self.clear_raster_interrupts();
+        self.overscan = [WHITE; VIRTUAL_HEIGHT];
+        self.reset_viewports();
+        self.line_scroll_list = [0; VIRTUAL_HEIGHT];
+        self.column_scroll_list = [0; VIRTUAL_WIDTH];
+        self.vertical_scroll = 0;
+        self.text_layer.set_scroll_offset(0, 0);
+    }
+
     pub fn get_frame_size(&self) -> (usize, usize) {
         (VIRTUAL_WIDTH, VIRTUAL_HEIGHT)
     }
@@ -70,8 +123,10 @@ impl DisplayController {
         &self.frame
     }
 
+    /// Returns the color of the pixel at x, y in the current viewport,
+    /// None if the pixel is outside of the viewport.
     pub fn get_pixel(&mut self, x: usize, y: usize) -> Option<usize> {
-        let index = frame_coord_to_index(x as isize, y as isize);
+        let index = self.viewport.to_frame_index(x as isize, y as isize);
 
         if let Some(i) = index {
             return Some(self.frame[i]);
@@ -80,10 +135,12 @@ impl DisplayController {
         None
     }
 
+    /// Sets the pixel at x, y in the current viewport.
+    /// All the 2D shapes end up calling this one, so they are translated
+    /// and clipped the same way.
     pub fn set_pixel(&mut self, x: isize, y: isize, color: usize) {
-        let index = frame_coord_to_index(x, y);
-        if index.is_some() {
-            self.frame[index.unwrap()] = color
+        if let Some(index) = self.viewport.to_frame_index(x, y) {
+            self.frame[index] = color
         }
     }
 
@@ -97,6 +154,28 @@ impl DisplayController {
         }
     }
 
+    /// Vertical offsets applied to each column of the frame,
+    /// a positive value moves the column down.
+    pub fn get_column_scroll_list(&mut self) -> &mut [isize] {
+        &mut self.column_scroll_list
+    }
+
+    pub fn set_column_scroll_list(&mut self, index: usize, value: isize) {
+        if index < self.column_scroll_list.len() {
+            self.column_scroll_list[index] = value;
+        }
+    }
+
+    pub fn get_vertical_scroll(&self) -> isize {
+        self.vertical_scroll
+    }
+
+    /// Scrolls the whole frame vertically like the CRTC screen start address,
+    /// lines leaving at the bottom come back at the top. A positive value moves the picture down.
+    pub fn set_vertical_scroll(&mut self, value: isize) {
+        self.vertical_scroll = value;
+    }
+
     pub fn set_brightness(&mut self, br: u8) {
         self.brightness = br;
     }
@@ -126,42 +205,30 @@ impl DisplayController {
     }
 
     pub fn overscan_renderer(&mut self) {
-        for (line_count, line) in self.frame.chunks_exact_mut(VIRTUAL_WIDTH).enumerate() {
-            if !(OVERSCAN_V..VIRTUAL_HEIGHT - OVERSCAN_V).contains(&line_count) {
-                line.copy_from_slice(&[self.overscan[line_count]; VIRTUAL_WIDTH]);
-            } else {
-                line.chunks_exact_mut(OVERSCAN_H)
-                    .next()
-                    .unwrap()
-                    .copy_from_slice(&[self.overscan[line_count]; OVERSCAN_H]);
-                line.chunks_exact_mut(OVERSCAN_H)
-                    .last()
-                    .unwrap()
-                    .copy_from_slice(&[self.overscan[line_count]; OVERSCAN_H]);
-            }
+        let (overscan_h, overscan_v) = self.bezel.get_overscan();
+        if overscan_h == 0 && overscan_v == 0 {
+            return;
         }
-    }
 
-    pub fn is_inside_rounded_corner(&self, x: usize, y: usize) -> bool {
-        if y < ROUNDED_CORNER.len()
-            && (x < ROUNDED_CORNER[y] || x >= VIRTUAL_WIDTH - ROUNDED_CORNER[y])
-        {
-            return true;
-        }
+        for (line_count, line) in self.frame.chunks_exact_mut(VIRTUAL_WIDTH).enumerate() {
+            let overscan_color = self.raster_lines[line_count]
+                .overscan_color
+                .unwrap_or(self.overscan[line_count]);
 
-        if y >= VIRTUAL_HEIGHT - ROUNDED_CORNER.len()
-            && (x < ROUNDED_CORNER[VIRTUAL_HEIGHT - y - 1]
-                || x >= VIRTUAL_WIDTH - ROUNDED_CORNER[VIRTUAL_HEIGHT - y - 1])
-        {
-            return true;
+            if !(overscan_v..VIRTUAL_HEIGHT - overscan_v).contains(&line_count) {
+                line.fill(overscan_color);
+            } else {
+                line[..overscan_h].fill(overscan_color);
+                line[VIRTUAL_WIDTH - overscan_h..].fill(overscan_color);
+            }
         }
-
-        false
     }
 
-    /// Sets all the pixels to the specified color of the color palette
-    /// Used to clear the screen between frames or set the background when
-    /// redering only the text layer. Doesn't include the overscan.
+    /// Sets all the pixels of the frame and the overscan to the specified color of the
+    /// color palette. Used to clear the screen between frames or set the background when
+    /// redering only the text layer.
+    /// The viewport is ignored, the whole frame is cleared: use clear_viewport() to only
+    /// fill the current viewport.
     pub fn clear(&mut self, color: usize) {
         self.frame
             .copy_from_slice(&[color; VIRTUAL_WIDTH * VIRTUAL_HEIGHT]);
@@ -197,9 +264,15 @@ impl DisplayController {
         &self.sprites
     }
 
+    /// Combines all the layers into the frame and converts it to RGBA in output_frame.
+    /// Sprites and the text layer are composited here, so they are translated and clipped
+    /// by the viewport that is active when render() is called.
     pub fn render(&mut self, output_frame: &mut [u8]) {
         self.clock.update();
 
+        //Raster interrupts, sets the state of each line before anything is drawn
+        self.run_raster_interrupts();
+
         //Sprites
         self.sprite_layer_renderer();
 
@@ -211,64 +284,64 @@ impl DisplayController {
         //     self.console_renderer();
         // }
 
+        //Frame and column offsets
+        self.apply_vertical_scroll_effect();
+        self.apply_column_scroll_effect();
+
         //Line offset
         self.apply_line_scroll_effect();
 
         //Overscan
         self.overscan_renderer();
 
+        //Mouse pointer, over everything
+        self.mouse_pointer_renderer();
+
         self.render_to_output_frame(output_frame);
+        self.restore_mouse_pointer_background();
 
         self.clock.count_frame();
     }
 
-    fn apply_line_scroll_effect(&mut self) {
-        for (line_index, line_scroll_value) in self.line_scroll_list.into_iter().enumerate() {
-            if line_scroll_value > 0 {
-                self.frame[VIRTUAL_WIDTH * line_index..VIRTUAL_WIDTH * line_index + VIRTUAL_WIDTH]
-                    .rotate_right(line_scroll_value as usize);
-            }
+    fn apply_vertical_scroll_effect(&mut self) {
+        let scroll = self.vertical_scroll.rem_euclid(VIRTUAL_HEIGHT as isize) as usize;
 
-            if line_scroll_value < 0 {
-                self.frame[VIRTUAL_WIDTH * line_index..VIRTUAL_WIDTH * line_index + VIRTUAL_WIDTH]
-                    .rotate_left((-line_scroll_value) as usize);
-            }
+        if scroll > 0 {
+            self.frame.rotate_right(scroll * VIRTUAL_WIDTH);
         }
     }
 
-    pub fn render_to_output_frame(&self, output_frame: &mut [u8]) {
-        let mut rendered_line: [u8; RENDERED_LINE_LENGTH] = [0; RENDERED_LINE_LENGTH];
+    fn apply_column_scroll_effect(&mut self) {
+        let mut column: [usize; VIRTUAL_HEIGHT] = [0; VIRTUAL_HEIGHT];
 
-        for (frame_line_count, frame_line) in self.frame.chunks_exact(VIRTUAL_WIDTH).enumerate() {
-            for frame_pixel in 0..VIRTUAL_WIDTH {
-                let mut rgb = unsafe { COLOR_PALETTE[frame_line[frame_pixel]] };
+        for (column_index, column_scroll_value) in self.column_scroll_list.into_iter().enumerate() {
+            let scroll = column_scroll_value.rem_euclid(VIRTUAL_HEIGHT as isize) as usize;
 
-                if self.is_inside_rounded_corner(frame_pixel, frame_line_count) {
-                    rgb = (0, 0, 0)
-                };
-
-                let screen_pixel_index = SUB_PIXEL_COUNT * frame_pixel;
+            if scroll == 0 {
+                continue;
+            }
 
-                let r = rgb.0;
-                let r_index = screen_pixel_index;
+            for (line_index, pixel) in column.iter_mut().enumerate() {
+                *pixel = self.frame[line_index * VIRTUAL_WIDTH + column_index];
+            }
 
-                let g = rgb.1;
-                let g_index = 1 + screen_pixel_index;
+            column.rotate_right(scroll);
 
-                let b = rgb.2;
-                let b_index = 2 + screen_pixel_index;
+            for (line_index, pixel) in column.iter().enumerate() {
+                self.frame[line_index * VIRTUAL_WIDTH + column_index] = *pixel;
+            }
+        }
+    }
 
-                let a = self.brightness;
-                let a_index = 3 + screen_pixel_index;
+    fn apply_line_scroll_effect(&mut self) {
+        for (line_index, line_scroll_value) in self.line_scroll_list.into_iter().enumerate() {
+            let scroll = (line_scroll_value + self.raster_lines[line_index].line_scroll)
+                .rem_euclid(VIRTUAL_WIDTH as isize);
 
-                rendered_line[r_index] = r;
-                rendered_line[g_index] = g;
-                rendered_line[b_index] = b;
-                rendered_line[a_index] = a;
+            if scroll > 0 {
+                self.frame[VIRTUAL_WIDTH * line_index..VIRTUAL_WIDTH * line_index + VIRTUAL_WIDTH]
+                    .rotate_right(scroll as usize);
             }
-
-            let start = frame_line_count * RENDERED_LINE_LENGTH;
-            output_frame[start..start + RENDERED_LINE_LENGTH].copy_from_slice(&rendered_line);
         }
     }
 
@@ -311,3 +384,40 @@ pub const fn frame_coord_to_index(x: isize, y: isize) -> Option<usize> {
 
     Some(y as usize * VIRTUAL_WIDTH + x as usize)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_effects_leaves_a_plain_display() {
+        let mut display_controller = DisplayController::new();
+        display_controller.add_raster_interrupt(0, Box::new(|_line, _state| {}));
+        display_controller.set_overscan_color(BLACK);
+        display_controller.push_viewport(10, 10, 20, 20);
+        display_controller.set_line_scroll_list(3, 5);
+        display_controller.set_column_scroll_list(4, -2);
+        display_controller.set_vertical_scroll(7);
+        display_controller
+            .get_text_layer_mut()
+            .set_scroll_offset(0, -4);
+
+        display_controller.reset_effects();
+
+        let plain = DisplayController::new();
+        assert!(display_controller.raster_interrupts.is_empty());
+        assert_eq!(display_controller.overscan, plain.overscan);
+        assert_eq!(display_controller.get_viewport(), plain.get_viewport());
+        assert!(display_controller.viewport_stack.is_empty());
+        assert_eq!(display_controller.line_scroll_list, plain.line_scroll_list);
+        assert_eq!(
+            display_controller.column_scroll_list,
+            plain.column_scroll_list
+        );
+        assert_eq!(display_controller.get_vertical_scroll(), 0);
+        assert_eq!(
+            display_controller.get_text_layer().get_scroll_offset(),
+            (0, 0)
+        );
+    }
+}
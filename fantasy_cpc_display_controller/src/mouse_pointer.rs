@@ -0,0 +1,167 @@
+use crate::{
+    config::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
+    DisplayController,
+};
+
+/// The hardware mouse pointer: a sprite of its own, drawn over the layers and the overscan
+/// at the end of the render, in frame coordinates. The viewport and the raster lines don't
+/// change it, and it stays when the apps clear their sprites.
+/// The pixels under it are put back once the frame is converted, so it leaves no trail in
+/// the frame the apps draw on.
+#[derive(Default)]
+pub struct MousePointer {
+    x: isize,
+    y: isize,
+    visible: bool,
+    width: usize,
+    height: usize,
+    /// Pixel of the image that is drawn at the position, the tip of an arrow
+    hotspot: (usize, usize),
+    /// Colors of the palette, 0 is transparent
+    image: Vec<usize>,
+    /// Frame index and color of the pixels it covers during the render
+    covered_pixels: Vec<(usize, usize)>,
+}
+
+impl MousePointer {
+    pub fn get_position(&self) -> (isize, isize) {
+        (self.x, self.y)
+    }
+
+    pub fn set_position(&mut self, x: isize, y: isize) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn set_hotspot(&mut self, x: usize, y: usize) {
+        self.hotspot = (x, y);
+    }
+
+    /// image has width * height colors, line after line.
+    pub fn set_image(&mut self, width: usize, height: usize, image: Vec<usize>) {
+        self.width = width;
+        self.height = height;
+        self.image = image;
+        self.image.resize(width * height, 0);
+    }
+
+    /// Reads an image in the format of the sprites in resources/sprites:
+    /// "width,height," followed by the colors separated by commas.
+    pub fn set_image_from_text(&mut self, text: &str) -> Result<(), String> {
+        let values = text
+            .split(',')
+            .map(|value| value.trim().parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|error| format!("Invalid pointer image: {}", error))?;
+
+        match values.as_slice() {
+            [width, height, image @ ..] if image.len() == width * height => {
+                self.set_image(*width, *height, image.to_vec());
+                Ok(())
+            }
+            _ => Err(String::from("Invalid pointer image: wrong size")),
+        }
+    }
+}
+
+impl DisplayController {
+    pub fn get_mouse_pointer(&self) -> &MousePointer {
+        &self.mouse_pointer
+    }
+
+    pub fn get_mouse_pointer_mut(&mut self) -> &mut MousePointer {
+        &mut self.mouse_pointer
+    }
+
+    /// Draws the pointer into the frame, keeping the pixels it covers for
+    /// restore_mouse_pointer_background().
+    pub(crate) fn mouse_pointer_renderer(&mut self) {
+        let pointer = &mut self.mouse_pointer;
+        pointer.covered_pixels.clear();
+        if !pointer.visible || pointer.width == 0 {
+            return;
+        }
+
+        let left = pointer.x - pointer.hotspot.0 as isize;
+        let top = pointer.y - pointer.hotspot.1 as isize;
+        for (pixel_count, pixel) in pointer.image.iter().enumerate() {
+            if *pixel == 0 {
+                continue;
+            }
+
+            let x = left + (pixel_count % pointer.width) as isize;
+            let y = top + (pixel_count / pointer.width) as isize;
+            if (0..VIRTUAL_WIDTH as isize).contains(&x) && (0..VIRTUAL_HEIGHT as isize).contains(&y)
+            {
+                let index = y as usize * VIRTUAL_WIDTH + x as usize;
+                pointer.covered_pixels.push((index, self.frame[index]));
+                self.frame[index] = *pixel;
+            }
+        }
+    }
+
+    /// Removes the pointer from the frame once it is in the output frame.
+    pub(crate) fn restore_mouse_pointer_background(&mut self) {
+        for (index, color) in self.mouse_pointer.covered_pixels.drain(..) {
+            self.frame[index] = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTER_COLOR: usize = 5;
+
+    fn render(display_controller: &mut DisplayController) -> Vec<u8> {
+        let mut output_frame = vec![0; VIRTUAL_WIDTH * VIRTUAL_HEIGHT * 4];
+        display_controller.render(&mut output_frame);
+        output_frame
+    }
+
+    #[test]
+    fn pointer_leaves_no_trail_in_the_frame() {
+        let mut display_controller = DisplayController::new();
+        let (x, y) = (VIRTUAL_WIDTH / 2, VIRTUAL_HEIGHT / 2);
+        let index = y * VIRTUAL_WIDTH + x;
+        let background = display_controller.get_frame()[index];
+
+        let pointer = display_controller.get_mouse_pointer_mut();
+        pointer.set_image(1, 1, vec![POINTER_COLOR]);
+        pointer.set_position(x as isize, y as isize);
+        pointer.set_visible(true);
+        let with_pointer = render(&mut display_controller);
+        assert_eq!(display_controller.get_frame()[index], background);
+
+        display_controller
+            .get_mouse_pointer_mut()
+            .set_position(x as isize + 10, y as isize);
+        let moved = render(&mut display_controller);
+        assert_eq!(display_controller.get_frame()[index], background);
+        assert_ne!(with_pointer[index * 4..][..4], moved[index * 4..][..4]);
+    }
+
+    #[test]
+    fn pointer_outside_of_the_frame_is_clipped() {
+        let mut display_controller = DisplayController::new();
+        let pointer = display_controller.get_mouse_pointer_mut();
+        pointer.set_image(2, 2, vec![POINTER_COLOR; 4]);
+        pointer.set_position(-1, VIRTUAL_HEIGHT as isize - 1);
+        pointer.set_visible(true);
+        let frame = display_controller.get_frame().to_vec();
+
+        display_controller.mouse_pointer_renderer();
+        assert_eq!(display_controller.mouse_pointer.covered_pixels.len(), 1);
+        display_controller.restore_mouse_pointer_background();
+        assert_eq!(display_controller.get_frame(), frame.as_slice());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    color_palettes::PALETE_SIZE,
+    config::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
+    DisplayController,
+};
+
+const SUB_PIXEL_COUNT: usize = 4;
+const RENDERED_LINE_LENGTH: usize = VIRTUAL_WIDTH * SUB_PIXEL_COUNT;
+
+/// A palette converted to RGBA pixels ready to be copied to the output frame.
+type RgbaPalette = [u32; PALETE_SIZE];
+
+/// What was written to the output frame by the previous render, used to skip
+/// the lines that haven't changed since.
+pub(crate) struct OutputFrameCache {
+    rgba_palettes: Vec<RgbaPalette>,
+    rendered_frame: Box<[usize]>,
+    rendered_palettes: Vec<RgbaPalette>,
+    valid: bool,
+}
+
+impl OutputFrameCache {
+    pub fn new() -> OutputFrameCache {
+        OutputFrameCache {
+            rgba_palettes: Vec::new(),
+            rendered_frame: vec![0; VIRTUAL_WIDTH * VIRTUAL_HEIGHT].into_boxed_slice(),
+            rendered_palettes: vec![[0; PALETE_SIZE]; VIRTUAL_HEIGHT],
+            valid: false,
+        }
+    }
+}
+
+/// Packs a color in a u32 whose bytes in memory are R, G, B and A, in that order.
+fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    u32::from_ne_bytes([r, g, b, a])
+}
+
+impl DisplayController {
+    /// Converts the frame to RGBA and writes it to the output frame, applying the
+    /// palette of each line and the bezel mask.
+    /// Lines that are the same as in the previous call, with the same palette, are not
+    /// rewritten, so the same output frame must be passed every time. Call
+    /// invalidate_output_frame() if the output frame was changed or replaced.
+    pub fn render_to_output_frame(&mut self, output_frame: &mut [u8]) {
+        let brightness = self.brightness;
+        let mask_pixel = rgba(0, 0, 0, brightness).to_ne_bytes();
+        let cache = &mut self.output_cache;
+
+        cache.rgba_palettes.clear();
+        cache
+            .rgba_palettes
+            .extend(self.raster_palettes.iter().map(|palette| {
+                palette.map(|(r, g, b)| rgba(r, g, b, brightness))
+            }));
+
+        for (line_index, (frame_line, output_line)) in self
+            .frame
+            .chunks_exact(VIRTUAL_WIDTH)
+            .zip(output_frame.chunks_exact_mut(RENDERED_LINE_LENGTH))
+            .enumerate()
+        {
+            let palette = &cache.rgba_palettes[self.raster_lines[line_index].palette];
+            let rendered_line =
+                &mut cache.rendered_frame[line_index * VIRTUAL_WIDTH..(line_index + 1) * VIRTUAL_WIDTH];
+
+            if cache.valid
+                && rendered_line == frame_line
+                && cache.rendered_palettes[line_index] == *palette
+            {
+                continue;
+            }
+
+            rendered_line.copy_from_slice(frame_line);
+            cache.rendered_palettes[line_index] = *palette;
+
+            for (output_pixel, color) in output_line
+                .chunks_exact_mut(SUB_PIXEL_COUNT)
+                .zip(frame_line)
+            {
+                output_pixel.copy_from_slice(&palette[*color].to_ne_bytes());
+            }
+
+            for (span_start, span_end) in self.bezel.get_masked_spans(line_index) {
+                for output_pixel in output_line
+                    [span_start * SUB_PIXEL_COUNT..span_end * SUB_PIXEL_COUNT]
+                    .chunks_exact_mut(SUB_PIXEL_COUNT)
+                {
+                    output_pixel.copy_from_slice(&mask_pixel);
+                }
+            }
+        }
+
+        cache.valid = true;
+    }
+
+    /// Forces the next render to rewrite every line of the output frame.
+    pub fn invalidate_output_frame(&mut self) {
+        self.output_cache.valid = false;
+    }
+}
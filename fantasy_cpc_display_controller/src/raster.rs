@@ -0,0 +1,127 @@
+use crate::{
+    color_palettes::{COLOR_PALETTE, PALETE_SIZE},
+    config::VIRTUAL_HEIGHT,
+    DisplayController,
+};
+
+/// The part of the video chip's state that raster interrupts can change mid-frame.
+/// A change made by an interrupt applies from the line it is registered on down to
+/// the bottom of the frame, or until another interrupt changes it again.
+#[derive(Clone, Copy)]
+pub struct RasterState {
+    /// Horizontal offset added to the line scroll of the lines below.
+    pub line_scroll: isize,
+    /// Color palette used to convert the lines below to RGB.
+    pub palette: [(u8, u8, u8); PALETE_SIZE],
+    /// Overrides the overscan color of the lines below if not None.
+    pub overscan_color: Option<usize>,
+    /// Shows or hides the text layer on the lines below.
+    pub text_layer_enabled: bool,
+    /// Shows or hides the sprites on the lines below.
+    pub sprites_enabled: bool,
+}
+
+impl RasterState {
+    fn new() -> RasterState {
+        RasterState {
+            line_scroll: 0,
+            palette: unsafe { COLOR_PALETTE },
+            overscan_color: None,
+            text_layer_enabled: true,
+            sprites_enabled: true,
+        }
+    }
+
+    /// Changes one entry of the palette, like the INK command of the CPC.
+    pub fn set_ink(&mut self, color: usize, rgb: (u8, u8, u8)) {
+        if color < PALETE_SIZE {
+            self.palette[color] = rgb;
+        }
+    }
+}
+
+/// Called when the beam reaches the line the interrupt is registered on.
+/// Receives the line number and the raster state to modify for the lines below.
+pub type RasterCallback = Box<dyn FnMut(usize, &mut RasterState)>;
+
+pub(crate) struct RasterInterrupt {
+    line: usize,
+    callback: RasterCallback,
+}
+
+/// The raster state as it was when the beam drew a given line.
+#[derive(Clone, Copy)]
+pub(crate) struct RasterLine {
+    pub line_scroll: isize,
+    pub overscan_color: Option<usize>,
+    pub text_layer_enabled: bool,
+    pub sprites_enabled: bool,
+    pub palette: usize,
+}
+
+impl RasterLine {
+    pub const fn new() -> RasterLine {
+        RasterLine {
+            line_scroll: 0,
+            overscan_color: None,
+            text_layer_enabled: true,
+            sprites_enabled: true,
+            palette: 0,
+        }
+    }
+}
+
+impl DisplayController {
+    /// Registers a callback executed during render() when the beam reaches the line.
+    /// Interrupts registered on the same line are executed in the order they were added.
+    /// Lines outside of the frame are ignored.
+    pub fn add_raster_interrupt(&mut self, line: usize, callback: RasterCallback) {
+        if line >= VIRTUAL_HEIGHT {
+            return;
+        }
+
+        let index = self
+            .raster_interrupts
+            .partition_point(|interrupt| interrupt.line <= line);
+        self.raster_interrupts
+            .insert(index, RasterInterrupt { line, callback });
+    }
+
+    /// Removes all the raster interrupts.
+    pub fn clear_raster_interrupts(&mut self) {
+        self.raster_interrupts.clear();
+    }
+
+    /// Runs the raster interrupts from top to bottom and records, for each line,
+    /// the state the beam will use to draw it.
+    pub(crate) fn run_raster_interrupts(&mut self) {
+        let mut state = RasterState::new();
+        self.raster_palettes.clear();
+        self.raster_palettes.push(state.palette);
+
+        if self.raster_interrupts.is_empty() {
+            self.raster_lines = [RasterLine::new(); VIRTUAL_HEIGHT];
+            return;
+        }
+
+        let mut interrupts = self.raster_interrupts.iter_mut().peekable();
+
+        for (line_index, raster_line) in self.raster_lines.iter_mut().enumerate() {
+            while let Some(interrupt) = interrupts.next_if(|interrupt| interrupt.line == line_index) {
+                (interrupt.callback)(line_index, &mut state);
+            }
+
+            if *self.raster_palettes.last().unwrap() != state.palette {
+                self.raster_palettes.push(state.palette);
+            }
+
+            *raster_line = RasterLine {
+                line_scroll: state.line_scroll,
+                overscan_color: state.overscan_color,
+                text_layer_enabled: state.text_layer_enabled,
+                sprites_enabled: state.sprites_enabled,
+                palette: self.raster_palettes.len() - 1,
+            };
+        }
+    }
+}
@@ -1,32 +1,27 @@
-use crate::{DisplayController, config::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH}, frame_coord_to_index};
+use crate::{config::VIRTUAL_WIDTH, DisplayController};
 
 impl DisplayController {
     /// Gets all the sprites listed in the sprite vector and renders them at the right place in the
-    /// the virtual frame buffer
+    /// the virtual frame buffer.
+    /// Sprite positions are relative to the current viewport, and sprites are clipped by it.
     pub fn sprite_layer_renderer(&mut self) {
         for sprite in &self.sprites {
-            let mut pixel_count = 0;
-            let mut sprite_line_count = 0;
+            let sprite_width = sprite.size.size().0;
 
-            let global_offset = frame_coord_to_index(sprite.pos_x, sprite.pos_y);
-
-            if global_offset.is_some() {
-                for pixel in &sprite.image {
-                    let virtual_fb_offset =
-                        (global_offset.unwrap() + VIRTUAL_WIDTH * sprite_line_count + pixel_count)
-                            % (VIRTUAL_WIDTH * VIRTUAL_HEIGHT);
+            for (pixel_count, pixel) in sprite.image.iter().enumerate() {
+                if *pixel == 0 {
+                    continue;
+                }
 
-                    if *pixel != 0 {
-                        self.frame[virtual_fb_offset] = *pixel;
-                    }
+                let x = sprite.pos_x + (pixel_count % sprite_width) as isize;
+                let y = sprite.pos_y + (pixel_count / sprite_width) as isize;
 
-                    pixel_count += 1;
-                    if pixel_count == sprite.size.size().0 {
-                        pixel_count = 0;
-                        sprite_line_count += 1;
+                if let Some(index) = self.viewport.to_frame_index(x, y) {
+                    if self.raster_lines[index / VIRTUAL_WIDTH].sprites_enabled {
+                        self.frame[index] = *pixel;
                     }
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
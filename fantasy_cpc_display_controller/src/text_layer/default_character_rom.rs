@@ -1,9 +1,19 @@
 use crate::text_layer::character_rom_trait::FantasyCpc8by8CharacterRomTrait;
 
+/// Drawn for the characters that aren't in the ROM: a question mark cut out of a block
+const REPLACEMENT_GLYPH: [u8; 8] = [
+    0b01000010,
+    0b10011001,
+    0b11111001,
+    0b11110011,
+    0b11100111,
+    0b11111111,
+    0b11100111,
+    0b01111110,
+];
+
 /// The character "ROM", returns the 8x8 pixels drawing corresponding to each character.
 /// Inspired by the AMSTRAD character set.
-
-
 pub struct FantasyCpcAmstradCharacterSet;
 
 impl FantasyCpc8by8CharacterRomTrait for FantasyCpcAmstradCharacterSet {
@@ -15,7 +25,7 @@ impl FantasyCpc8by8CharacterRomTrait for FantasyCpcAmstradCharacterSet {
     fn get_char_table() -> String {
         String::from(" !\"#$%&', ()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\, ]\
     ↑_`abcdefghijklmnopqrstuvwxyz{|}~▒ ▘▗▀▖▌▞▛▗▚▐▜▄▙▟█▪╵╶╰╷│╭├╴╯─┴╮┤┬┼^´¨£©¶§‘¼½¾±÷¬¿¡αβγδεθλμπ\
-    σφψχωΣΩ🮠🮡🮣🮢🮧🮥🮦🮤🮨🮩🮮╳╱╲🮕▒▔▕▁▏◤◥◢◣🮎🮍🮏🮌🮜🮝🮞🮟☺☹♣♦♥♠○●□■♂♀♩♪☼🙭⭡⭣⭠⭢▲▼▶◀🯆🯅🯇🯈⤋⍏⭥⭤")
+    σφψχωΣΩ🮠🮡🮣🮢🮧🮥🮦🮤🮨🮩🮮╳╱╲🮕▒▔▕▁▏◤◥◢◣🮎🮍🮏🮌🮜🮝🮞🮟☺☹♣♦♥♠○●□■♂♀♩♪☼🙭⭡⭣⭠⭢▲▼▶◀🯆🯅🯇🯈⤋⍏⭥⭤\u{FFFD}")
     }
 
     fn get_char(c: char) -> [u8;8] {
@@ -2258,16 +2268,45 @@ impl FantasyCpc8by8CharacterRomTrait for FantasyCpcAmstradCharacterSet {
                 0b00000000,
                 0b00000000,
             ],
-            _ => [
-                0b00000000,
-                0b00000000,
-                0b00000000,
-                0b00000000,
-                0b00000000,
-                0b00000000,
-                0b00000000,
-                0b00000000,
-            ],
+            unicode::REPLACEMENT_CHAR => REPLACEMENT_GLYPH,
+            // Accented letters are drawn from the letter without its accent
+            _ => match unicode::compose::decompose(c) {
+                Some((letter, accent)) => with_accent(Self::get_char(letter), accent),
+                None => REPLACEMENT_GLYPH,
+            },
         }
     }
-}
\ No newline at end of file
+}
+
+/// Adds an accent to the drawing of a letter. Lowercase letters leave the first two lines
+/// empty for it, capitals are moved one line down and get a flatter accent.
+/// The cedilla goes on the last line, empty for the letters that take one.
+fn with_accent(letter: [u8; 8], accent: char) -> [u8; 8] {
+    // Over a lowercase letter, then over a capital
+    let (lowercase_mark, capital_mark) = match accent {
+        '´' => ([0b00001100, 0b00011000], 0b00001100),
+        'ˋ' => ([0b00110000, 0b00011000], 0b00110000),
+        'ˆ' => ([0b00011000, 0b00100100], 0b00111100),
+        '¨' => ([0b00000000, 0b01100110], 0b01100110),
+        '˜' => ([0b00110010, 0b01001100], 0b00110110),
+        '¸' if letter[7] == 0 => {
+            let mut glyph = letter;
+            glyph[7] = 0b00011000;
+            return glyph;
+        }
+        _ => return REPLACEMENT_GLYPH,
+    };
+
+    let mut glyph = letter;
+    if letter[1] == 0 {
+        // Replaces the dot of the i
+        glyph[0] = lowercase_mark[0];
+        glyph[1] = lowercase_mark[1];
+    } else if letter[7] == 0 {
+        glyph[0] = capital_mark;
+        glyph[1..].copy_from_slice(&letter[..7]);
+    } else {
+        return REPLACEMENT_GLYPH;
+    }
+    glyph
+}
@@ -1,24 +1,55 @@
-use crate::{DisplayController, config::VIRTUAL_WIDTH, text_layer::{character_rom_trait::FantasyCpc8by8CharacterRomTrait, default_character_rom::FantasyCpcAmstradCharacterSet, text_layer::{TextLayerChar, text_index_to_frame_coord}}};
+use crate::{DisplayController, config::{TEXT_COLUMNS, TEXT_ROWS, VIRTUAL_HEIGHT, VIRTUAL_WIDTH}, text_layer::{character_rom_trait::FantasyCpc8by8CharacterRomTrait, default_character_rom::FantasyCpcAmstradCharacterSet, text_layer::{TextLayerChar, text_coord_to_frame_coord, text_index_to_frame_coord}}};
 
 impl DisplayController {
+    /// Draws the text layer in the frame buffer. Text is positioned relative to the
+    /// current viewport, and characters falling outside of it are clipped.
+    /// When the text layer has a scroll offset, characters are also clipped to the text area.
     pub fn text_layer_renderer(&mut self) {
+        let (offset_x, offset_y) = self.text_layer.get_scroll_offset();
+        let scrolling = offset_x != 0 || offset_y != 0;
+
+        if scrolling {
+            let (area_x, area_y) = text_coord_to_frame_coord(0, 0);
+            self.push_clip_rect(
+                area_x as isize,
+                area_y as isize,
+                TEXT_COLUMNS * 8,
+                TEXT_ROWS * 8,
+            );
+        }
+
         for char_counter in 0..self.text_layer.get_len() {
             let frame_coord = text_index_to_frame_coord(char_counter);
 
             let text_layer_char = self.text_layer.get_char_map()[char_counter];
 
             if let Some(char_struct) = text_layer_char {
-                self.text_layer_char_renderer(&char_struct, frame_coord.0, frame_coord.1);
+                let (x_pos, y_pos) = self.viewport.to_frame_coord(
+                    frame_coord.0 as isize + offset_x,
+                    frame_coord.1 as isize + offset_y,
+                );
+                self.text_layer_char_renderer(&char_struct, x_pos, y_pos);
             }
         }
+
+        if scrolling {
+            self.pop_viewport();
+        }
     }
 
     fn text_layer_char_renderer(
         &mut self,
         text_layer_char: &TextLayerChar,
-        frame_x_pos: usize,
-        frame_y_pos: usize,
+        frame_x_pos: isize,
+        frame_y_pos: isize,
     ) {
+        let fully_visible = self.viewport.contains(frame_x_pos, frame_y_pos)
+            && self.viewport.contains(frame_x_pos + 7, frame_y_pos + 7);
+
+        if !fully_visible && !self.viewport.overlaps(frame_x_pos, frame_y_pos, 8, 8) {
+            return;
+        }
+
         let char = text_layer_char.c;
         let char_color = text_layer_char.color;
         let bck_color = text_layer_char.bkg_color;
@@ -46,9 +77,23 @@ impl DisplayController {
             let row = pic[row_count];
             let mut mask: u8 = 128;
 
+            let line = frame_y_pos + row_count as isize;
+            if !(0..VIRTUAL_HEIGHT as isize).contains(&line)
+                || !self.raster_lines[line as usize].text_layer_enabled
+            {
+                continue;
+            }
+
             for col_count in 0..8 {
-                let virtual_frame_buffer_pos =
-                    frame_x_pos + col_count + (frame_y_pos + row_count) * VIRTUAL_WIDTH;
+                let pixel_x = frame_x_pos + col_count;
+                let pixel_y = frame_y_pos + row_count as isize;
+
+                if !fully_visible && !self.viewport.contains(pixel_x, pixel_y) {
+                    mask >>= 1;
+                    continue;
+                }
+
+                let virtual_frame_buffer_pos = pixel_x as usize + pixel_y as usize * VIRTUAL_WIDTH;
 
                 if shadowed {
                     let shadow_mask: u8 = if row_count % 2 == 0 {
@@ -3,7 +3,7 @@ use crate::{config::*, color_palettes::*};
 const DEFAULT_COLOR: usize = WHITE;
 const DEFAULT_BKG_COLOR: usize = BLACK;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct TextLayerChar {
     pub c: char,
     pub color: usize,
@@ -17,6 +17,7 @@ pub struct TextLayer {
     pub default_color: usize,
     pub default_bkg_color: usize,
     char_map: [Option<TextLayerChar>; TEXT_COLUMNS * TEXT_ROWS],
+    scroll_offset: (isize, isize),
 }
 
 impl Default for TextLayer {
@@ -30,12 +31,14 @@ impl TextLayer {
         TextLayer {
             default_color: DEFAULT_COLOR,
             default_bkg_color: DEFAULT_BKG_COLOR,
-            char_map: [None; TEXT_COLUMNS * TEXT_ROWS]
+            char_map: [None; TEXT_COLUMNS * TEXT_ROWS],
+            scroll_offset: (0, 0),
         }
     }
 
     pub fn clear(&mut self) {
         self.char_map = [None; TEXT_COLUMNS * TEXT_ROWS];
+        self.scroll_offset = (0, 0);
     }
 
     /// Returns the dimensions in columns and rowns of the text layer map.
@@ -56,6 +59,16 @@ impl TextLayer {
          &mut self.char_map
     }
 
+    /// Pixel offset applied to the whole text layer when it is rendered,
+    /// used for smooth scrolling. Characters pushed outside of the text area are clipped.
+    pub fn get_scroll_offset(&self) -> (isize, isize) {
+         self.scroll_offset
+    }
+
+    pub fn set_scroll_offset(&mut self, x: isize, y: isize) {
+         self.scroll_offset = (x, y);
+    }
+
     /// Inserts a TextLayerChar in the char_map at the specified index.
     /// This is the mother of all text inserting functions, all 
     /// the insert or push functions end up calling this one. 
@@ -123,4 +136,18 @@ pub const fn text_index_to_frame_coord(index: usize) -> (usize, usize) {
     let x = horizontal_border + (index % TEXT_COLUMNS) * 8;
     let y = vertical_border + ((index / TEXT_COLUMNS) % TEXT_ROWS) * 8;
     (x, y)
-}
\ No newline at end of file
+}
+/// Character under a pixel of the frame, None if the pixel is in the border around the text.
+pub const fn frame_coord_to_text_coord(x: usize, y: usize) -> Option<(usize, usize)> {
+    let horizontal_border: usize = (VIRTUAL_WIDTH - TEXT_COLUMNS * 8) / 2;
+    let vertical_border: usize = (VIRTUAL_HEIGHT - TEXT_ROWS * 8) / 2;
+    if x < horizontal_border || y < vertical_border {
+        return None;
+    }
+    let column = (x - horizontal_border) / 8;
+    let row = (y - vertical_border) / 8;
+    if column >= TEXT_COLUMNS || row >= TEXT_ROWS {
+        return None;
+    }
+    Some((column, row))
+}
@@ -0,0 +1,266 @@
+use crate::{
+    config::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
+    DisplayController,
+};
+
+/// A clipping rectangle with its own origin, in absolute frame coordinates.
+/// Every drawing operation of the DisplayController goes through the current
+/// viewport: coordinates are translated by the origin, and pixels falling
+/// outside of the clipping rectangle are discarded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    clip_x: isize,
+    clip_y: isize,
+    clip_width: usize,
+    clip_height: usize,
+    origin_x: isize,
+    origin_y: isize,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::full_frame()
+    }
+}
+
+impl Viewport {
+    /// The viewport covering the whole frame, overscan included, with its origin
+    /// at the top left corner of the frame.
+    pub const fn full_frame() -> Viewport {
+        Viewport {
+            clip_x: 0,
+            clip_y: 0,
+            clip_width: VIRTUAL_WIDTH,
+            clip_height: VIRTUAL_HEIGHT,
+            origin_x: 0,
+            origin_y: 0,
+        }
+    }
+
+    /// Top-Left corner of the clipping rectangle, in frame coordinates.
+    pub fn get_position(&self) -> (isize, isize) {
+        (self.clip_x, self.clip_y)
+    }
+
+    /// Width and height of the clipping rectangle, in pixels.
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.clip_width, self.clip_height)
+    }
+
+    /// Position of the (0, 0) point of the viewport, in frame coordinates.
+    pub fn get_origin(&self) -> (isize, isize) {
+        (self.origin_x, self.origin_y)
+    }
+
+    /// True if the point, in frame coordinates, is inside the clipping rectangle.
+    pub fn contains(&self, frame_x: isize, frame_y: isize) -> bool {
+        frame_x >= self.clip_x
+            && frame_y >= self.clip_y
+            && frame_x < self.clip_x + self.clip_width as isize
+            && frame_y < self.clip_y + self.clip_height as isize
+    }
+
+    /// True if the rectangle, in frame coordinates, is at least partially
+    /// inside the clipping rectangle.
+    pub fn overlaps(&self, frame_x: isize, frame_y: isize, width: usize, height: usize) -> bool {
+        frame_x < self.clip_x + self.clip_width as isize
+            && frame_y < self.clip_y + self.clip_height as isize
+            && frame_x + width as isize > self.clip_x
+            && frame_y + height as isize > self.clip_y
+    }
+
+    /// Converts viewport coordinates to frame coordinates.
+    pub fn to_frame_coord(&self, x: isize, y: isize) -> (isize, isize) {
+        (x + self.origin_x, y + self.origin_y)
+    }
+
+    /// Converts viewport coordinates to an index in the frame buffer,
+    /// None if the point is clipped.
+    pub fn to_frame_index(&self, x: isize, y: isize) -> Option<usize> {
+        let (frame_x, frame_y) = self.to_frame_coord(x, y);
+
+        if self.contains(frame_x, frame_y) {
+            return Some(frame_y as usize * VIRTUAL_WIDTH + frame_x as usize);
+        }
+
+        None
+    }
+
+    /// Returns a copy of the viewport with its clipping rectangle reduced to its
+    /// intersection with the rectangle passed as parameter (in viewport coordinates).
+    /// The origin is left untouched.
+    fn clipped_to(&self, x: isize, y: isize, width: usize, height: usize) -> Viewport {
+        let (frame_x, frame_y) = self.to_frame_coord(x, y);
+
+        let left = frame_x.max(self.clip_x);
+        let top = frame_y.max(self.clip_y);
+        let right = (frame_x + width as isize).min(self.clip_x + self.clip_width as isize);
+        let bottom = (frame_y + height as isize).min(self.clip_y + self.clip_height as isize);
+
+        if right <= left || bottom <= top {
+            return Viewport {
+                clip_width: 0,
+                clip_height: 0,
+                ..*self
+            };
+        }
+
+        Viewport {
+            clip_x: left,
+            clip_y: top,
+            clip_width: (right - left) as usize,
+            clip_height: (bottom - top) as usize,
+            origin_x: self.origin_x,
+            origin_y: self.origin_y,
+        }
+    }
+}
+
+impl DisplayController {
+    /// Returns the viewport currently applied to all the drawing operations.
+    pub fn get_viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+
+    /// Opens a window in the current viewport: the new viewport is clipped to the
+    /// rectangle (x, y, width, height), expressed in the coordinates of the current viewport,
+    /// and its origin is moved to the top left corner of that rectangle.
+    /// Call pop_viewport() to go back to the previous one.
+    pub fn push_viewport(&mut self, x: isize, y: isize, width: usize, height: usize) {
+        let mut viewport = self.viewport.clipped_to(x, y, width, height);
+        (viewport.origin_x, viewport.origin_y) = self.viewport.to_frame_coord(x, y);
+        self.viewport_stack.push(self.viewport);
+        self.viewport = viewport;
+    }
+
+    /// Same as push_viewport() but keeps the current origin, only the clipping
+    /// rectangle is reduced.
+    pub fn push_clip_rect(&mut self, x: isize, y: isize, width: usize, height: usize) {
+        let viewport = self.viewport.clipped_to(x, y, width, height);
+        self.viewport_stack.push(self.viewport);
+        self.viewport = viewport;
+    }
+
+    /// Restores the viewport that was active before the last push_viewport()
+    /// or push_clip_rect(). Does nothing if the stack is empty.
+    pub fn pop_viewport(&mut self) {
+        if let Some(viewport) = self.viewport_stack.pop() {
+            self.viewport = viewport;
+        }
+    }
+
+    /// Empties the viewport stack, drawing operations go back to the full frame.
+    pub fn reset_viewports(&mut self) {
+        self.viewport_stack.clear();
+        self.viewport = Viewport::full_frame();
+    }
+
+    /// Fills the clipping rectangle of the current viewport with the specified color.
+    pub fn clear_viewport(&mut self, color: usize) {
+        let (x, y) = self.viewport.get_position();
+        let (width, height) = self.viewport.get_size();
+
+        for line in y as usize..y as usize + height {
+            let start = line * VIRTUAL_WIDTH + x as usize;
+            self.frame[start..start + width].fill(color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLOR: usize = 3;
+
+    #[test]
+    fn clipped_to_is_the_intersection() {
+        let viewport = Viewport::full_frame().clipped_to(-10, 20, 30, 40);
+        assert_eq!(viewport.get_position(), (0, 20));
+        assert_eq!(viewport.get_size(), (20, 40));
+        assert_eq!(viewport.get_origin(), (0, 0));
+
+        let viewport = viewport.clipped_to(5, 50, 100, 100);
+        assert_eq!(viewport.get_position(), (5, 50));
+        assert_eq!(viewport.get_size(), (15, 10));
+    }
+
+    #[test]
+    fn empty_intersections_clip_everything() {
+        let full_frame = Viewport::full_frame();
+        for (x, y) in [(VIRTUAL_WIDTH as isize, 0), (-10, 0), (0, -10)] {
+            let viewport = full_frame.clipped_to(x, y, 10, 10);
+            assert_eq!(viewport.get_size(), (0, 0));
+            assert!(!viewport.contains(0, 0));
+            assert!(!viewport.overlaps(0, 0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT));
+            assert_eq!(viewport.to_frame_index(0, 0), None);
+        }
+        assert_eq!(full_frame.clipped_to(0, 0, 0, 10).get_size(), (0, 0));
+    }
+
+    #[test]
+    fn nested_viewports_add_their_origins() {
+        let mut display_controller = DisplayController::new();
+        display_controller.push_viewport(10, 20, 100, 100);
+        display_controller.push_viewport(5, 5, 200, 10);
+        let viewport = *display_controller.get_viewport();
+        assert_eq!(viewport.get_origin(), (15, 25));
+        assert_eq!(viewport.get_position(), (15, 25));
+        assert_eq!(viewport.get_size(), (95, 10));
+
+        display_controller.push_clip_rect(-5, 0, 10, 10);
+        assert_eq!(display_controller.get_viewport().get_origin(), (15, 25));
+        assert_eq!(display_controller.get_viewport().get_size(), (5, 10));
+
+        display_controller.pop_viewport();
+        assert_eq!(*display_controller.get_viewport(), viewport);
+        display_controller.pop_viewport();
+        display_controller.pop_viewport();
+        assert_eq!(*display_controller.get_viewport(), Viewport::full_frame());
+        display_controller.pop_viewport();
+        assert_eq!(*display_controller.get_viewport(), Viewport::full_frame());
+    }
+
+    #[test]
+    fn pixels_are_translated_and_clipped() {
+        let mut display_controller = DisplayController::new();
+        display_controller.push_viewport(10, 20, 4, 3);
+
+        display_controller.set_pixel(0, 0, COLOR);
+        assert_eq!(
+            display_controller.get_frame()[20 * VIRTUAL_WIDTH + 10],
+            COLOR
+        );
+        assert_eq!(display_controller.get_pixel(0, 0), Some(COLOR));
+        assert_eq!(display_controller.get_pixel(3, 2), Some(0));
+
+        let frame = display_controller.get_frame().to_vec();
+        for (x, y) in [(-1, 0), (0, -1), (4, 0), (0, 3), (1000, 1000)] {
+            display_controller.set_pixel(x, y, COLOR + 1);
+        }
+        assert_eq!(display_controller.get_frame(), frame.as_slice());
+        assert_eq!(display_controller.get_pixel(4, 0), None);
+        assert_eq!(display_controller.get_pixel(0, 3), None);
+    }
+
+    #[test]
+    fn clear_viewport_fills_the_viewport_and_clear_the_frame() {
+        let mut display_controller = DisplayController::new();
+        display_controller.push_viewport(10, 20, 4, 3);
+        display_controller.clear_viewport(COLOR);
+
+        let filled = display_controller
+            .get_frame()
+            .iter()
+            .filter(|color| **color == COLOR)
+            .count();
+        assert_eq!(filled, 4 * 3);
+
+        // clear() ignores the viewport
+        display_controller.clear(COLOR + 1);
+        assert!(display_controller
+            .get_frame()
+            .iter()
+            .all(|color| *color == COLOR + 1));
+    }
+}
@@ -0,0 +1,827 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{path_to_string, DirEntry, Drive, DrivePath, VfsError};
+
+const STANDARD_SIGNATURE: &[u8] = b"MV - CPC";
+const EXTENDED_SIGNATURE: &[u8] = b"EXTENDED CPC DSK File";
+const DISC_INFO_SIZE: usize = 0x100;
+const TRACK_INFO_SIZE: usize = 0x100;
+
+/// AMSDOS works with 512 bytes sectors, 1K blocks and 128 bytes records
+const SECTOR_SIZE: usize = 512;
+const BLOCK_SIZE: usize = 1024;
+const RECORD_SIZE: usize = 128;
+/// The directory takes the first 2 blocks: 64 entries of 32 bytes
+const DIRECTORY_BLOCKS: usize = 2;
+const DIRECTORY_ENTRIES: usize = 64;
+const DIRECTORY_ENTRY_SIZE: usize = 32;
+/// Each directory entry (extent) points to up to 16 blocks, 128 records
+const BLOCKS_PER_EXTENT: usize = 16;
+const RECORDS_PER_EXTENT: usize = BLOCKS_PER_EXTENT * BLOCK_SIZE / RECORD_SIZE;
+
+const DELETED: u8 = 0xE5;
+const EOF_MARKER: u8 = 0x1A;
+const AMSDOS_HEADER_SIZE: usize = 128;
+const AMSDOS_BINARY_FILE: u8 = 2;
+
+/// The disc formats AMSDOS knows, recognized by the ID of the first sector of a track.
+struct Format {
+    first_sector_id: u8,
+    sectors_per_track: usize,
+    reserved_tracks: usize,
+}
+
+const DATA_FORMAT: Format = Format {
+    first_sector_id: 0xC1,
+    sectors_per_track: 9,
+    reserved_tracks: 0,
+};
+
+const SYSTEM_FORMAT: Format = Format {
+    first_sector_id: 0x41,
+    sectors_per_track: 9,
+    reserved_tracks: 2,
+};
+
+const IBM_FORMAT: Format = Format {
+    first_sector_id: 0x01,
+    sectors_per_track: 8,
+    reserved_tracks: 1,
+};
+
+/// Offset and size in the image of the sectors of side 0, by track and sector ID
+type SectorMap = HashMap<(usize, u8), (usize, usize)>;
+
+/// One directory entry of a file, a file bigger than 16K has several.
+struct Extent {
+    /// Index of the entry in the directory
+    index: usize,
+    number: usize,
+    records: usize,
+    blocks: Vec<u8>,
+}
+
+/// A drive stored in an Amstrad CPC .dsk disc image, standard or extended,
+/// in AMSDOS DATA, SYSTEM or IBM format.
+/// AMSDOS has no directories, only files of user 0 are visible, and names are 8.3 in capitals.
+/// System files are hidden from the listing like CAT does, read-only files can't be changed.
+///
+/// Text files are written the way AMSDOS does, padded with EOF characters (&1A).
+/// Other files get an AMSDOS header so that their exact length is kept.
+/// Headers are removed when reading.
+pub struct DskDrive {
+    image_path: PathBuf,
+    image: Vec<u8>,
+    format: &'static Format,
+    sectors: SectorMap,
+    block_count: usize,
+}
+
+impl DskDrive {
+    pub fn open<P: AsRef<Path>>(image_path: P) -> Result<DskDrive, VfsError> {
+        let image = fs::read(image_path.as_ref())?;
+        let sectors = parse_image(&image)?;
+
+        let first_sector_id = sectors
+            .keys()
+            .filter(|(track, _)| *track == 0)
+            .map(|(_, id)| *id)
+            .min()
+            .ok_or_else(unknown_format)?;
+        let format = match first_sector_id {
+            0xC1 => &DATA_FORMAT,
+            0x41 => &SYSTEM_FORMAT,
+            0x01 => &IBM_FORMAT,
+            _ => return Err(unknown_format()),
+        };
+
+        let track_count = sectors
+            .keys()
+            .map(|(track, _)| track + 1)
+            .max()
+            .unwrap_or(0);
+        let data_sectors =
+            track_count.saturating_sub(format.reserved_tracks) * format.sectors_per_track;
+        let block_count = (data_sectors * SECTOR_SIZE / BLOCK_SIZE).min(256);
+
+        if block_count <= DIRECTORY_BLOCKS {
+            return Err(unknown_format());
+        }
+
+        Ok(DskDrive {
+            image_path: image_path.as_ref().to_path_buf(),
+            image,
+            format,
+            sectors,
+            block_count,
+        })
+    }
+
+    fn save(&self) -> Result<(), VfsError> {
+        fs::write(&self.image_path, &self.image)?;
+        Ok(())
+    }
+
+    /// Where a 512 bytes sector of the data area is in the image.
+    fn sector_offset(&self, logical_sector: usize) -> Result<usize, VfsError> {
+        let track = self.format.reserved_tracks + logical_sector / self.format.sectors_per_track;
+        let id =
+            self.format.first_sector_id + (logical_sector % self.format.sectors_per_track) as u8;
+        match self.sectors.get(&(track, id)) {
+            Some((offset, size)) if *size >= SECTOR_SIZE => Ok(*offset),
+            _ => Err(VfsError::Io(format!(
+                "sector {} of track {} missing",
+                id, track
+            ))),
+        }
+    }
+
+    fn read_block(&self, block: usize) -> Result<Vec<u8>, VfsError> {
+        let mut data = Vec::with_capacity(BLOCK_SIZE);
+        for sector in 0..BLOCK_SIZE / SECTOR_SIZE {
+            let offset = self.sector_offset(block * BLOCK_SIZE / SECTOR_SIZE + sector)?;
+            data.extend_from_slice(&self.image[offset..offset + SECTOR_SIZE]);
+        }
+        Ok(data)
+    }
+
+    fn write_block(&mut self, block: usize, data: &[u8]) -> Result<(), VfsError> {
+        for sector in 0..BLOCK_SIZE / SECTOR_SIZE {
+            let offset = self.sector_offset(block * BLOCK_SIZE / SECTOR_SIZE + sector)?;
+            let start = sector * SECTOR_SIZE;
+            let end = (start + SECTOR_SIZE).min(data.len());
+            let sector_data = &mut self.image[offset..offset + SECTOR_SIZE];
+            sector_data.fill(EOF_MARKER);
+            if start < end {
+                sector_data[..end - start].copy_from_slice(&data[start..end]);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_directory(&self) -> Result<Vec<u8>, VfsError> {
+        let mut directory = Vec::new();
+        for block in 0..DIRECTORY_BLOCKS {
+            directory.extend(self.read_block(block)?);
+        }
+        Ok(directory)
+    }
+
+    fn write_directory(&mut self, directory: &[u8]) -> Result<(), VfsError> {
+        for block in 0..DIRECTORY_BLOCKS {
+            self.write_block(
+                block,
+                &directory[block * BLOCK_SIZE..(block + 1) * BLOCK_SIZE],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Directory entries of the file, in order.
+    fn find_extents(&self, directory: &[u8], name: &[u8; 11]) -> Vec<Extent> {
+        let mut extents: Vec<Extent> = directory
+            .chunks(DIRECTORY_ENTRY_SIZE)
+            .enumerate()
+            .filter(|(_, entry)| entry[0] == 0 && entry_name(entry) == *name)
+            .map(|(index, entry)| Extent {
+                index,
+                number: entry[12] as usize + 32 * entry[14] as usize,
+                records: (entry[15] as usize).min(RECORDS_PER_EXTENT),
+                blocks: entry[16..32]
+                    .iter()
+                    .copied()
+                    .filter(|block| *block != 0)
+                    .collect(),
+            })
+            .collect();
+        extents.sort_by_key(|extent| extent.number);
+        extents
+    }
+
+    /// Blocks used by the directory and every file, of all users.
+    fn used_blocks(&self, directory: &[u8]) -> Vec<bool> {
+        let mut used = vec![false; self.block_count];
+        used[..DIRECTORY_BLOCKS].fill(true);
+        for entry in directory.chunks(DIRECTORY_ENTRY_SIZE) {
+            if entry[0] != DELETED {
+                for block in &entry[16..32] {
+                    if let Some(used) = used.get_mut(*block as usize) {
+                        *used = true;
+                    }
+                }
+            }
+        }
+        used
+    }
+
+    /// Name of an existing file of user 0, fails if the file is read-only.
+    fn writable_file(&self, directory: &[u8], path: &DrivePath) -> Result<[u8; 11], VfsError> {
+        let name = amsdos_name(path)?;
+        let entry = directory
+            .chunks(DIRECTORY_ENTRY_SIZE)
+            .find(|entry| entry[0] == 0 && entry_name(entry) == name)
+            .ok_or_else(|| VfsError::NotFound(path_to_string(path)))?;
+        match entry[9] & 0x80 != 0 {
+            true => Err(VfsError::ReadOnly),
+            false => Ok(name),
+        }
+    }
+}
+
+fn unknown_format() -> VfsError {
+    VfsError::Io(String::from("unknown disc format"))
+}
+
+/// Finds the sectors of side 0 in a standard or extended DSK image.
+fn parse_image(image: &[u8]) -> Result<SectorMap, VfsError> {
+    let corrupted = || VfsError::Io(String::from("corrupted disc image"));
+
+    if image.len() < DISC_INFO_SIZE {
+        return Err(corrupted());
+    }
+    let extended = image.starts_with(EXTENDED_SIGNATURE);
+    if !extended && !image.starts_with(STANDARD_SIGNATURE) {
+        return Err(corrupted());
+    }
+
+    let track_count = image[0x30] as usize;
+    let side_count = (image[0x31] as usize).max(1);
+    let standard_track_size = u16::from_le_bytes([image[0x32], image[0x33]]) as usize;
+
+    let mut sectors = HashMap::new();
+    let mut track_offset = DISC_INFO_SIZE;
+
+    for index in 0..track_count * side_count {
+        let track_size = match extended {
+            true => *image.get(0x34 + index).ok_or_else(corrupted)? as usize * 256,
+            false => standard_track_size,
+        };
+        // Unformatted track in an extended image
+        if track_size == 0 {
+            continue;
+        }
+
+        let track_info = image
+            .get(track_offset..track_offset + TRACK_INFO_SIZE)
+            .ok_or_else(corrupted)?;
+        if !track_info.starts_with(b"Track-Info") {
+            return Err(corrupted());
+        }
+
+        let track = track_info[0x10] as usize;
+        let side = track_info[0x11];
+        let sector_count = (track_info[0x15] as usize).min(29);
+        let mut sector_offset = track_offset + TRACK_INFO_SIZE;
+
+        for sector in 0..sector_count {
+            let sector_info = &track_info[0x18 + sector * 8..0x18 + sector * 8 + 8];
+            let id = sector_info[2];
+            let size = match extended {
+                true => u16::from_le_bytes([sector_info[6], sector_info[7]]) as usize,
+                false => 128 << (track_info[0x14].min(6)),
+            };
+            if sector_offset + size > image.len() {
+                return Err(corrupted());
+            }
+            if side == 0 {
+                sectors.insert((track, id), (sector_offset, size));
+            }
+            sector_offset += size;
+        }
+
+        track_offset += track_size;
+    }
+
+    Ok(sectors)
+}
+
+/// Name and extension of a directory entry, without the attribute bits.
+fn entry_name(entry: &[u8]) -> [u8; 11] {
+    let mut name = [0; 11];
+    for (index, c) in entry[1..12].iter().enumerate() {
+        name[index] = c & 0x7F;
+    }
+    name
+}
+
+/// "GAME.BAS" from a directory entry name.
+fn display_name(name: &[u8; 11]) -> String {
+    let base = String::from_utf8_lossy(&name[0..8]).trim_end().to_string();
+    let extension = String::from_utf8_lossy(&name[8..11]).trim_end().to_string();
+    match extension.is_empty() {
+        true => base,
+        false => format!("{}.{}", base, extension),
+    }
+}
+
+/// The 8.3 name of a file at the root of the disc, padded with spaces.
+fn amsdos_name(path: &DrivePath) -> Result<[u8; 11], VfsError> {
+    let invalid = || VfsError::InvalidPath(path_to_string(path));
+
+    if path.len() != 1 {
+        return Err(invalid());
+    }
+    let (base, extension) = match path[0].rsplit_once('.') {
+        Some((base, extension)) => (base, extension),
+        None => (path[0].as_str(), ""),
+    };
+    let valid_char = |c: char| c.is_ascii_graphic() && !"<>.,;:=?*[]|\"".contains(c);
+    if base.is_empty()
+        || base.len() > 8
+        || extension.len() > 3
+        || !base.chars().chain(extension.chars()).all(valid_char)
+    {
+        return Err(invalid());
+    }
+
+    let mut name = [b' '; 11];
+    for (index, c) in base.bytes().enumerate() {
+        name[index] = c.to_ascii_uppercase();
+    }
+    for (index, c) in extension.bytes().enumerate() {
+        name[8 + index] = c.to_ascii_uppercase();
+    }
+    Ok(name)
+}
+
+/// Length of the file if it starts with a valid AMSDOS header.
+fn header_length(data: &[u8]) -> Option<usize> {
+    let header = data.get(0..AMSDOS_HEADER_SIZE)?;
+    let sum: u16 = header[0..67].iter().map(|byte| *byte as u16).sum();
+    let checksum = u16::from_le_bytes([header[67], header[68]]);
+    match sum != 0 && sum == checksum {
+        true => Some(u32::from_le_bytes([header[64], header[65], header[66], 0]) as usize),
+        false => None,
+    }
+}
+
+fn amsdos_header(name: &[u8; 11], length: usize) -> Vec<u8> {
+    let mut header = vec![0; AMSDOS_HEADER_SIZE];
+    header[1..12].copy_from_slice(name);
+    header[18] = AMSDOS_BINARY_FILE;
+    let short_length = (length.min(0xFFFF) as u16).to_le_bytes();
+    header[19..21].copy_from_slice(&short_length);
+    header[24..26].copy_from_slice(&short_length);
+    header[64..67].copy_from_slice(&(length as u32).to_le_bytes()[0..3]);
+    let sum: u16 = header[0..67].iter().map(|byte| *byte as u16).sum();
+    header[67..69].copy_from_slice(&sum.to_le_bytes());
+    header
+}
+
+impl Drive for DskDrive {
+    fn read_file(&self, path: &DrivePath) -> Result<Vec<u8>, VfsError> {
+        if path.is_empty() {
+            return Err(VfsError::IsADirectory(path_to_string(path)));
+        }
+        let name = amsdos_name(path).map_err(|_| VfsError::NotFound(path_to_string(path)))?;
+        let directory = self.read_directory()?;
+        let extents = self.find_extents(&directory, &name);
+        if extents.is_empty() {
+            return Err(VfsError::NotFound(path_to_string(path)));
+        }
+
+        let mut data = Vec::new();
+        for extent in &extents {
+            let mut extent_data = Vec::new();
+            for block in &extent.blocks {
+                extent_data.extend(self.read_block(*block as usize)?);
+            }
+            extent_data.truncate(extent.records * RECORD_SIZE);
+            data.extend(extent_data);
+        }
+
+        match header_length(&data) {
+            Some(length) => {
+                data.drain(0..AMSDOS_HEADER_SIZE);
+                data.truncate(length);
+            }
+            None => {
+                // Text file, the last record is padded with EOF characters
+                let last_record = data.len().saturating_sub(RECORD_SIZE);
+                if let Some(eof) = data[last_record..].iter().position(|c| *c == EOF_MARKER) {
+                    data.truncate(last_record + eof);
+                }
+            }
+        }
+
+        Ok(data)
+    }
+
+    fn write_file(&mut self, path: &DrivePath, data: &[u8]) -> Result<(), VfsError> {
+        if path.is_empty() {
+            return Err(VfsError::IsADirectory(path_to_string(path)));
+        }
+        let name = amsdos_name(path)?;
+        let mut directory = self.read_directory()?;
+
+        if self.is_dir(path).is_some() {
+            self.writable_file(&directory, path)?;
+        }
+
+        let content = match data.contains(&EOF_MARKER) {
+            true => [amsdos_header(&name, data.len()), data.to_vec()].concat(),
+            false => data.to_vec(),
+        };
+        let records = content.len().div_ceil(RECORD_SIZE);
+        let block_count = content.len().div_ceil(BLOCK_SIZE);
+        let extent_count = block_count.div_ceil(BLOCKS_PER_EXTENT).max(1);
+
+        // Frees the blocks of the old file before looking for space
+        let old_entries: Vec<usize> = self
+            .find_extents(&directory, &name)
+            .iter()
+            .map(|extent| extent.index)
+            .collect();
+        for index in &old_entries {
+            directory[index * DIRECTORY_ENTRY_SIZE] = DELETED;
+        }
+
+        let used = self.used_blocks(&directory);
+        let free_blocks: Vec<usize> = (0..self.block_count)
+            .filter(|block| !used[*block])
+            .collect();
+        let free_entries: Vec<usize> = (0..DIRECTORY_ENTRIES)
+            .filter(|index| directory[index * DIRECTORY_ENTRY_SIZE] == DELETED)
+            .collect();
+        if free_blocks.len() < block_count || free_entries.len() < extent_count {
+            return Err(VfsError::DiscFull);
+        }
+
+        for (block_index, block) in free_blocks.iter().take(block_count).enumerate() {
+            let start = block_index * BLOCK_SIZE;
+            let end = (start + BLOCK_SIZE).min(content.len());
+            self.write_block(*block, &content[start..end])?;
+        }
+
+        for (extent_number, entry_index) in free_entries.iter().take(extent_count).enumerate() {
+            let entry_offset = entry_index * DIRECTORY_ENTRY_SIZE;
+            let entry = &mut directory[entry_offset..entry_offset + DIRECTORY_ENTRY_SIZE];
+            let extent_records = records
+                .saturating_sub(extent_number * RECORDS_PER_EXTENT)
+                .min(RECORDS_PER_EXTENT);
+
+            entry.fill(0);
+            entry[1..12].copy_from_slice(&name);
+            entry[12] = (extent_number % 32) as u8;
+            entry[14] = (extent_number / 32) as u8;
+            entry[15] = extent_records as u8;
+            for (slot, block) in free_blocks
+                .iter()
+                .skip(extent_number * BLOCKS_PER_EXTENT)
+                .take(block_count.saturating_sub(extent_number * BLOCKS_PER_EXTENT))
+                .take(BLOCKS_PER_EXTENT)
+                .enumerate()
+            {
+                entry[16 + slot] = *block as u8;
+            }
+        }
+
+        self.write_directory(&directory)?;
+        self.save()
+    }
+
+    fn read_dir(&self, path: &DrivePath) -> Result<Vec<DirEntry>, VfsError> {
+        if !path.is_empty() {
+            return match self.is_dir(path) {
+                Some(_) => Err(VfsError::NotADirectory(path_to_string(path))),
+                None => Err(VfsError::NotFound(path_to_string(path))),
+            };
+        }
+
+        let directory = self.read_directory()?;
+        let mut names: Vec<[u8; 11]> = directory
+            .chunks(DIRECTORY_ENTRY_SIZE)
+            .filter(|entry| entry[0] == 0 && entry[10] & 0x80 == 0)
+            .map(entry_name)
+            .collect();
+        names.sort();
+        names.dedup();
+
+        Ok(names
+            .iter()
+            .map(|name| {
+                let size = self
+                    .find_extents(&directory, name)
+                    .iter()
+                    .map(|extent| (extent.records * RECORD_SIZE) as u64)
+                    .sum();
+                DirEntry {
+                    name: display_name(name),
+                    is_dir: false,
+                    size,
+                }
+            })
+            .collect())
+    }
+
+    fn create_dir(&mut self, path: &DrivePath) -> Result<(), VfsError> {
+        Err(VfsError::InvalidPath(path_to_string(path)))
+    }
+
+    fn remove(&mut self, path: &DrivePath) -> Result<(), VfsError> {
+        let mut directory = self.read_directory()?;
+        let name = self.writable_file(&directory, path)?;
+        for extent in self.find_extents(&directory, &name) {
+            directory[extent.index * DIRECTORY_ENTRY_SIZE] = DELETED;
+        }
+        self.write_directory(&directory)?;
+        self.save()
+    }
+
+    fn rename(&mut self, from: &DrivePath, to: &DrivePath) -> Result<(), VfsError> {
+        let mut directory = self.read_directory()?;
+        let from_name = self.writable_file(&directory, from)?;
+        let to_name = amsdos_name(to)?;
+        if !self.find_extents(&directory, &to_name).is_empty() {
+            return Err(VfsError::AlreadyExists(path_to_string(to)));
+        }
+
+        for extent in self.find_extents(&directory, &from_name) {
+            let entry_offset = extent.index * DIRECTORY_ENTRY_SIZE;
+            for (index, c) in to_name.iter().enumerate() {
+                // Keeps the attribute bits
+                let byte = &mut directory[entry_offset + 1 + index];
+                *byte = (*byte & 0x80) | c;
+            }
+        }
+        self.write_directory(&directory)?;
+        self.save()
+    }
+
+    fn is_dir(&self, path: &DrivePath) -> Option<bool> {
+        if path.is_empty() {
+            return Some(true);
+        }
+        let name = amsdos_name(path).ok()?;
+        let directory = self.read_directory().ok()?;
+        match self.find_extents(&directory, &name).is_empty() {
+            true => None,
+            false => Some(false),
+        }
+    }
+
+    fn free_space(&self) -> Option<u64> {
+        let directory = self.read_directory().ok()?;
+        let free_blocks = self
+            .used_blocks(&directory)
+            .iter()
+            .filter(|used| !**used)
+            .count();
+        Some((free_blocks * BLOCK_SIZE) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACKS: usize = 40;
+    const TRACK_SIZE: usize = TRACK_INFO_SIZE + DATA_FORMAT.sectors_per_track * SECTOR_SIZE;
+
+    /// A freshly formatted standard image in DATA format, one side.
+    fn data_image() -> Vec<u8> {
+        let mut image = vec![0; DISC_INFO_SIZE];
+        image[..STANDARD_SIGNATURE.len()].copy_from_slice(STANDARD_SIGNATURE);
+        image[0x30] = TRACKS as u8;
+        image[0x31] = 1;
+        image[0x32..0x34].copy_from_slice(&(TRACK_SIZE as u16).to_le_bytes());
+
+        for track in 0..TRACKS {
+            let mut track_info = vec![0; TRACK_INFO_SIZE];
+            track_info[..10].copy_from_slice(b"Track-Info");
+            track_info[0x10] = track as u8;
+            track_info[0x14] = 2;
+            track_info[0x15] = DATA_FORMAT.sectors_per_track as u8;
+            for sector in 0..DATA_FORMAT.sectors_per_track {
+                let sector_info = &mut track_info[0x18 + sector * 8..0x18 + sector * 8 + 8];
+                sector_info[0] = track as u8;
+                sector_info[2] = DATA_FORMAT.first_sector_id + sector as u8;
+                sector_info[3] = 2;
+            }
+            image.extend(track_info);
+            image.extend(vec![DELETED; DATA_FORMAT.sectors_per_track * SECTOR_SIZE]);
+        }
+        image
+    }
+
+    /// An image file in the temporary directory, removed at the end of the test.
+    struct TempImage(PathBuf);
+
+    impl TempImage {
+        fn new(name: &str, image: &[u8]) -> TempImage {
+            let path = std::env::temp_dir().join(format!(
+                "fantasy_cpc_vfs_{}_{}.dsk",
+                std::process::id(),
+                name
+            ));
+            fs::write(&path, image).unwrap();
+            TempImage(path)
+        }
+
+        fn open(&self) -> Result<DskDrive, VfsError> {
+            DskDrive::open(&self.0)
+        }
+    }
+
+    impl Drop for TempImage {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn path(name: &str) -> Vec<String> {
+        vec![name.to_string()]
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let temp_image = TempImage::new("round_trip", &data_image());
+        let mut drive = temp_image.open().unwrap();
+        let free_space = drive.free_space().unwrap();
+        assert_eq!(
+            free_space,
+            ((TRACKS * 9 / 2 - DIRECTORY_BLOCKS) * BLOCK_SIZE) as u64
+        );
+
+        let binary: Vec<u8> = (0..=255).collect();
+        drive.write_file(&path("hello.txt"), b"hello").unwrap();
+        drive.write_file(&path("DATA.BIN"), &binary).unwrap();
+        assert_eq!(drive.read_file(&path("HELLO.TXT")).unwrap(), b"hello");
+        assert_eq!(drive.read_file(&path("data.bin")).unwrap(), binary);
+        assert_eq!(drive.free_space(), Some(free_space - 2 * BLOCK_SIZE as u64));
+
+        drive.rename(&path("hello.txt"), &path("bye.txt")).unwrap();
+        assert_eq!(
+            drive.read_file(&path("hello.txt")),
+            Err(VfsError::NotFound(String::from("/hello.txt")))
+        );
+        assert_eq!(
+            drive.rename(&path("bye.txt"), &path("data.bin")),
+            Err(VfsError::AlreadyExists(String::from("/data.bin")))
+        );
+
+        drive.remove(&path("data.bin")).unwrap();
+        assert_eq!(drive.is_dir(&path("data.bin")), None);
+        assert_eq!(drive.free_space(), Some(free_space - BLOCK_SIZE as u64));
+
+        // Every change is saved in the image file
+        let drive = temp_image.open().unwrap();
+        let entries = drive.read_dir(&[]).unwrap();
+        assert_eq!(
+            entries,
+            vec![DirEntry {
+                name: String::from("BYE.TXT"),
+                is_dir: false,
+                size: RECORD_SIZE as u64,
+            }]
+        );
+        assert_eq!(drive.read_file(&path("bye.txt")).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn files_are_replaced() {
+        let temp_image = TempImage::new("replace", &data_image());
+        let mut drive = temp_image.open().unwrap();
+        drive.write_file(&path("a"), &[1; 3000]).unwrap();
+        drive.write_file(&path("a"), &[2; 10]).unwrap();
+        assert_eq!(drive.read_file(&path("a")).unwrap(), vec![2; 10]);
+        assert_eq!(drive.read_dir(&[]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn big_files_take_several_extents() {
+        let temp_image = TempImage::new("extents", &data_image());
+        let mut drive = temp_image.open().unwrap();
+        let data: Vec<u8> = (0..40 * BLOCK_SIZE)
+            .map(|index| (index % 251) as u8)
+            .collect();
+        drive.write_file(&path("big.bin"), &data).unwrap();
+
+        let directory = drive.read_directory().unwrap();
+        let extents = drive.find_extents(&directory, &amsdos_name(&path("big.bin")).unwrap());
+        let numbers: Vec<usize> = extents.iter().map(|extent| extent.number).collect();
+        assert_eq!(numbers, vec![0, 1, 2]);
+
+        let drive = temp_image.open().unwrap();
+        assert_eq!(drive.read_file(&path("big.bin")).unwrap(), data);
+    }
+
+    #[test]
+    fn full_disc_and_full_directory() {
+        let temp_image = TempImage::new("full", &data_image());
+        let mut drive = temp_image.open().unwrap();
+        let too_big = vec![0; drive.free_space().unwrap() as usize + 1];
+        assert_eq!(
+            drive.write_file(&path("big"), &too_big),
+            Err(VfsError::DiscFull)
+        );
+
+        for index in 0..DIRECTORY_ENTRIES {
+            drive
+                .write_file(&path(&format!("F{}", index)), b"x")
+                .unwrap();
+        }
+        assert_eq!(
+            drive.write_file(&path("last.txt"), b"x"),
+            Err(VfsError::DiscFull)
+        );
+        assert_eq!(drive.read_dir(&[]).unwrap().len(), DIRECTORY_ENTRIES);
+    }
+
+    #[test]
+    fn amsdos_names_are_checked() {
+        let temp_image = TempImage::new("names", &data_image());
+        let mut drive = temp_image.open().unwrap();
+        for name in ["toolongname", "a.long", "a:b", ".bas"] {
+            assert_eq!(
+                drive.write_file(&path(name), b"x"),
+                Err(VfsError::InvalidPath(format!("/{}", name)))
+            );
+        }
+        let in_directory = vec![String::from("dir"), String::from("a")];
+        assert!(drive.write_file(&in_directory, b"x").is_err());
+        assert!(drive.create_dir(&path("dir")).is_err());
+    }
+
+    #[test]
+    fn corrupted_images_are_errors() {
+        let image = data_image();
+        let corrupted = |name: &str, image: &[u8]| {
+            let temp_image = TempImage::new(name, image);
+            assert!(
+                matches!(temp_image.open(), Err(VfsError::Io(_))),
+                "{}",
+                name
+            );
+        };
+
+        corrupted("empty", &[]);
+        corrupted("disc_info", &image[..DISC_INFO_SIZE - 1]);
+        corrupted(
+            "first_track",
+            &image[..DISC_INFO_SIZE + TRACK_INFO_SIZE / 2],
+        );
+        corrupted("last_sector", &image[..image.len() - 1]);
+
+        let mut signature = image.clone();
+        signature[0] = b'X';
+        corrupted("signature", &signature);
+
+        let mut track_info = image.clone();
+        track_info[DISC_INFO_SIZE] = b'X';
+        corrupted("track_info", &track_info);
+
+        let mut no_tracks = image.clone();
+        no_tracks[0x30] = 0;
+        corrupted("no_tracks", &no_tracks);
+
+        let mut unknown_format = image.clone();
+        for sector in 0..DATA_FORMAT.sectors_per_track {
+            unknown_format[DISC_INFO_SIZE + 0x18 + sector * 8 + 2] = 0x80 + sector as u8;
+        }
+        corrupted("unknown_format", &unknown_format);
+
+        let mut extended = image.clone();
+        extended[..EXTENDED_SIGNATURE.len()].copy_from_slice(EXTENDED_SIGNATURE);
+        extended[0x34] = (TRACK_SIZE / 256) as u8;
+        corrupted("extended_track_sizes", &extended[..0x34 + 2]);
+    }
+
+    #[test]
+    fn truncated_images_are_errors() {
+        let image = data_image();
+        for length in (0..image.len()).step_by(SECTOR_SIZE / 2 + 1) {
+            let temp_image = TempImage::new("truncated", &image[..length]);
+            assert!(matches!(temp_image.open(), Err(VfsError::Io(_))));
+        }
+    }
+
+    #[test]
+    fn corrupted_directories_are_errors() {
+        let mut image = data_image();
+        // A file whose blocks are past the end of the disc
+        let entry = &mut image[DISC_INFO_SIZE + TRACK_INFO_SIZE..][..DIRECTORY_ENTRY_SIZE];
+        entry.fill(0);
+        entry[1..12].copy_from_slice(b"BROKEN  BIN");
+        entry[15] = 16;
+        entry[16] = 250;
+        entry[17] = 251;
+        let temp_image = TempImage::new("directory", &image);
+        let drive = temp_image.open().unwrap();
+        assert!(matches!(
+            drive.read_file(&path("broken.bin")),
+            Err(VfsError::Io(_))
+        ));
+
+        // Sectors too small to hold AMSDOS blocks
+        let mut image = data_image();
+        image[DISC_INFO_SIZE + 0x14] = 1;
+        let temp_image = TempImage::new("sector_size", &image);
+        let drive = temp_image.open().unwrap();
+        assert!(matches!(drive.read_dir(&[]), Err(VfsError::Io(_))));
+    }
+}
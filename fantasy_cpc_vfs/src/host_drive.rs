@@ -0,0 +1,247 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{path_to_string, DirEntry, Drive, DrivePath, VfsError};
+
+/// A drive stored in a directory of the host computer.
+/// Everything outside of that directory is out of reach, paths are normalized
+/// by the FileSystem before getting here.
+pub struct HostDrive {
+    root: PathBuf,
+}
+
+impl HostDrive {
+    /// Uses the directory as the root of the drive, creating it if needed.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<HostDrive, VfsError> {
+        fs::create_dir_all(root.as_ref())?;
+        Ok(HostDrive {
+            root: root.as_ref().to_path_buf(),
+        })
+    }
+
+    fn host_path(&self, path: &DrivePath) -> PathBuf {
+        let mut host_path = self.root.clone();
+        host_path.extend(path);
+        host_path
+    }
+}
+
+/// Converts a host error to the file system's errors.
+fn vfs_error(error: std::io::Error, path: &DrivePath) -> VfsError {
+    match error.kind() {
+        ErrorKind::NotFound => VfsError::NotFound(path_to_string(path)),
+        ErrorKind::AlreadyExists => VfsError::AlreadyExists(path_to_string(path)),
+        ErrorKind::PermissionDenied => VfsError::ReadOnly,
+        _ => VfsError::Io(error.to_string()),
+    }
+}
+
+impl Drive for HostDrive {
+    fn read_file(&self, path: &DrivePath) -> Result<Vec<u8>, VfsError> {
+        match self.is_dir(path) {
+            Some(true) => Err(VfsError::IsADirectory(path_to_string(path))),
+            _ => fs::read(self.host_path(path)).map_err(|error| vfs_error(error, path)),
+        }
+    }
+
+    fn write_file(&mut self, path: &DrivePath, data: &[u8]) -> Result<(), VfsError> {
+        if self.is_dir(path) == Some(true) {
+            return Err(VfsError::IsADirectory(path_to_string(path)));
+        }
+        fs::write(self.host_path(path), data).map_err(|error| vfs_error(error, path))
+    }
+
+    fn read_dir(&self, path: &DrivePath) -> Result<Vec<DirEntry>, VfsError> {
+        if self.is_dir(path) == Some(false) {
+            return Err(VfsError::NotADirectory(path_to_string(path)));
+        }
+
+        let mut entries = Vec::new();
+        for host_entry in
+            fs::read_dir(self.host_path(path)).map_err(|error| vfs_error(error, path))?
+        {
+            let host_entry = host_entry?;
+            let metadata = host_entry.metadata()?;
+            entries.push(DirEntry {
+                name: host_entry.file_name().to_string_lossy().into_owned(),
+                is_dir: metadata.is_dir(),
+                size: if metadata.is_dir() { 0 } else { metadata.len() },
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(entries)
+    }
+
+    fn create_dir(&mut self, path: &DrivePath) -> Result<(), VfsError> {
+        fs::create_dir(self.host_path(path)).map_err(|error| vfs_error(error, path))
+    }
+
+    fn remove(&mut self, path: &DrivePath) -> Result<(), VfsError> {
+        match self.is_dir(path) {
+            None => Err(VfsError::NotFound(path_to_string(path))),
+            Some(true) => {
+                if fs::read_dir(self.host_path(path))?.next().is_some() {
+                    return Err(VfsError::DirectoryNotEmpty(path_to_string(path)));
+                }
+                fs::remove_dir(self.host_path(path)).map_err(|error| vfs_error(error, path))
+            }
+            Some(false) => {
+                fs::remove_file(self.host_path(path)).map_err(|error| vfs_error(error, path))
+            }
+        }
+    }
+
+    fn rename(&mut self, from: &DrivePath, to: &DrivePath) -> Result<(), VfsError> {
+        if self.is_dir(to).is_some() {
+            return Err(VfsError::AlreadyExists(path_to_string(to)));
+        }
+        if to.starts_with(from) {
+            return Err(VfsError::InvalidPath(path_to_string(to)));
+        }
+        fs::rename(self.host_path(from), self.host_path(to))
+            .map_err(|error| vfs_error(error, from))
+    }
+
+    fn is_dir(&self, path: &DrivePath) -> Option<bool> {
+        fs::metadata(self.host_path(path))
+            .ok()
+            .map(|metadata| metadata.is_dir())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileSystem;
+
+    /// A directory in the temporary directory with a drive root inside it,
+    /// removed at the end of the test.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = std::env::temp_dir().join(format!(
+                "fantasy_cpc_vfs_{}_{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&path);
+            TempDir(path)
+        }
+
+        fn root(&self) -> PathBuf {
+            self.0.join("root")
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn path(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn files_are_read_and_written_in_the_root() {
+        let temp_dir = TempDir::new("read_write");
+        let mut drive = HostDrive::new(temp_dir.root()).unwrap();
+        drive.write_file(&path(&["a.txt"]), b"hello").unwrap();
+
+        assert_eq!(fs::read(temp_dir.root().join("a.txt")).unwrap(), b"hello");
+        assert_eq!(drive.read_file(&path(&["a.txt"])).unwrap(), b"hello");
+        assert_eq!(
+            drive.read_file(&path(&["b.txt"])),
+            Err(VfsError::NotFound(String::from("/b.txt")))
+        );
+        assert_eq!(
+            drive.read_file(&[]),
+            Err(VfsError::IsADirectory(String::from("/")))
+        );
+    }
+
+    #[test]
+    fn directories() {
+        let temp_dir = TempDir::new("directories");
+        let mut drive = HostDrive::new(temp_dir.root()).unwrap();
+        drive.create_dir(&path(&["games"])).unwrap();
+        drive.write_file(&path(&["games", "b"]), b"12").unwrap();
+        drive.write_file(&path(&["a"]), b"1").unwrap();
+
+        assert_eq!(
+            drive.create_dir(&path(&["games"])),
+            Err(VfsError::AlreadyExists(String::from("/games")))
+        );
+        assert_eq!(drive.is_dir(&path(&["games"])), Some(true));
+        assert_eq!(drive.is_dir(&path(&["games", "b"])), Some(false));
+        assert_eq!(
+            drive.read_dir(&[]).unwrap(),
+            vec![
+                DirEntry {
+                    name: String::from("a"),
+                    is_dir: false,
+                    size: 1,
+                },
+                DirEntry {
+                    name: String::from("games"),
+                    is_dir: true,
+                    size: 0,
+                },
+            ]
+        );
+        assert_eq!(
+            drive.remove(&path(&["games"])),
+            Err(VfsError::DirectoryNotEmpty(String::from("/games")))
+        );
+        drive.remove(&path(&["games", "b"])).unwrap();
+        drive.remove(&path(&["games"])).unwrap();
+        assert_eq!(drive.is_dir(&path(&["games"])), None);
+    }
+
+    #[test]
+    fn rename() {
+        let temp_dir = TempDir::new("rename");
+        let mut drive = HostDrive::new(temp_dir.root()).unwrap();
+        drive.create_dir(&path(&["dir"])).unwrap();
+        drive.write_file(&path(&["a"]), b"1").unwrap();
+
+        drive.rename(&path(&["a"]), &path(&["dir", "b"])).unwrap();
+        assert_eq!(drive.read_file(&path(&["dir", "b"])).unwrap(), b"1");
+        assert_eq!(drive.is_dir(&path(&["a"])), None);
+        assert_eq!(
+            drive.rename(&path(&["dir"]), &path(&["dir", "sub"])),
+            Err(VfsError::InvalidPath(String::from("/dir/sub")))
+        );
+        assert_eq!(
+            drive.rename(&path(&["dir", "b"]), &path(&["dir"])),
+            Err(VfsError::AlreadyExists(String::from("/dir")))
+        );
+    }
+
+    #[test]
+    fn paths_cant_escape_the_root() {
+        let temp_dir = TempDir::new("escape");
+        let mut file_system = FileSystem::new();
+        file_system.mount('C', Box::new(HostDrive::new(temp_dir.root()).unwrap()));
+        fs::write(temp_dir.0.join("secret"), b"host").unwrap();
+
+        assert_eq!(
+            file_system.read("../secret"),
+            Err(VfsError::NotFound(String::from("/secret")))
+        );
+        file_system.write("../../escaped", b"x").unwrap();
+        file_system.write("C:\\..\\..\\escaped2", b"x").unwrap();
+        assert!(temp_dir.root().join("escaped").exists());
+        assert!(temp_dir.root().join("escaped2").exists());
+        assert!(!temp_dir.0.join("escaped").exists());
+
+        file_system.change_dir("/..").unwrap();
+        assert_eq!(file_system.get_current_dir(), "C:/");
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{path_to_string, DirEntry, Drive, DrivePath, VfsError};
+
+const MAGIC: &[u8; 8] = b"FCPCDISC";
+const VERSION: u8 = 1;
+const DIR_ENTRY: u8 = 0;
+const FILE_ENTRY: u8 = 1;
+
+enum Entry {
+    Dir,
+    File(Vec<u8>),
+}
+
+/// A drive stored in a single disc-image file on the host computer.
+/// The whole disc is kept in memory and the image file is rewritten after every change.
+///
+/// Image format, numbers in little endian:
+/// - "FCPCDISC", then a version byte (1)
+/// - number of entries: u32
+/// - for each entry: kind u8 (0 directory, 1 file), path length u16, path in UTF-8
+///   with / separators and no leading /, data length u32, data
+pub struct ImageDrive {
+    image_path: PathBuf,
+    entries: BTreeMap<Vec<String>, Entry>,
+}
+
+impl ImageDrive {
+    /// Opens a disc image, an empty one is created if the file doesn't exist.
+    pub fn open<P: AsRef<Path>>(image_path: P) -> Result<ImageDrive, VfsError> {
+        let mut drive = ImageDrive {
+            image_path: image_path.as_ref().to_path_buf(),
+            entries: BTreeMap::new(),
+        };
+
+        if drive.image_path.exists() {
+            let image = fs::read(&drive.image_path)?;
+            drive.entries = parse_image(&image)?;
+        } else {
+            drive.save()?;
+        }
+
+        Ok(drive)
+    }
+
+    fn save(&self) -> Result<(), VfsError> {
+        let mut image: Vec<u8> = Vec::new();
+        image.extend_from_slice(MAGIC);
+        image.push(VERSION);
+        image.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+
+        for (path, entry) in &self.entries {
+            let path = path.join("/");
+            let (kind, data): (u8, &[u8]) = match entry {
+                Entry::Dir => (DIR_ENTRY, &[]),
+                Entry::File(data) => (FILE_ENTRY, data),
+            };
+            image.push(kind);
+            image.extend_from_slice(&(path.len() as u16).to_le_bytes());
+            image.extend_from_slice(path.as_bytes());
+            image.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            image.extend_from_slice(data);
+        }
+
+        fs::write(&self.image_path, image)?;
+        Ok(())
+    }
+
+    fn check_parent_dir(&self, path: &DrivePath) -> Result<(), VfsError> {
+        let parent = &path[..path.len().saturating_sub(1)];
+        match self.is_dir(parent) {
+            Some(true) => Ok(()),
+            Some(false) => Err(VfsError::NotADirectory(path_to_string(parent))),
+            None => Err(VfsError::NotFound(path_to_string(parent))),
+        }
+    }
+
+    /// Entries directly inside the directory.
+    fn children<'a>(
+        &'a self,
+        path: &'a DrivePath,
+    ) -> impl Iterator<Item = (&'a Vec<String>, &'a Entry)> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(entry_path, _)| {
+                entry_path.len() == path.len() + 1 && entry_path.starts_with(path)
+            })
+    }
+}
+
+fn parse_image(image: &[u8]) -> Result<BTreeMap<Vec<String>, Entry>, VfsError> {
+    let corrupted = || VfsError::Io(String::from("corrupted disc image"));
+    let mut entries = BTreeMap::new();
+
+    if image.len() < 13 || &image[0..8] != MAGIC || image[8] != VERSION {
+        return Err(corrupted());
+    }
+
+    let mut cursor = 9;
+    let mut take = |length: usize| -> Result<&[u8], VfsError> {
+        let bytes = image.get(cursor..cursor + length).ok_or_else(corrupted)?;
+        cursor += length;
+        Ok(bytes)
+    };
+
+    let entry_count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+
+    for _ in 0..entry_count {
+        let kind = take(1)?[0];
+        let path_length = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+        let path = std::str::from_utf8(take(path_length)?).map_err(|_| corrupted())?;
+        let path: Vec<String> = path.split('/').map(String::from).collect();
+        let data_length = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+        let data = take(data_length)?;
+
+        let entry = match kind {
+            DIR_ENTRY => Entry::Dir,
+            FILE_ENTRY => Entry::File(data.to_vec()),
+            _ => return Err(corrupted()),
+        };
+        entries.insert(path, entry);
+    }
+
+    Ok(entries)
+}
+
+impl Drive for ImageDrive {
+    fn read_file(&self, path: &DrivePath) -> Result<Vec<u8>, VfsError> {
+        match self.entries.get(path) {
+            Some(Entry::File(data)) => Ok(data.clone()),
+            Some(Entry::Dir) => Err(VfsError::IsADirectory(path_to_string(path))),
+            None if path.is_empty() => Err(VfsError::IsADirectory(path_to_string(path))),
+            None => Err(VfsError::NotFound(path_to_string(path))),
+        }
+    }
+
+    fn write_file(&mut self, path: &DrivePath, data: &[u8]) -> Result<(), VfsError> {
+        if self.is_dir(path) == Some(true) {
+            return Err(VfsError::IsADirectory(path_to_string(path)));
+        }
+        self.check_parent_dir(path)?;
+        self.entries.insert(path.to_vec(), Entry::File(data.to_vec()));
+        self.save()
+    }
+
+    fn read_dir(&self, path: &DrivePath) -> Result<Vec<DirEntry>, VfsError> {
+        match self.is_dir(path) {
+            Some(true) => (),
+            Some(false) => return Err(VfsError::NotADirectory(path_to_string(path))),
+            None => return Err(VfsError::NotFound(path_to_string(path))),
+        }
+
+        Ok(self
+            .children(path)
+            .map(|(entry_path, entry)| DirEntry {
+                name: entry_path.last().cloned().unwrap_or_default(),
+                is_dir: matches!(entry, Entry::Dir),
+                size: match entry {
+                    Entry::Dir => 0,
+                    Entry::File(data) => data.len() as u64,
+                },
+            })
+            .collect())
+    }
+
+    fn create_dir(&mut self, path: &DrivePath) -> Result<(), VfsError> {
+        if self.is_dir(path).is_some() {
+            return Err(VfsError::AlreadyExists(path_to_string(path)));
+        }
+        self.check_parent_dir(path)?;
+        self.entries.insert(path.to_vec(), Entry::Dir);
+        self.save()
+    }
+
+    fn remove(&mut self, path: &DrivePath) -> Result<(), VfsError> {
+        match self.entries.get(path) {
+            None => return Err(VfsError::NotFound(path_to_string(path))),
+            Some(Entry::Dir) if self.children(path).next().is_some() => {
+                return Err(VfsError::DirectoryNotEmpty(path_to_string(path)))
+            }
+            Some(_) => (),
+        }
+        self.entries.remove(path);
+        self.save()
+    }
+
+    fn rename(&mut self, from: &DrivePath, to: &DrivePath) -> Result<(), VfsError> {
+        if self.is_dir(from).is_none() || from.is_empty() {
+            return Err(VfsError::NotFound(path_to_string(from)));
+        }
+        if self.is_dir(to).is_some() {
+            return Err(VfsError::AlreadyExists(path_to_string(to)));
+        }
+        if to.starts_with(from) {
+            return Err(VfsError::InvalidPath(path_to_string(to)));
+        }
+        self.check_parent_dir(to)?;
+
+        // Moves the entry and everything inside it if it's a directory
+        let moved_paths: Vec<Vec<String>> = self
+            .entries
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+
+        for old_path in moved_paths {
+            if let Some(entry) = self.entries.remove(&old_path) {
+                let mut new_path = to.to_vec();
+                new_path.extend_from_slice(&old_path[from.len()..]);
+                self.entries.insert(new_path, entry);
+            }
+        }
+
+        self.save()
+    }
+
+    fn is_dir(&self, path: &DrivePath) -> Option<bool> {
+        if path.is_empty() {
+            return Some(true);
+        }
+        self.entries
+            .get(path)
+            .map(|entry| matches!(entry, Entry::Dir))
+    }
+}
@@ -0,0 +1,433 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+pub mod dsk_drive;
+pub mod host_drive;
+pub mod image_drive;
+
+/// Longest file or directory name accepted by the file system
+pub const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VfsError {
+    NotFound(String),
+    AlreadyExists(String),
+    NotADirectory(String),
+    IsADirectory(String),
+    DirectoryNotEmpty(String),
+    InvalidPath(String),
+    NoSuchDrive(char),
+    ReadOnly,
+    DiscFull,
+    Io(String),
+}
+
+impl fmt::Display for VfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VfsError::NotFound(path) => write!(f, "{} not found", path),
+            VfsError::AlreadyExists(path) => write!(f, "{} already exists", path),
+            VfsError::NotADirectory(path) => write!(f, "{} is not a directory", path),
+            VfsError::IsADirectory(path) => write!(f, "{} is a directory", path),
+            VfsError::DirectoryNotEmpty(path) => write!(f, "{} is not empty", path),
+            VfsError::InvalidPath(path) => write!(f, "Bad path {}", path),
+            VfsError::NoSuchDrive(letter) => write!(f, "Drive {}: not found", letter),
+            VfsError::ReadOnly => write!(f, "Disc is write protected"),
+            VfsError::DiscFull => write!(f, "Disc full"),
+            VfsError::Io(message) => write!(f, "Disc error: {}", message),
+        }
+    }
+}
+
+impl From<std::io::Error> for VfsError {
+    fn from(error: std::io::Error) -> Self {
+        VfsError::Io(error.to_string())
+    }
+}
+
+/// A path inside a drive, from its root: ["games", "save.dat"] for /games/save.dat.
+/// Paths are always normalized, they can't contain "." or ".." so they can't
+/// get out of the drive.
+pub type DrivePath = [String];
+
+/// Formats a drive path the way it is shown to the user, ex: "/games/save.dat".
+pub fn path_to_string(path: &DrivePath) -> String {
+    format!("/{}", path.join("/"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// A storage device the file system can mount under a drive letter.
+pub trait Drive {
+    fn read_file(&self, path: &DrivePath) -> Result<Vec<u8>, VfsError>;
+
+    /// Creates or replaces a file, the parent directory must exist.
+    fn write_file(&mut self, path: &DrivePath, data: &[u8]) -> Result<(), VfsError>;
+
+    /// Lists a directory, sorted by name.
+    fn read_dir(&self, path: &DrivePath) -> Result<Vec<DirEntry>, VfsError>;
+
+    fn create_dir(&mut self, path: &DrivePath) -> Result<(), VfsError>;
+
+    /// Removes a file or an empty directory.
+    fn remove(&mut self, path: &DrivePath) -> Result<(), VfsError>;
+
+    fn rename(&mut self, from: &DrivePath, to: &DrivePath) -> Result<(), VfsError>;
+
+    /// None if nothing exists at that path, Some(true) for a directory.
+    fn is_dir(&self, path: &DrivePath) -> Option<bool>;
+
+    /// Bytes left on the drive, None if the drive can't tell.
+    fn free_space(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Drives mounted under a letter, and a current directory like on a DOS or AMSDOS machine.
+/// Paths passed to the file system can be:
+/// - relative to the current directory: "save.dat", "../games"
+/// - absolute on the current drive: "/games/save.dat"
+/// - on another drive: "A:save.dat", "A:/games/save.dat"
+///
+/// Both / and \ are accepted as separators.
+pub struct FileSystem {
+    drives: Vec<(char, Box<dyn Drive>)>,
+    current_drive: char,
+    current_dir: Vec<String>,
+}
+
+/// The file system is shared between the system and the apps.
+pub type FileSystemHandle = Rc<RefCell<FileSystem>>;
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem {
+    pub fn new() -> FileSystem {
+        FileSystem {
+            drives: Vec::new(),
+            current_drive: 'A',
+            current_dir: Vec::new(),
+        }
+    }
+
+    pub fn into_handle(self) -> FileSystemHandle {
+        Rc::new(RefCell::new(self))
+    }
+
+    /// Mounts the drive under the letter, replacing the one that was there.
+    /// The first drive mounted becomes the current drive.
+    pub fn mount(&mut self, letter: char, drive: Box<dyn Drive>) {
+        let letter = letter.to_ascii_uppercase();
+        self.unmount(letter);
+        if self.drives.is_empty() {
+            self.current_drive = letter;
+            self.current_dir.clear();
+        }
+        self.drives.push((letter, drive));
+        self.drives.sort_by_key(|(letter, _)| *letter);
+    }
+
+    pub fn unmount(&mut self, letter: char) {
+        let letter = letter.to_ascii_uppercase();
+        self.drives
+            .retain(|(drive_letter, _)| *drive_letter != letter);
+        if self.current_drive == letter {
+            self.current_dir.clear();
+            if let Some((first, _)) = self.drives.first() {
+                self.current_drive = *first;
+            }
+        }
+    }
+
+    /// Letters of the mounted drives, in alphabetical order.
+    pub fn get_drives(&self) -> Vec<char> {
+        self.drives.iter().map(|(letter, _)| *letter).collect()
+    }
+
+    pub fn get_current_drive(&self) -> char {
+        self.current_drive
+    }
+
+    /// The current directory with its drive, ex: "A:/games".
+    pub fn get_current_dir(&self) -> String {
+        format!(
+            "{}:{}",
+            self.current_drive,
+            path_to_string(&self.current_dir)
+        )
+    }
+
+    /// Splits a path into a drive letter and a normalized path from the root of that drive.
+    pub fn resolve(&self, path: &str) -> Result<(char, Vec<String>), VfsError> {
+        let mut chars = path.chars();
+        let (letter, rest) = match (chars.next(), chars.next()) {
+            (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => {
+                (letter.to_ascii_uppercase(), &path[2..])
+            }
+            _ => (self.current_drive, path),
+        };
+
+        let mut components: Vec<String> =
+            if rest.starts_with(['/', '\\']) || letter != self.current_drive {
+                Vec::new()
+            } else {
+                self.current_dir.clone()
+            };
+
+        for component in rest.split(['/', '\\']) {
+            match component {
+                "" | "." => (),
+                ".." => {
+                    components.pop();
+                }
+                name => {
+                    if name.len() > MAX_NAME_LENGTH
+                        || name.chars().any(|c| c.is_control() || c == ':')
+                    {
+                        return Err(VfsError::InvalidPath(path.to_string()));
+                    }
+                    components.push(name.to_string());
+                }
+            }
+        }
+
+        Ok((letter, components))
+    }
+
+    fn get_drive(&self, letter: char) -> Result<&dyn Drive, VfsError> {
+        self.drives
+            .iter()
+            .find(|(drive_letter, _)| *drive_letter == letter)
+            .map(|(_, drive)| drive.as_ref())
+            .ok_or(VfsError::NoSuchDrive(letter))
+    }
+
+    fn get_drive_mut(&mut self, letter: char) -> Result<&mut Box<dyn Drive>, VfsError> {
+        self.drives
+            .iter_mut()
+            .find(|(drive_letter, _)| *drive_letter == letter)
+            .map(|(_, drive)| drive)
+            .ok_or(VfsError::NoSuchDrive(letter))
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, VfsError> {
+        let (letter, path) = self.resolve(path)?;
+        self.get_drive(letter)?.read_file(&path)
+    }
+
+    /// Reads a text file, invalid UTF-8 is replaced.
+    pub fn read_to_string(&self, path: &str) -> Result<String, VfsError> {
+        Ok(String::from_utf8_lossy(&self.read(path)?).into_owned())
+    }
+
+    pub fn write(&mut self, path: &str, data: &[u8]) -> Result<(), VfsError> {
+        let (letter, path) = self.resolve(path)?;
+        if path.is_empty() {
+            return Err(VfsError::IsADirectory(String::from("/")));
+        }
+        self.get_drive_mut(letter)?.write_file(&path, data)
+    }
+
+    pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, VfsError> {
+        let (letter, path) = self.resolve(path)?;
+        self.get_drive(letter)?.read_dir(&path)
+    }
+
+    pub fn create_dir(&mut self, path: &str) -> Result<(), VfsError> {
+        let (letter, path) = self.resolve(path)?;
+        if path.is_empty() {
+            return Err(VfsError::AlreadyExists(String::from("/")));
+        }
+        self.get_drive_mut(letter)?.create_dir(&path)
+    }
+
+    pub fn remove(&mut self, path: &str) -> Result<(), VfsError> {
+        let (letter, path) = self.resolve(path)?;
+        if path.is_empty() {
+            return Err(VfsError::InvalidPath(String::from("/")));
+        }
+        self.get_drive_mut(letter)?.remove(&path)
+    }
+
+    /// Bytes left on the drive of that path, None if the drive can't tell.
+    pub fn free_space(&self, path: &str) -> Result<Option<u64>, VfsError> {
+        let (letter, _) = self.resolve(path)?;
+        Ok(self.get_drive(letter)?.free_space())
+    }
+
+    /// True if a file or directory exists at that path.
+    pub fn exists(&self, path: &str) -> bool {
+        match self.resolve(path) {
+            Ok((letter, path)) => self
+                .get_drive(letter)
+                .map(|drive| drive.is_dir(&path).is_some())
+                .unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    /// Where a file copied or moved to destination ends up: inside destination
+    /// if it is a directory, with the same name as the source.
+    fn destination_path(
+        &self,
+        source: &DrivePath,
+        destination: &str,
+    ) -> Result<(char, Vec<String>), VfsError> {
+        let (letter, mut path) = self.resolve(destination)?;
+        if self.get_drive(letter)?.is_dir(&path) == Some(true) {
+            if let Some(name) = source.last() {
+                path.push(name.clone());
+            }
+        }
+        Ok((letter, path))
+    }
+
+    /// Renames or moves a file or directory. Files can be moved to another drive,
+    /// directories only inside their drive.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), VfsError> {
+        let (from_letter, from_path) = self.resolve(from)?;
+        let (to_letter, to_path) = self.destination_path(&from_path, to)?;
+
+        if from_letter == to_letter {
+            return self
+                .get_drive_mut(from_letter)?
+                .rename(&from_path, &to_path);
+        }
+
+        let data = self.get_drive(from_letter)?.read_file(&from_path)?;
+        self.get_drive_mut(to_letter)?.write_file(&to_path, &data)?;
+        self.get_drive_mut(from_letter)?.remove(&from_path)
+    }
+
+    /// Copies a file, to another drive if needed.
+    pub fn copy(&mut self, from: &str, to: &str) -> Result<(), VfsError> {
+        let (from_letter, from_path) = self.resolve(from)?;
+        let (to_letter, to_path) = self.destination_path(&from_path, to)?;
+
+        let data = self.get_drive(from_letter)?.read_file(&from_path)?;
+        self.get_drive_mut(to_letter)?.write_file(&to_path, &data)
+    }
+
+    /// Changes the current directory, and the current drive if the path starts with one.
+    pub fn change_dir(&mut self, path: &str) -> Result<(), VfsError> {
+        let (letter, path) = self.resolve(path)?;
+
+        match self.get_drive(letter)?.is_dir(&path) {
+            Some(true) => {
+                self.current_drive = letter;
+                self.current_dir = path;
+                Ok(())
+            }
+            Some(false) => Err(VfsError::NotADirectory(path_to_string(&path))),
+            None => Err(VfsError::NotFound(path_to_string(&path))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(letter: char, path: &[&str]) -> Result<(char, Vec<String>), VfsError> {
+        Ok((letter, path.iter().map(|name| name.to_string()).collect()))
+    }
+
+    /// A file system on drive A, in A:/games/save
+    fn file_system() -> FileSystem {
+        let mut file_system = FileSystem::new();
+        file_system.current_dir = vec![String::from("games"), String::from("save")];
+        file_system
+    }
+
+    #[test]
+    fn relative_paths_start_at_the_current_dir() {
+        let file_system = file_system();
+        assert_eq!(
+            file_system.resolve("a.txt"),
+            resolved('A', &["games", "save", "a.txt"])
+        );
+        assert_eq!(file_system.resolve(""), resolved('A', &["games", "save"]));
+        assert_eq!(
+            file_system.resolve("a:x"),
+            resolved('A', &["games", "save", "x"])
+        );
+    }
+
+    #[test]
+    fn absolute_paths_and_other_drives_start_at_the_root() {
+        let file_system = file_system();
+        assert_eq!(file_system.resolve("/a.txt"), resolved('A', &["a.txt"]));
+        assert_eq!(file_system.resolve("\\a.txt"), resolved('A', &["a.txt"]));
+        assert_eq!(file_system.resolve("c:a.txt"), resolved('C', &["a.txt"]));
+        assert_eq!(file_system.resolve("C:/x/y"), resolved('C', &["x", "y"]));
+    }
+
+    #[test]
+    fn paths_are_normalized() {
+        let file_system = file_system();
+        assert_eq!(
+            file_system.resolve("./x//y/./"),
+            resolved('A', &["games", "save", "x", "y"])
+        );
+        assert_eq!(
+            file_system.resolve("..\\x\\..\\y"),
+            resolved('A', &["games", "y"])
+        );
+        assert_eq!(file_system.resolve("/x/../y"), resolved('A', &["y"]));
+    }
+
+    #[test]
+    fn dot_dot_stops_at_the_root() {
+        let file_system = file_system();
+        assert_eq!(file_system.resolve("../../../.."), resolved('A', &[]));
+        assert_eq!(
+            file_system.resolve("../../../../etc/passwd"),
+            resolved('A', &["etc", "passwd"])
+        );
+        assert_eq!(file_system.resolve("C:/../../x"), resolved('C', &["x"]));
+        assert_eq!(file_system.resolve("/.."), resolved('A', &[]));
+    }
+
+    #[test]
+    fn colons_and_control_characters_are_rejected() {
+        let file_system = file_system();
+        for path in [
+            "a:b:c",
+            "x/c:y",
+            "1:x",
+            "bad\u{7}name",
+            "new\nline",
+            "/tab\t",
+        ] {
+            assert_eq!(
+                file_system.resolve(path),
+                Err(VfsError::InvalidPath(path.to_string())),
+                "{:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn names_are_limited_in_length() {
+        let file_system = file_system();
+        let longest = "x".repeat(MAX_NAME_LENGTH);
+        assert_eq!(
+            file_system.resolve(&longest),
+            resolved('A', &["games", "save", &longest])
+        );
+        let too_long = format!("/{}", "x".repeat(MAX_NAME_LENGTH + 1));
+        assert_eq!(
+            file_system.resolve(&too_long),
+            Err(VfsError::InvalidPath(too_long))
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use fantasy_cpc_app::{
+    input::{InputState, Key},
+    mouse::MouseMode,
+    AppResponse, AppStatus, FantasyCpcApp, SystemCommand,
+};
+use fantasy_cpc_clock::Clock;
+use fantasy_cpc_display_controller::DisplayController;
+use fantasy_cpc_vfs::FileSystemHandle;
+
+#[cfg(unix)]
+use crate::apps::pty::app::PtyApp;
+use crate::apps::{
+    basic::app::Basic, boot::Boot, cli::shell::Shell, life::Life, mandelbrot::game::Mandelbrot,
+    raycaster::game::Raycaster, script::app::ScriptHost, telnet::Telnet, weather_app::WeatherApp,
+};
+
+/// Key that sends the app in the foreground to the background and brings the next one forward
+pub const APP_SWITCH_KEY: Key = Key::F12;
+
+/// Owns the apps and the shell, and decides which one has the focus.
+/// Only one app can be Running, it's the foreground app that receives the inputs and draws
+/// on the screen. Apps in the Background keep being updated, without inputs, and aren't drawn.
+/// When no app is Running, the shell has the focus.
+/// The display effects of an app don't follow the focus, they are reset when it changes.
+pub struct AppManager {
+    apps: Vec<Box<dyn FantasyCpcApp>>,
+    shell: Box<Shell>,
+    /// App in the foreground during the last update, None for the shell
+    focus: Option<usize>,
+}
+
+impl AppManager {
+    /// Creates the apps and the shell in their power-on state, the boot animation is started.
+    /// Every app gets access to the file system.
+    pub fn new(file_system: FileSystemHandle) -> AppManager {
+        // ********* //
+        // The apps  //
+        // ********* //
+
+        // To be managed properly, apps must be added to that list.
+        // The main loop goes through the list and updates/renders the apps according to their statuses.
+        let mut apps: Vec<Box<dyn FantasyCpcApp>> = Vec::new();
+
+        // BOOT APP, not really an app, just plays the animation at startup and when rebooting
+        let mut boot = Box::new(Boot::new());
+        boot.get_app_params().change_status(AppStatus::Running);
+        apps.push(boot);
+
+        // CONWAY'S GAME OF LIFE, TEXT MODE
+        let life = Box::new(Life::new());
+        apps.push(life);
+
+        // WEATHER APP
+        let weather_app = Box::new(WeatherApp::new());
+        apps.push(weather_app);
+
+        // MANDELBROT
+        let mandelbrot = Box::new(Mandelbrot::new());
+        apps.push(mandelbrot);
+
+        // RAYCASTER
+        let raycaster = Box::new(Raycaster::new());
+        apps.push(raycaster);
+
+        // BASIC INTERPRETER
+        let basic = Box::new(Basic::new());
+        apps.push(basic);
+
+        // SCRIPT HOST, runs the Rhai cartridges loaded from the disc
+        let script_host = Box::new(ScriptHost::new());
+        apps.push(script_host);
+
+        // TELNET, connects to a server over TCP
+        let telnet = Box::new(Telnet::new());
+        apps.push(telnet);
+
+        // PTY, runs a program of the host computer in a terminal
+        #[cfg(unix)]
+        apps.push(Box::new(PtyApp::new()));
+
+        // The Shell is the command line interpreter app.
+        // It is launched at startup after the boot animation.
+        // The winit event loop will update and render the shell by default if
+        // no other process is running or has the focus.
+        // The Shell uses the console as default output.
+        // When closing/quitting an app, it should always fall back to the shell.
+        let mut shell = Box::new(Shell::new());
+        shell.get_app_params().change_status(AppStatus::Running);
+
+        // The shell launches apps by name
+        shell.set_app_names(
+            apps.iter_mut()
+                .map(|app| app.get_app_params().get_name().to_string())
+                .collect(),
+        );
+
+        for app in apps.iter_mut() {
+            app.get_app_params().set_file_system(file_system.clone());
+        }
+        shell.get_app_params().set_file_system(file_system);
+
+        let mut app_manager = AppManager {
+            apps,
+            shell,
+            focus: None,
+        };
+        app_manager.focus = app_manager.get_foreground_app();
+        app_manager
+    }
+
+    /// Updates every app that isn't stopped, and the shell if no app is in the foreground.
+    /// Returns the system commands sent by the apps.
+    pub fn update(
+        &mut self,
+        input: &InputState,
+        clock: &Clock,
+        display_controller: &mut DisplayController,
+    ) -> Vec<SystemCommand> {
+        if input.key_pressed(APP_SWITCH_KEY) {
+            self.switch_to_next_app();
+        }
+
+        let focus = self.get_foreground_app();
+        if focus != self.focus {
+            self.focus = focus;
+            display_controller.reset_effects();
+        }
+
+        let mut commands: Vec<SystemCommand> = Vec::new();
+        let mut responses: Vec<AppResponse> = Vec::new();
+
+        // The sprite layer belongs to the app in the foreground, it sets its sprites every frame
+        display_controller.get_sprites_mut().clear();
+
+        for app in self.apps.iter_mut() {
+            let response = match app.get_app_params().get_status() {
+                AppStatus::Running => app.exec_app(Some(input), clock, display_controller),
+                _ => app.exec_app(None, clock, display_controller),
+            };
+            responses.extend(response);
+        }
+
+        // If no app is in focus, run the shell
+        if self.get_foreground_app().is_none() {
+            responses.extend(self.shell.exec_app(Some(input), clock, display_controller));
+        }
+
+        for response in responses {
+            commands.extend(response.commands);
+        }
+
+        commands
+    }
+
+    /// How the app in the foreground, or the shell, wants the mouse.
+    pub fn get_mouse_mode(&mut self) -> MouseMode {
+        match self.get_foreground_app() {
+            Some(index) => self.apps[index].get_app_params().get_mouse_mode(),
+            None => self.shell.get_app_params().get_mouse_mode(),
+        }
+    }
+
+    /// Index of the Running app, if any.
+    fn get_foreground_app(&mut self) -> Option<usize> {
+        self.apps
+            .iter_mut()
+            .position(|app| *app.get_app_params().get_status() == AppStatus::Running)
+    }
+
+    fn find_app(&mut self, name: &str) -> Option<usize> {
+        let index = self
+            .apps
+            .iter_mut()
+            .position(|app| app.get_app_params().get_name().eq_ignore_ascii_case(name));
+
+        if index.is_none() {
+            self.print_message(&format!("No app named '{}'", name));
+        }
+
+        index
+    }
+
+    fn set_status(&mut self, index: usize, status: AppStatus) {
+        if status == AppStatus::Stopped {
+            self.apps[index].stop_app();
+        }
+        self.apps[index].get_app_params().change_status(status);
+    }
+
+    /// Brings the app to the foreground, starting it if it was stopped.
+    /// The app that was in the foreground goes to the background.
+    pub fn launch(&mut self, name: &str) {
+        if let Some(index) = self.find_app(name) {
+            self.bring_to_foreground(index);
+        }
+    }
+
+    /// Starts the app again on that file and brings it to the foreground.
+    pub fn launch_with_file(&mut self, name: &str, path: &str) {
+        if let Some(index) = self.find_app(name) {
+            self.set_status(index, AppStatus::Stopped);
+            self.apps[index].open_file(path);
+            self.bring_to_foreground(index);
+        }
+    }
+
+    /// Brings an app that is already started back to the foreground.
+    pub fn foreground(&mut self, name: &str) {
+        if let Some(index) = self.find_app(name) {
+            if *self.apps[index].get_app_params().get_status() == AppStatus::Stopped {
+                self.print_message(&format!("'{}' is not started, use run", name));
+            } else {
+                self.bring_to_foreground(index);
+            }
+        }
+    }
+
+    /// Keeps the app running without inputs or display, starting it if it was stopped.
+    pub fn background(&mut self, name: &str) {
+        if let Some(index) = self.find_app(name) {
+            self.set_status(index, AppStatus::Background);
+        }
+    }
+
+    pub fn stop(&mut self, name: &str) {
+        if let Some(index) = self.find_app(name) {
+            self.set_status(index, AppStatus::Stopped);
+        }
+    }
+
+    fn bring_to_foreground(&mut self, index: usize) {
+        if let Some(foreground) = self.get_foreground_app() {
+            if foreground != index {
+                self.set_status(foreground, AppStatus::Background);
+            }
+        }
+        self.set_status(index, AppStatus::Running);
+    }
+
+    /// Sends the foreground app to the background and brings the next started app forward,
+    /// going back to the shell after the last one.
+    pub fn switch_to_next_app(&mut self) {
+        let foreground = self.get_foreground_app();
+        let first_candidate = foreground.map_or(0, |index| index + 1);
+
+        if let Some(index) = foreground {
+            self.set_status(index, AppStatus::Background);
+        }
+
+        let next = (first_candidate..self.apps.len()).find(|index| {
+            *self.apps[*index].get_app_params().get_status() == AppStatus::Background
+        });
+
+        if let Some(next) = next {
+            self.set_status(next, AppStatus::Running);
+        }
+    }
+
+    /// Prints the status of every app in the shell.
+    pub fn list_apps(&mut self) {
+        let mut lines: Vec<String> = vec![format!("{:<12}{}", "NAME", "STATUS")];
+        let shell_status = match self.get_foreground_app() {
+            Some(_) => "background",
+            None => "foreground",
+        };
+        lines.push(format!("{:<12}{}", "shell", shell_status));
+
+        for app in self.apps.iter_mut() {
+            let status = match app.get_app_params().get_status() {
+                AppStatus::Running => "foreground",
+                AppStatus::Background => "background",
+                AppStatus::Stopped => "stopped",
+            };
+            lines.push(format!("{:<12}{}", app.get_app_params().get_name(), status));
+        }
+
+        self.print_message(&lines.join("\u{000D}"));
+    }
+
+    /// Stops everything, reinitializes the shell and plays the boot animation.
+    /// Apps keep their data.
+    pub fn warm_reboot(&mut self) {
+        self.save_history();
+        for app in self.apps.iter_mut() {
+            app.stop_app();
+            let app_params = app.get_app_params();
+            app_params.change_status(AppStatus::Stopped);
+            if app_params.get_name() == "boot" {
+                app_params.change_status(AppStatus::Running);
+            }
+        }
+        self.shell.get_app_params().change_status(AppStatus::Stopped);
+        self.shell.get_app_params().change_status(AppStatus::Running);
+    }
+
+    /// Saves what has to survive a reboot or the end of the session.
+    pub fn save_history(&mut self) {
+        self.shell.save_history();
+    }
+
+    pub fn print_message(&mut self, text: &str) {
+        self.shell.print_message(text);
+    }
+
+    /// Runs a command received from the network console in the shell,
+    /// even when an app is in the foreground.
+    pub fn run_remote_command(&mut self, command: &str) -> Vec<SystemCommand> {
+        self.shell.run_remote_command(command).commands
+    }
+
+    /// What the shell printed since the last call.
+    pub fn take_shell_output(&mut self) -> String {
+        self.shell.take_output()
+    }
+}
@@ -0,0 +1,152 @@
+use fantasy_cpc_app::{
+    input::{InputState, Key, TextChar},
+    AppResponse, AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams,
+};
+use fantasy_cpc_clock::Clock;
+use fantasy_cpc_display_controller::DisplayController;
+
+use super::interpreter::{Interpreter, State};
+use crate::apps::cli::line_editor::LineEditor;
+
+const BANNER: &str = "Fantasy CPC BASIC 1.0";
+
+/// Statements executed per frame, keeps the machine responsive during long programs
+const STATEMENTS_PER_FRAME: usize = 1000;
+
+/// The BASIC interpreter app. ESC stops the running program,
+/// ESC on an empty line goes back to the shell.
+/// The program and the variables are kept when leaving.
+pub struct Basic {
+    app_params: FantasyCppAppDefaultParams,
+    interpreter: Interpreter,
+    editor: LineEditor,
+    leaving: bool,
+}
+
+impl Basic {
+    pub fn new() -> Basic {
+        Basic {
+            app_params: FantasyCppAppDefaultParams::new(String::from("basic"), false),
+            interpreter: Interpreter::new(),
+            editor: LineEditor::new(),
+            leaving: false,
+        }
+    }
+
+    fn leave(&mut self) {
+        self.app_params.change_status(AppStatus::Stopped);
+        self.app_params.set_initialized(false);
+        self.leaving = true;
+    }
+
+    /// Edits the line being typed, in direct mode or for an INPUT.
+    fn edit_line(&mut self, inputs: &InputState) {
+        for text_char in inputs.text() {
+            match text_char {
+                TextChar::Char(c) if c.is_control() => (),
+                TextChar::Char(c) => self.editor.insert(c),
+                TextChar::Back => self.editor.backspace(),
+            }
+        }
+
+        if inputs.key_pressed_os(Key::Left) {
+            self.editor.move_left();
+        }
+        if inputs.key_pressed_os(Key::Right) {
+            self.editor.move_right();
+        }
+        if inputs.key_pressed_os(Key::Home) {
+            self.editor.move_home();
+        }
+        if inputs.key_pressed_os(Key::End) {
+            self.editor.move_end();
+        }
+        if inputs.key_pressed_os(Key::Delete) {
+            self.editor.delete();
+        }
+        if inputs.key_pressed_os(Key::Up) {
+            self.editor.history_up();
+        }
+        if inputs.key_pressed_os(Key::Down) {
+            self.editor.history_down();
+        }
+
+        if inputs.key_pressed_os(Key::Return) {
+            let line = self.editor.take_line();
+            self.interpreter.get_screen_mut().print_line(&line);
+            self.interpreter.enter_line(&line);
+        }
+    }
+}
+
+impl FantasyCpcApp for Basic {
+    fn get_app_params(&mut self) -> &mut FantasyCppAppDefaultParams {
+        &mut self.app_params
+    }
+
+    fn init_app(&mut self, _system_clock: &Clock, display_controller: &mut DisplayController) {
+        display_controller.clear_text_layer();
+        self.interpreter
+            .set_file_system(self.app_params.get_file_system());
+        self.editor.clear();
+
+        let screen = self.interpreter.get_screen_mut();
+        screen.reset();
+        screen.print_line(BANNER);
+        screen.new_line();
+        screen.print_line("Ready");
+    }
+
+    fn update_app(&mut self, inputs: Option<&InputState>, _clock: &Clock) -> Option<AppResponse> {
+        // In the background, the program keeps running without inputs
+        let Some(inputs) = inputs else {
+            self.interpreter.get_screen_mut().reinstall_inks();
+            self.interpreter.run(STATEMENTS_PER_FRAME);
+            return None;
+        };
+
+        let escape = inputs.key_pressed(Key::Escape);
+
+        match self.interpreter.get_state() {
+            State::Running if escape => self.interpreter.break_program(),
+            State::Running => {
+                for text_char in inputs.text() {
+                    if let TextChar::Char(c) = text_char {
+                        self.interpreter.press_key(c);
+                    }
+                }
+            }
+            State::Ready if escape && self.editor.get_line().is_empty() => {
+                self.leave();
+                return None;
+            }
+            State::Ready | State::Input if escape => {
+                self.editor.clear();
+                self.interpreter.break_program();
+            }
+            State::Ready | State::Input => self.edit_line(inputs),
+        }
+
+        self.interpreter.run(STATEMENTS_PER_FRAME);
+        None
+    }
+
+    fn draw_app(&mut self, _clock: &Clock, display_controller: &mut DisplayController) {
+        if self.leaving {
+            self.leaving = false;
+            self.interpreter
+                .get_screen_mut()
+                .restore_inks(display_controller);
+            display_controller.clear_text_layer();
+            return;
+        }
+
+        let show_cursor = self.interpreter.get_state() != State::Running;
+        self.interpreter.get_screen_mut().render(
+            display_controller,
+            self.editor.get_line(),
+            self.editor.get_cursor_offset(),
+            show_cursor,
+        );
+    }
+}
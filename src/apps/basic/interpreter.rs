@@ -0,0 +1,1917 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    ops::{Bound, RangeInclusive},
+    rc::Rc,
+    time::Instant,
+};
+
+use fantasy_cpc_vfs::FileSystemHandle;
+use rand::Rng;
+
+use super::{
+    lexer::{split_line_number, tokenize, Token},
+    screen::{Screen, PRINT_ZONE},
+};
+use crate::sound::play;
+
+/// Size of the arrays used without DIM, for each dimension
+const DEFAULT_ARRAY_SIZE: usize = 10;
+
+/// Number of elements of the largest array, all dimensions together
+const MAX_ARRAY_SIZE: usize = 1 << 20;
+
+/// Graphics coordinates are 16 bit numbers, like on the CPC
+const GRAPHICS_COORDINATES: RangeInclusive<i64> = i16::MIN as i64..=i16::MAX as i64;
+
+/// Parenthesis, functions and unary operators nested at most in an expression
+const MAX_EXPRESSION_NESTING: usize = 64;
+
+/// GOSUBs without RETURN and FOR loops without NEXT kept at most
+const MAX_STACK_SIZE: usize = 256;
+
+/// Extension added to program names that have none, like AMSDOS does
+const PROGRAM_EXTENSION: &str = ".BAS";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BasicError {
+    Syntax,
+    TypeMismatch,
+    DivisionByZero,
+    UnexpectedNext,
+    UnexpectedReturn,
+    LineDoesNotExist,
+    SubscriptOutOfRange,
+    ArrayAlreadyDimensioned,
+    ImproperArgument,
+    Overflow,
+    MemoryFull,
+    DataExhausted,
+    BadFile,
+    Disc(String),
+}
+
+impl fmt::Display for BasicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BasicError::Syntax => write!(f, "Syntax error"),
+            BasicError::TypeMismatch => write!(f, "Type mismatch"),
+            BasicError::DivisionByZero => write!(f, "Division by zero"),
+            BasicError::UnexpectedNext => write!(f, "Unexpected NEXT"),
+            BasicError::UnexpectedReturn => write!(f, "Unexpected RETURN"),
+            BasicError::LineDoesNotExist => write!(f, "Line does not exist"),
+            BasicError::SubscriptOutOfRange => write!(f, "Subscript out of range"),
+            BasicError::ArrayAlreadyDimensioned => write!(f, "Array already dimensioned"),
+            BasicError::ImproperArgument => write!(f, "Improper argument"),
+            BasicError::Overflow => write!(f, "Overflow"),
+            BasicError::MemoryFull => write!(f, "Memory full"),
+            BasicError::DataExhausted => write!(f, "DATA exhausted"),
+            BasicError::BadFile => write!(f, "Bad file"),
+            BasicError::Disc(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Str(String),
+}
+
+impl Value {
+    fn number(&self) -> Result<f64, BasicError> {
+        match self {
+            Value::Number(value) => Ok(*value),
+            Value::Str(_) => Err(BasicError::TypeMismatch),
+        }
+    }
+
+    fn text(self) -> Result<String, BasicError> {
+        match self {
+            Value::Str(text) => Ok(text),
+            Value::Number(_) => Err(BasicError::TypeMismatch),
+        }
+    }
+
+    /// Value printed by PRINT: numbers get a space before (in place of the sign) and after.
+    fn to_print(&self) -> String {
+        match self {
+            Value::Number(value) => format!("{} ", format_number(*value)),
+            Value::Str(text) => text.clone(),
+        }
+    }
+}
+
+/// Numbers as the CPC shows them: positive numbers start with a space,
+/// no useless decimals, 9 significant digits.
+fn format_number(value: f64) -> String {
+    let digits = if value.fract() == 0.0 && value.abs() < 1e9 {
+        format!("{}", value as i64)
+    } else if value.abs() >= 1e9 || value.abs() < 1e-5 {
+        format!("{:E}", value)
+    } else {
+        let decimals = (8 - value.abs().log10().floor() as i32).clamp(0, 9) as usize;
+        let fixed = format!("{:.*}", decimals, value);
+        fixed
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    };
+
+    match value < 0.0 {
+        true => digits,
+        false => format!(" {}", digits),
+    }
+}
+
+fn boolean(value: bool) -> Value {
+    Value::Number(if value { -1.0 } else { 0.0 })
+}
+
+fn is_string_name(name: &str) -> bool {
+    name.ends_with('$')
+}
+
+fn default_value(name: &str) -> Value {
+    match is_string_name(name) {
+        true => Value::Str(String::new()),
+        false => Value::Number(0.0),
+    }
+}
+
+fn graphics_coordinate(value: i64) -> Result<isize, BasicError> {
+    match GRAPHICS_COORDINATES.contains(&value) {
+        true => Ok(value as isize),
+        false => Err(BasicError::ImproperArgument),
+    }
+}
+
+/// Where the interpreter is in the program. line is None for a statement typed in direct mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Location {
+    line: Option<u16>,
+    position: usize,
+}
+
+struct ForLoop {
+    variable: String,
+    end: f64,
+    step: f64,
+    body: Location,
+}
+
+struct Array {
+    dimensions: Vec<usize>,
+    values: Vec<Value>,
+}
+
+/// A variable or array element that INPUT or READ assigns.
+enum Target {
+    Variable(String),
+    Element(String, Vec<usize>),
+}
+
+impl Target {
+    fn name(&self) -> &str {
+        match self {
+            Target::Variable(name) | Target::Element(name, _) => name,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Direct mode, waiting for a command or a program line
+    Ready,
+    Running,
+    /// A program is waiting for the user to answer an INPUT
+    Input,
+}
+
+struct ProgramLine {
+    text: String,
+    tokens: Rc<[Token]>,
+}
+
+/// Reads the tokens of one line.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn accept(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), BasicError> {
+        match self.accept(token) {
+            true => Ok(()),
+            false => Err(BasicError::Syntax),
+        }
+    }
+
+    fn peek_word(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(next)) if next == word)
+    }
+
+    fn accept_word(&mut self, word: &str) -> bool {
+        if self.peek_word(word) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), BasicError> {
+        match self.accept_word(word) {
+            true => Ok(()),
+            false => Err(BasicError::Syntax),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, BasicError> {
+        match self.next() {
+            Some(Token::Word(name)) => Ok(name.clone()),
+            _ => Err(BasicError::Syntax),
+        }
+    }
+
+    fn line_number(&mut self) -> Result<u16, BasicError> {
+        match self.next() {
+            Some(Token::Number(number)) if (0.0..=65535.0).contains(number) => Ok(*number as u16),
+            _ => Err(BasicError::Syntax),
+        }
+    }
+
+    fn at_end_of_statement(&self) -> bool {
+        matches!(self.peek(), None | Some(Token::Colon) | Some(Token::Rem(_)))
+            || self.peek_word("ELSE")
+    }
+
+    fn skip_to_end_of_statement(&mut self) {
+        while !matches!(self.peek(), None | Some(Token::Colon)) {
+            self.position += 1;
+        }
+    }
+
+    fn skip_to_end_of_line(&mut self) {
+        self.position = self.tokens.len();
+    }
+}
+
+/// A Locomotive BASIC flavoured interpreter. Programs are kept as numbered lines of tokens
+/// and executed a few statements at a time by run(), so that the app stays responsive.
+pub struct Interpreter {
+    program: BTreeMap<u16, ProgramLine>,
+    /// The last line typed in direct mode
+    direct: Rc<[Token]>,
+    location: Location,
+    jump: Option<Location>,
+    state: State,
+    variables: HashMap<String, Value>,
+    arrays: HashMap<String, Array>,
+    for_stack: Vec<ForLoop>,
+    gosub_stack: Vec<Location>,
+    /// Depth of the expression being evaluated
+    expression_nesting: usize,
+    data: Vec<(u16, Value)>,
+    data_pointer: usize,
+    input_targets: Vec<Target>,
+    input_prompt: String,
+    keys: VecDeque<char>,
+    end_of_frame: bool,
+    power_on: Instant,
+    screen: Screen,
+    file_system: Option<FileSystemHandle>,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter {
+            program: BTreeMap::new(),
+            direct: Rc::from(Vec::new()),
+            location: Location {
+                line: None,
+                position: 0,
+            },
+            jump: None,
+            state: State::Ready,
+            variables: HashMap::new(),
+            arrays: HashMap::new(),
+            for_stack: Vec::new(),
+            gosub_stack: Vec::new(),
+            expression_nesting: 0,
+            data: Vec::new(),
+            data_pointer: 0,
+            input_targets: Vec::new(),
+            input_prompt: String::new(),
+            keys: VecDeque::new(),
+            end_of_frame: false,
+            power_on: Instant::now(),
+            screen: Screen::new(),
+            file_system: None,
+        }
+    }
+
+    pub fn get_screen_mut(&mut self) -> &mut Screen {
+        &mut self.screen
+    }
+
+    pub fn get_state(&self) -> State {
+        self.state
+    }
+
+    pub fn set_file_system(&mut self, file_system: Option<FileSystemHandle>) {
+        self.file_system = file_system;
+    }
+
+    /// Key typed while a program runs, read by INKEY$.
+    pub fn press_key(&mut self, c: char) {
+        self.keys.push_back(c);
+    }
+
+    /// Handles a line typed by the user: the answer to an INPUT, a program line to store,
+    /// or statements to execute right away.
+    pub fn enter_line(&mut self, line: &str) {
+        match self.state {
+            State::Input => self.answer_input(line),
+            State::Running => (),
+            State::Ready => match split_line_number(line) {
+                Some((number, "")) => {
+                    self.program.remove(&number);
+                }
+                Some((number, text)) => {
+                    self.program.insert(
+                        number,
+                        ProgramLine {
+                            text: text.to_string(),
+                            tokens: tokenize(text).into(),
+                        },
+                    );
+                }
+                None if line.trim().is_empty() => (),
+                None => {
+                    self.direct = tokenize(line).into();
+                    self.location = Location {
+                        line: None,
+                        position: 0,
+                    };
+                    self.state = State::Running;
+                }
+            },
+        }
+    }
+
+    /// ESC: stops the program where it is.
+    pub fn break_program(&mut self) {
+        if self.state == State::Ready {
+            return;
+        }
+        self.screen.end_line();
+        match self.location.line {
+            Some(line) => self.screen.print_line(&format!("Break in {}", line)),
+            None => self.screen.print_line("Break"),
+        }
+        self.ready();
+    }
+
+    fn ready(&mut self) {
+        self.state = State::Ready;
+        self.keys.clear();
+        self.screen.end_line();
+        self.screen.print_line("Ready");
+    }
+
+    fn report_error(&mut self, error: BasicError) {
+        self.screen.end_line();
+        match self.location.line {
+            Some(line) => self.screen.print_line(&format!("{} in {}", error, line)),
+            None => self.screen.print_line(&error.to_string()),
+        }
+        self.ready();
+    }
+
+    /// Executes up to budget statements, less if the program ends, waits for an INPUT or
+    /// calls FRAME to wait for the next frame.
+    pub fn run(&mut self, budget: usize) {
+        self.end_of_frame = false;
+        let mut count = 0;
+        while self.state == State::Running && count < budget && !self.end_of_frame {
+            count += 1;
+            if let Err(error) = self.step() {
+                self.report_error(error);
+            }
+        }
+    }
+
+    fn current_tokens(&self) -> Result<Rc<[Token]>, BasicError> {
+        match self.location.line {
+            None => Ok(self.direct.clone()),
+            Some(number) => self
+                .program
+                .get(&number)
+                .map(|line| line.tokens.clone())
+                .ok_or(BasicError::LineDoesNotExist),
+        }
+    }
+
+    fn next_line(&self, line: u16) -> Option<u16> {
+        self.program
+            .range((Bound::Excluded(line), Bound::Unbounded))
+            .next()
+            .map(|(number, _)| *number)
+    }
+
+    /// Executes one statement.
+    fn step(&mut self) -> Result<(), BasicError> {
+        let tokens = self.current_tokens()?;
+
+        if self.location.position >= tokens.len() {
+            match self.location.line.and_then(|line| self.next_line(line)) {
+                Some(next) => {
+                    self.location = Location {
+                        line: Some(next),
+                        position: 0,
+                    }
+                }
+                None => self.ready(),
+            }
+            return Ok(());
+        }
+
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: self.location.position,
+        };
+        while parser.accept(&Token::Colon) {}
+        if parser.peek().is_none() {
+            self.location.position = parser.position;
+            return Ok(());
+        }
+
+        self.jump = None;
+        self.statement(&mut parser)?;
+
+        match self.jump.take() {
+            Some(location) => self.location = location,
+            None => {
+                if !parser.at_end_of_statement() {
+                    return Err(BasicError::Syntax);
+                }
+                self.location.position = parser.position;
+            }
+        }
+        Ok(())
+    }
+
+    fn goto(&mut self, line: u16) -> Result<(), BasicError> {
+        if !self.program.contains_key(&line) {
+            return Err(BasicError::LineDoesNotExist);
+        }
+        self.jump = Some(Location {
+            line: Some(line),
+            position: 0,
+        });
+        Ok(())
+    }
+
+    fn gosub(&mut self, line: u16, parser: &Parser) -> Result<(), BasicError> {
+        if self.gosub_stack.len() >= MAX_STACK_SIZE {
+            return Err(BasicError::MemoryFull);
+        }
+        self.gosub_stack.push(self.here(parser));
+        self.goto(line)
+    }
+
+    fn here(&self, parser: &Parser) -> Location {
+        Location {
+            line: self.location.line,
+            position: parser.position,
+        }
+    }
+
+    fn statement(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let keyword = match parser.peek() {
+            Some(Token::Rem(_)) => {
+                parser.skip_to_end_of_line();
+                return Ok(());
+            }
+            Some(Token::Other('?')) => {
+                parser.next();
+                return self.print(parser);
+            }
+            Some(Token::Word(word)) => word.clone(),
+            _ => return Err(BasicError::Syntax),
+        };
+        parser.next();
+
+        match keyword.as_str() {
+            "PRINT" => self.print(parser),
+            "LET" => self.assignment(parser),
+            "INPUT" => self.input(parser, false),
+            "LINE" => {
+                parser.expect_word("INPUT")?;
+                self.input(parser, true)
+            }
+            "IF" => self.if_statement(parser),
+            "ELSE" => {
+                parser.skip_to_end_of_line();
+                Ok(())
+            }
+            "FOR" => self.for_statement(parser),
+            "NEXT" => self.next_statement(parser),
+            "GOTO" => {
+                let line = parser.line_number()?;
+                self.goto(line)
+            }
+            "GOSUB" => {
+                let line = parser.line_number()?;
+                self.gosub(line, parser)
+            }
+            "RETURN" => {
+                let location = self.gosub_stack.pop().ok_or(BasicError::UnexpectedReturn)?;
+                self.jump = Some(location);
+                Ok(())
+            }
+            "ON" => self.on_statement(parser),
+            "END" => {
+                parser.skip_to_end_of_line();
+                self.ready();
+                Ok(())
+            }
+            "STOP" => {
+                parser.skip_to_end_of_line();
+                self.break_program();
+                Ok(())
+            }
+            "DIM" => self.dim(parser),
+            "DATA" => {
+                parser.skip_to_end_of_statement();
+                Ok(())
+            }
+            "READ" => self.read(parser),
+            "RESTORE" => {
+                let line = match parser.at_end_of_statement() {
+                    true => 0,
+                    false => parser.line_number()?,
+                };
+                self.data_pointer = self
+                    .data
+                    .partition_point(|(data_line, _)| *data_line < line);
+                Ok(())
+            }
+            "CLEAR" => {
+                self.clear_variables();
+                Ok(())
+            }
+            "RANDOMIZE" => {
+                if !parser.at_end_of_statement() {
+                    self.expression(parser)?;
+                }
+                Ok(())
+            }
+            "FRAME" => {
+                self.end_of_frame = true;
+                Ok(())
+            }
+            "CLS" => {
+                self.screen.clear();
+                Ok(())
+            }
+            "MODE" => {
+                let mode = self.int_expression(parser)?;
+                if !(0..=2).contains(&mode) {
+                    return Err(BasicError::ImproperArgument);
+                }
+                self.screen.reset();
+                Ok(())
+            }
+            "LOCATE" => {
+                let x = self.int_expression(parser)?;
+                parser.expect(&Token::Comma)?;
+                let y = self.int_expression(parser)?;
+                if x < 1 || y < 1 {
+                    return Err(BasicError::ImproperArgument);
+                }
+                self.screen.set_cursor(x as usize - 1, y as usize - 1);
+                Ok(())
+            }
+            "PEN" => {
+                let pen = self.color_expression(parser)?;
+                self.screen.set_pen(pen);
+                Ok(())
+            }
+            "PAPER" => {
+                let paper = self.color_expression(parser)?;
+                self.screen.set_paper(paper);
+                Ok(())
+            }
+            "BORDER" => {
+                let border = self.color_expression(parser)?;
+                // The CPC accepts a second color to make the border flash
+                if parser.accept(&Token::Comma) {
+                    self.color_expression(parser)?;
+                }
+                self.screen.set_border(border);
+                Ok(())
+            }
+            "INK" => {
+                let pen = self.color_expression(parser)?;
+                parser.expect(&Token::Comma)?;
+                let color = self.color_expression(parser)?;
+                if parser.accept(&Token::Comma) {
+                    self.color_expression(parser)?;
+                }
+                self.screen.set_ink(pen, color);
+                Ok(())
+            }
+            "GRAPHICS" => {
+                parser.expect_word("PEN")?;
+                let pen = self.color_expression(parser)?;
+                self.screen.set_graphics_pen(pen);
+                Ok(())
+            }
+            "CLG" => {
+                if !parser.at_end_of_statement() {
+                    let paper = self.color_expression(parser)?;
+                    self.screen.set_paper(paper);
+                }
+                self.screen.clear_graphics();
+                Ok(())
+            }
+            "PLOT" | "PLOTR" | "DRAW" | "DRAWR" | "MOVE" | "MOVER" => {
+                self.graphics_statement(&keyword, parser)
+            }
+            "SOUND" => self.sound(parser),
+            "LIST" => self.list(parser),
+            "RUN" => self.run_statement(parser),
+            "NEW" => {
+                self.program.clear();
+                self.clear_variables();
+                parser.skip_to_end_of_line();
+                self.ready();
+                Ok(())
+            }
+            "LOAD" => {
+                let name = self.string_expression(parser)?;
+                self.load(&name)?;
+                parser.skip_to_end_of_line();
+                self.ready();
+                Ok(())
+            }
+            "SAVE" => {
+                let name = self.string_expression(parser)?;
+                self.save(&name)
+            }
+            "CAT" => self.cat(),
+            _ => {
+                // LET is optional
+                parser.position -= 1;
+                self.assignment(parser)
+            }
+        }
+    }
+
+    fn clear_variables(&mut self) {
+        self.variables.clear();
+        self.arrays.clear();
+        self.for_stack.clear();
+        self.gosub_stack.clear();
+    }
+
+    // ********************************* Expressions ********************************* //
+
+    fn expression(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        self.nested(parser, Interpreter::or_expression)
+    }
+
+    /// Evaluates a part of an expression one level deeper, a too deep expression is an error
+    /// instead of a stack overflow.
+    fn nested(
+        &mut self,
+        parser: &mut Parser,
+        evaluate: fn(&mut Interpreter, &mut Parser) -> Result<Value, BasicError>,
+    ) -> Result<Value, BasicError> {
+        if self.expression_nesting >= MAX_EXPRESSION_NESTING {
+            return Err(BasicError::MemoryFull);
+        }
+        self.expression_nesting += 1;
+        let value = evaluate(self, parser);
+        self.expression_nesting -= 1;
+        value
+    }
+
+    fn or_expression(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        let mut left = self.and_expression(parser)?;
+        loop {
+            let xor = if parser.accept_word("OR") {
+                false
+            } else if parser.accept_word("XOR") {
+                true
+            } else {
+                return Ok(left);
+            };
+            let a = left.number()?.round() as i64;
+            let b = self.and_expression(parser)?.number()?.round() as i64;
+            left = Value::Number(if xor { a ^ b } else { a | b } as f64);
+        }
+    }
+
+    fn and_expression(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        let mut left = self.not_expression(parser)?;
+        while parser.accept_word("AND") {
+            let a = left.number()?.round() as i64;
+            let b = self.not_expression(parser)?.number()?.round() as i64;
+            left = Value::Number((a & b) as f64);
+        }
+        Ok(left)
+    }
+
+    fn not_expression(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        if parser.accept_word("NOT") {
+            let value = self
+                .nested(parser, Interpreter::not_expression)?
+                .number()?
+                .round() as i64;
+            return Ok(Value::Number(!value as f64));
+        }
+        self.comparison(parser)
+    }
+
+    fn comparison(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        let mut left = self.additive(parser)?;
+        loop {
+            let operator = match parser.peek() {
+                Some(
+                    operator @ (Token::Equal
+                    | Token::NotEqual
+                    | Token::Less
+                    | Token::Greater
+                    | Token::LessEqual
+                    | Token::GreaterEqual),
+                ) => operator.clone(),
+                _ => return Ok(left),
+            };
+            parser.next();
+            let right = self.additive(parser)?;
+
+            let ordering = match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+                (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+                _ => return Err(BasicError::TypeMismatch),
+            };
+            let result = match ordering {
+                Some(ordering) => match operator {
+                    Token::Equal => ordering.is_eq(),
+                    Token::NotEqual => ordering.is_ne(),
+                    Token::Less => ordering.is_lt(),
+                    Token::Greater => ordering.is_gt(),
+                    Token::LessEqual => ordering.is_le(),
+                    _ => ordering.is_ge(),
+                },
+                None => false,
+            };
+            left = boolean(result);
+        }
+    }
+
+    fn additive(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        let mut left = self.modulo(parser)?;
+        loop {
+            if parser.accept(&Token::Plus) {
+                let right = self.modulo(parser)?;
+                left = match (left, right) {
+                    (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+                    (Value::Str(a), Value::Str(b)) => Value::Str(a + b.as_str()),
+                    _ => return Err(BasicError::TypeMismatch),
+                };
+            } else if parser.accept(&Token::Minus) {
+                let right = self.modulo(parser)?.number()?;
+                left = Value::Number(left.number()? - right);
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn modulo(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        let mut left = self.integer_division(parser)?;
+        while parser.accept_word("MOD") {
+            let a = left.number()?.round() as i64;
+            let b = self.integer_division(parser)?.number()?.round() as i64;
+            if b == 0 {
+                return Err(BasicError::DivisionByZero);
+            }
+            left = Value::Number(a.checked_rem(b).ok_or(BasicError::Overflow)? as f64);
+        }
+        Ok(left)
+    }
+
+    fn integer_division(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        let mut left = self.term(parser)?;
+        while parser.accept(&Token::Backslash) {
+            let a = left.number()?.round() as i64;
+            let b = self.term(parser)?.number()?.round() as i64;
+            if b == 0 {
+                return Err(BasicError::DivisionByZero);
+            }
+            left = Value::Number(a.checked_div(b).ok_or(BasicError::Overflow)? as f64);
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        let mut left = self.unary(parser)?;
+        loop {
+            if parser.accept(&Token::Star) {
+                let right = self.unary(parser)?.number()?;
+                left = Value::Number(left.number()? * right);
+            } else if parser.accept(&Token::Slash) {
+                let right = self.unary(parser)?.number()?;
+                if right == 0.0 {
+                    return Err(BasicError::DivisionByZero);
+                }
+                left = Value::Number(left.number()? / right);
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn unary(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        if parser.accept(&Token::Minus) {
+            let value = self.nested(parser, Interpreter::unary)?;
+            return Ok(Value::Number(-value.number()?));
+        }
+        if parser.accept(&Token::Plus) {
+            let value = self.nested(parser, Interpreter::unary)?;
+            return Ok(Value::Number(value.number()?));
+        }
+        let base = self.primary(parser)?;
+        if parser.accept(&Token::Caret) {
+            let exponent = self.nested(parser, Interpreter::unary)?.number()?;
+            return Ok(Value::Number(base.number()?.powf(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self, parser: &mut Parser) -> Result<Value, BasicError> {
+        match parser.next() {
+            Some(Token::Number(value)) => Ok(Value::Number(*value)),
+            Some(Token::Str(text)) => Ok(Value::Str(text.clone())),
+            Some(Token::LeftParen) => {
+                let value = self.expression(parser)?;
+                parser.expect(&Token::RightParen)?;
+                Ok(value)
+            }
+            Some(Token::Word(name)) => {
+                let name = name.clone();
+                match self.function(&name, parser)? {
+                    Some(value) => Ok(value),
+                    None => self.variable(&name, parser),
+                }
+            }
+            _ => Err(BasicError::Syntax),
+        }
+    }
+
+    /// The arguments of a function, between parenthesis. No parenthesis means no arguments.
+    fn arguments(&mut self, parser: &mut Parser) -> Result<Vec<Value>, BasicError> {
+        let mut arguments = Vec::new();
+        if parser.accept(&Token::LeftParen) {
+            loop {
+                arguments.push(self.expression(parser)?);
+                if !parser.accept(&Token::Comma) {
+                    break;
+                }
+            }
+            parser.expect(&Token::RightParen)?;
+        }
+        Ok(arguments)
+    }
+
+    /// Calls the function if name is one, returns None if it's a variable.
+    fn function(&mut self, name: &str, parser: &mut Parser) -> Result<Option<Value>, BasicError> {
+        // Functions without arguments
+        let value = match name {
+            "PI" => Value::Number(std::f64::consts::PI),
+            "TIME" => Value::Number((self.power_on.elapsed().as_secs_f64() * 300.0).floor()),
+            "INKEY$" => Value::Str(self.keys.pop_front().map(String::from).unwrap_or_default()),
+            "XPOS" => Value::Number(self.screen.get_graphics_cursor().0 as f64),
+            "YPOS" => Value::Number(self.screen.get_graphics_cursor().1 as f64),
+            _ => {
+                if !is_function(name) {
+                    return Ok(None);
+                }
+                let arguments = self.arguments(parser)?;
+                self.call(name, arguments)?
+            }
+        };
+        Ok(Some(value))
+    }
+
+    fn call(&mut self, name: &str, mut arguments: Vec<Value>) -> Result<Value, BasicError> {
+        let count = arguments.len();
+        let number = |index: usize| -> Result<f64, BasicError> {
+            arguments.get(index).ok_or(BasicError::Syntax)?.number()
+        };
+        let int = |index: usize| -> Result<i64, BasicError> { Ok(number(index)?.round() as i64) };
+        let length = |index: usize| -> Result<usize, BasicError> {
+            match int(index)? {
+                length @ 0..=255 => Ok(length as usize),
+                _ => Err(BasicError::ImproperArgument),
+            }
+        };
+
+        let value = match (name, count) {
+            ("ABS", 1) => Value::Number(number(0)?.abs()),
+            ("INT", 1) => Value::Number(number(0)?.floor()),
+            ("FIX", 1) => Value::Number(number(0)?.trunc()),
+            ("CINT", 1) => Value::Number(number(0)?.round()),
+            ("SGN", 1) => Value::Number(match number(0)? {
+                value if value > 0.0 => 1.0,
+                value if value < 0.0 => -1.0,
+                _ => 0.0,
+            }),
+            ("SQR", 1) => match number(0)? {
+                value if value < 0.0 => return Err(BasicError::ImproperArgument),
+                value => Value::Number(value.sqrt()),
+            },
+            ("SIN", 1) => Value::Number(number(0)?.sin()),
+            ("COS", 1) => Value::Number(number(0)?.cos()),
+            ("TAN", 1) => Value::Number(number(0)?.tan()),
+            ("ATN", 1) => Value::Number(number(0)?.atan()),
+            ("EXP", 1) => Value::Number(number(0)?.exp()),
+            ("LOG" | "LOG10", 1) => match number(0)? {
+                value if value <= 0.0 => return Err(BasicError::ImproperArgument),
+                value if name == "LOG" => Value::Number(value.ln()),
+                value => Value::Number(value.log10()),
+            },
+            ("RND", 0 | 1) => Value::Number(rand::thread_rng().gen::<f64>()),
+            ("ROUND", 1) => Value::Number(number(0)?.round()),
+            ("ROUND", 2) => {
+                let scale = 10f64.powi(int(1)? as i32);
+                Value::Number((number(0)? * scale).round() / scale)
+            }
+            ("MAX" | "MIN", 1..) => {
+                let mut result = number(0)?;
+                for index in 1..count {
+                    let value = number(index)?;
+                    result = if name == "MAX" {
+                        result.max(value)
+                    } else {
+                        result.min(value)
+                    };
+                }
+                Value::Number(result)
+            }
+            ("TEST", 2) => {
+                let (x, y) = (graphics_coordinate(int(0)?)?, graphics_coordinate(int(1)?)?);
+                Value::Number(self.screen.test(x, y) as f64)
+            }
+            ("CHR$", 1) => match char::from_u32(int(0)? as u32) {
+                Some(c) => Value::Str(c.to_string()),
+                None => return Err(BasicError::ImproperArgument),
+            },
+            ("STR$", 1) => Value::Str(format_number(number(0)?)),
+            ("HEX$", 1) => Value::Str(format!("{:X}", int(0)?)),
+            ("BIN$", 1) => Value::Str(format!("{:b}", int(0)?)),
+            ("SPACE$", 1) => Value::Str(" ".repeat(length(0)?)),
+            ("STRING$", 2) => {
+                let count = length(0)?;
+                let c = match &arguments[1] {
+                    Value::Str(text) => text.chars().next().ok_or(BasicError::ImproperArgument)?,
+                    Value::Number(code) => {
+                        char::from_u32(*code as u32).ok_or(BasicError::ImproperArgument)?
+                    }
+                };
+                Value::Str(c.to_string().repeat(count))
+            }
+            ("LEN" | "ASC" | "VAL" | "UPPER$" | "LOWER$", 1) => {
+                let text = arguments.remove(0).text()?;
+                match name {
+                    "LEN" => Value::Number(text.chars().count() as f64),
+                    "ASC" => match text.chars().next() {
+                        Some(c) => Value::Number(c as u32 as f64),
+                        None => return Err(BasicError::ImproperArgument),
+                    },
+                    "VAL" => Value::Number(parse_number(&text)),
+                    "UPPER$" => Value::Str(text.to_uppercase()),
+                    _ => Value::Str(text.to_lowercase()),
+                }
+            }
+            ("LEFT$" | "RIGHT$", 2) => {
+                let length = length(1)?;
+                let chars: Vec<char> = arguments.remove(0).text()?.chars().collect();
+                let length = length.min(chars.len());
+                match name {
+                    "LEFT$" => Value::Str(chars[..length].iter().collect()),
+                    _ => Value::Str(chars[chars.len() - length..].iter().collect()),
+                }
+            }
+            ("MID$", 2 | 3) => {
+                let start = int(1)?;
+                let length = if count == 3 { length(2)? } else { 255 };
+                if !(1..=255).contains(&start) {
+                    return Err(BasicError::ImproperArgument);
+                }
+                let text = arguments.remove(0).text()?;
+                Value::Str(text.chars().skip(start as usize - 1).take(length).collect())
+            }
+            ("INSTR", 2 | 3) => {
+                let start = if count == 3 {
+                    int(0)?.max(1) as usize
+                } else {
+                    1
+                };
+                let find = arguments.pop().ok_or(BasicError::Syntax)?.text()?;
+                let text = arguments.pop().ok_or(BasicError::Syntax)?.text()?;
+                let chars: Vec<char> = text.chars().collect();
+                let find: Vec<char> = find.chars().collect();
+                let position = (start - 1..chars.len())
+                    .find(|index| chars[*index..].starts_with(&find))
+                    .map_or(0, |index| index + 1);
+                Value::Number(position as f64)
+            }
+            _ => return Err(BasicError::Syntax),
+        };
+        Ok(value)
+    }
+
+    /// Array indexes between parenthesis, after the name of an array.
+    fn indexes(&mut self, parser: &mut Parser) -> Result<Vec<usize>, BasicError> {
+        let mut indexes = Vec::new();
+        for value in self.arguments(parser)? {
+            match value.number()?.round() {
+                index if (0.0..=MAX_ARRAY_SIZE as f64).contains(&index) => {
+                    indexes.push(index as usize)
+                }
+                _ => return Err(BasicError::SubscriptOutOfRange),
+            }
+        }
+        Ok(indexes)
+    }
+
+    fn variable(&mut self, name: &str, parser: &mut Parser) -> Result<Value, BasicError> {
+        if parser.peek() == Some(&Token::LeftParen) {
+            let indexes = self.indexes(parser)?;
+            let offset = self.element_offset(name, &indexes)?;
+            return Ok(self.arrays[name].values[offset].clone());
+        }
+        Ok(self
+            .variables
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| default_value(name)))
+    }
+
+    fn dimension_array(&mut self, name: &str, dimensions: Vec<usize>) -> Result<(), BasicError> {
+        if self.arrays.contains_key(name) {
+            return Err(BasicError::ArrayAlreadyDimensioned);
+        }
+        let size = dimensions
+            .iter()
+            .try_fold(1usize, |size, dimension| {
+                size.checked_mul(dimension.checked_add(1)?)
+            })
+            .filter(|size| *size <= MAX_ARRAY_SIZE)
+            .ok_or(BasicError::SubscriptOutOfRange)?;
+        self.arrays.insert(
+            name.to_string(),
+            Array {
+                dimensions,
+                values: vec![default_value(name); size],
+            },
+        );
+        Ok(())
+    }
+
+    /// Index of an element in the values of the array. The array is created if it doesn't exist.
+    fn element_offset(&mut self, name: &str, indexes: &[usize]) -> Result<usize, BasicError> {
+        if !self.arrays.contains_key(name) {
+            self.dimension_array(name, vec![DEFAULT_ARRAY_SIZE; indexes.len()])?;
+        }
+        let array = &self.arrays[name];
+        if array.dimensions.len() != indexes.len() {
+            return Err(BasicError::SubscriptOutOfRange);
+        }
+        let mut offset = 0;
+        for (index, dimension) in indexes.iter().zip(&array.dimensions) {
+            if index > dimension {
+                return Err(BasicError::SubscriptOutOfRange);
+            }
+            let size = dimension
+                .checked_add(1)
+                .ok_or(BasicError::SubscriptOutOfRange)?;
+            offset = offset * size + index;
+        }
+        Ok(offset)
+    }
+
+    fn int_expression(&mut self, parser: &mut Parser) -> Result<i64, BasicError> {
+        Ok(self.expression(parser)?.number()?.round() as i64)
+    }
+
+    /// A pen, paper or ink number.
+    fn color_expression(&mut self, parser: &mut Parser) -> Result<usize, BasicError> {
+        match self.int_expression(parser)? {
+            color if color >= 0 => Ok(color as usize),
+            _ => Err(BasicError::ImproperArgument),
+        }
+    }
+
+    fn string_expression(&mut self, parser: &mut Parser) -> Result<String, BasicError> {
+        self.expression(parser)?.text()
+    }
+
+    // ********************************* Statements ********************************** //
+
+    fn target(&mut self, parser: &mut Parser) -> Result<Target, BasicError> {
+        let name = parser.name()?;
+        if is_function(&name) {
+            return Err(BasicError::Syntax);
+        }
+        if parser.peek() == Some(&Token::LeftParen) {
+            let indexes = self.indexes(parser)?;
+            return Ok(Target::Element(name, indexes));
+        }
+        Ok(Target::Variable(name))
+    }
+
+    fn assign(&mut self, target: &Target, value: Value) -> Result<(), BasicError> {
+        let value = match (is_string_name(target.name()), value) {
+            (true, Value::Str(text)) => Value::Str(text),
+            (false, Value::Number(number)) if target.name().ends_with('%') => {
+                Value::Number(number.round())
+            }
+            (false, Value::Number(number)) => Value::Number(number),
+            _ => return Err(BasicError::TypeMismatch),
+        };
+        match target {
+            Target::Variable(name) => {
+                self.variables.insert(name.clone(), value);
+            }
+            Target::Element(name, indexes) => {
+                let offset = self.element_offset(name, indexes)?;
+                if let Some(array) = self.arrays.get_mut(name) {
+                    array.values[offset] = value;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn assignment(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let target = self.target(parser)?;
+        parser.expect(&Token::Equal)?;
+        let value = self.expression(parser)?;
+        self.assign(&target, value)
+    }
+
+    fn print(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let mut new_line = true;
+
+        while !parser.at_end_of_statement() {
+            new_line = true;
+            if parser.accept(&Token::Semicolon) {
+                new_line = false;
+            } else if parser.accept(&Token::Comma) {
+                new_line = false;
+                self.screen.print_char(' ');
+                while !self.screen.get_cursor().0.is_multiple_of(PRINT_ZONE) {
+                    self.screen.print_char(' ');
+                }
+            } else if parser.accept_word("SPC") {
+                let arguments = self.arguments(parser)?;
+                let count = match arguments.as_slice() {
+                    [count] => count.number()?.max(0.0) as usize,
+                    _ => return Err(BasicError::Syntax),
+                };
+                self.screen.print(&" ".repeat(count));
+            } else if parser.accept_word("TAB") {
+                let arguments = self.arguments(parser)?;
+                let column = match arguments.as_slice() {
+                    [column] => (column.number()?.max(1.0) as usize) - 1,
+                    _ => return Err(BasicError::Syntax),
+                };
+                if self.screen.get_cursor().0 > column {
+                    self.screen.new_line();
+                }
+                while self.screen.get_cursor().0 < column {
+                    self.screen.print_char(' ');
+                }
+            } else {
+                let value = self.expression(parser)?;
+                self.screen.print(&value.to_print());
+            }
+        }
+
+        if new_line {
+            self.screen.new_line();
+        }
+        Ok(())
+    }
+
+    fn input(&mut self, parser: &mut Parser, whole_line: bool) -> Result<(), BasicError> {
+        let mut prompt = String::from("?");
+        if let Some(Token::Str(text)) = parser.peek() {
+            parser.next();
+            prompt = match parser.next() {
+                Some(Token::Semicolon) => format!("{}?", text),
+                Some(Token::Comma) => text.clone(),
+                _ => return Err(BasicError::Syntax),
+            };
+        }
+        if !prompt.ends_with(' ') && prompt.ends_with('?') {
+            prompt.push(' ');
+        }
+
+        let mut targets = vec![self.target(parser)?];
+        while !whole_line && parser.accept(&Token::Comma) {
+            targets.push(self.target(parser)?);
+        }
+        if whole_line && !is_string_name(targets[0].name()) {
+            return Err(BasicError::TypeMismatch);
+        }
+
+        self.input_targets = targets;
+        self.input_prompt = prompt;
+        self.screen.print(&self.input_prompt.clone());
+        self.state = State::Input;
+        Ok(())
+    }
+
+    fn answer_input(&mut self, line: &str) {
+        let targets = std::mem::take(&mut self.input_targets);
+        let answers: Vec<&str> = match targets.len() {
+            1 => vec![line],
+            _ => line.split(',').collect(),
+        };
+
+        let values: Option<Vec<Value>> = match answers.len() == targets.len() {
+            true => targets
+                .iter()
+                .zip(&answers)
+                .map(|(target, answer)| match is_string_name(target.name()) {
+                    true => Some(Value::Str(answer.to_string())),
+                    false => answer.trim().parse::<f64>().ok().map(Value::Number),
+                })
+                .collect(),
+            false => None,
+        };
+
+        let Some(values) = values else {
+            self.screen.print_line("?Redo from start");
+            self.screen.print(&self.input_prompt.clone());
+            self.input_targets = targets;
+            return;
+        };
+
+        self.state = State::Running;
+        for (target, value) in targets.iter().zip(values) {
+            if let Err(error) = self.assign(target, value) {
+                self.report_error(error);
+                return;
+            }
+        }
+    }
+
+    fn if_statement(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let condition = self.expression(parser)?.number()? != 0.0;
+
+        if !parser.accept_word("THEN") && !parser.peek_word("GOTO") {
+            return Err(BasicError::Syntax);
+        }
+
+        if !condition {
+            // Looks for an ELSE, the rest of the line is skipped otherwise
+            while parser.peek().is_some() && !parser.peek_word("ELSE") {
+                parser.next();
+            }
+            if !parser.accept_word("ELSE") {
+                parser.skip_to_end_of_line();
+                return Ok(());
+            }
+        }
+
+        // THEN 100 and ELSE 100 are GOTOs
+        if let Some(Token::Number(_)) = parser.peek() {
+            let line = parser.line_number()?;
+            return self.goto(line);
+        }
+
+        // The statements after THEN or ELSE are executed as the rest of the line
+        self.jump = Some(self.here(parser));
+        Ok(())
+    }
+
+    fn for_statement(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let variable = parser.name()?;
+        if is_string_name(&variable) {
+            return Err(BasicError::TypeMismatch);
+        }
+        parser.expect(&Token::Equal)?;
+        let start = self.expression(parser)?.number()?;
+        parser.expect_word("TO")?;
+        let end = self.expression(parser)?.number()?;
+        let step = match parser.accept_word("STEP") {
+            true => self.expression(parser)?.number()?,
+            false => 1.0,
+        };
+
+        self.assign(&Target::Variable(variable.clone()), Value::Number(start))?;
+        self.for_stack
+            .retain(|for_loop| for_loop.variable != variable);
+
+        let start = self.variables[&variable].number()?;
+        if (step >= 0.0 && start > end) || (step < 0.0 && start < end) {
+            return self.skip_loop(parser);
+        }
+
+        if self.for_stack.len() >= MAX_STACK_SIZE {
+            return Err(BasicError::MemoryFull);
+        }
+        self.for_stack.push(ForLoop {
+            variable,
+            end,
+            step,
+            body: self.here(parser),
+        });
+        Ok(())
+    }
+
+    /// Moves after the NEXT of a loop that runs zero times.
+    fn skip_loop(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let mut depth = 0;
+        let mut line = self.location.line;
+        let mut tokens = self.current_tokens()?;
+        let mut position = parser.position;
+
+        loop {
+            while position < tokens.len() {
+                match &tokens[position] {
+                    Token::Word(word) if word == "FOR" => depth += 1,
+                    Token::Word(word) if word == "NEXT" && depth == 0 => {
+                        let mut after = Parser {
+                            tokens: &tokens,
+                            position: position + 1,
+                        };
+                        // NEXT I, J closes the loop of J when skipping the one of I
+                        if let Some(Token::Word(_)) = after.peek() {
+                            after.next();
+                        }
+                        self.jump = Some(Location {
+                            line,
+                            position: after.position,
+                        });
+                        return Ok(());
+                    }
+                    Token::Word(word) if word == "NEXT" => depth -= 1,
+                    _ => (),
+                }
+                position += 1;
+            }
+
+            line = line.and_then(|line| self.next_line(line));
+            let Some(number) = line else {
+                return Err(BasicError::Syntax);
+            };
+            tokens = self.program[&number].tokens.clone();
+            position = 0;
+        }
+    }
+
+    fn next_statement(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        loop {
+            let variable = match parser.peek() {
+                Some(Token::Word(name)) if name != "ELSE" => Some(parser.name()?),
+                _ => None,
+            };
+
+            let index = match &variable {
+                Some(variable) => self
+                    .for_stack
+                    .iter()
+                    .rposition(|for_loop| &for_loop.variable == variable),
+                None => self.for_stack.len().checked_sub(1),
+            }
+            .ok_or(BasicError::UnexpectedNext)?;
+            self.for_stack.truncate(index + 1);
+
+            let for_loop = &self.for_stack[index];
+            let (name, end, step, body) = (
+                for_loop.variable.clone(),
+                for_loop.end,
+                for_loop.step,
+                for_loop.body,
+            );
+            let value = self
+                .variables
+                .get(&name)
+                .cloned()
+                .unwrap_or(Value::Number(0.0));
+            let value = value.number()? + step;
+            self.assign(&Target::Variable(name), Value::Number(value))?;
+
+            if (step >= 0.0 && value <= end) || (step < 0.0 && value >= end) {
+                self.jump = Some(body);
+                return Ok(());
+            }
+
+            self.for_stack.pop();
+            if !parser.accept(&Token::Comma) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn on_statement(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let choice = self.int_expression(parser)?;
+        let gosub = match parser.name()?.as_str() {
+            "GOTO" => false,
+            "GOSUB" => true,
+            _ => return Err(BasicError::Syntax),
+        };
+
+        let mut lines = vec![parser.line_number()?];
+        while parser.accept(&Token::Comma) {
+            lines.push(parser.line_number()?);
+        }
+
+        // Out of range choices continue with the next statement
+        let Some(line) = usize::try_from(choice - 1)
+            .ok()
+            .and_then(|index| lines.get(index))
+        else {
+            return Ok(());
+        };
+        match gosub {
+            true => self.gosub(*line, parser),
+            false => self.goto(*line),
+        }
+    }
+
+    fn dim(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        loop {
+            let name = parser.name()?;
+            if parser.peek() != Some(&Token::LeftParen) {
+                return Err(BasicError::Syntax);
+            }
+            let dimensions = self.indexes(parser)?;
+            self.dimension_array(&name, dimensions)?;
+            if !parser.accept(&Token::Comma) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Collects the values of the DATA statements of the program, for READ.
+    fn collect_data(&mut self) {
+        self.data.clear();
+        self.data_pointer = 0;
+
+        for (number, line) in &self.program {
+            let mut tokens = line.tokens.iter();
+            while let Some(token) = tokens.next() {
+                if *token != Token::Word(String::from("DATA")) {
+                    continue;
+                }
+                let mut item: Vec<&Token> = Vec::new();
+                loop {
+                    match tokens.next() {
+                        None | Some(Token::Colon) => {
+                            self.data.push((*number, data_value(&item)));
+                            break;
+                        }
+                        Some(Token::Comma) => {
+                            self.data.push((*number, data_value(&item)));
+                            item.clear();
+                        }
+                        Some(token) => item.push(token),
+                    }
+                }
+            }
+        }
+    }
+
+    fn read(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        loop {
+            let target = self.target(parser)?;
+            let (_, value) = self
+                .data
+                .get(self.data_pointer)
+                .cloned()
+                .ok_or(BasicError::DataExhausted)?;
+            self.data_pointer += 1;
+
+            let value = match (is_string_name(target.name()), value) {
+                (true, Value::Number(number)) => {
+                    Value::Str(format_number(number).trim().to_string())
+                }
+                (false, Value::Str(text)) => match text.trim().parse::<f64>() {
+                    Ok(number) => Value::Number(number),
+                    Err(_) => return Err(BasicError::TypeMismatch),
+                },
+                (_, value) => value,
+            };
+            self.assign(&target, value)?;
+
+            if !parser.accept(&Token::Comma) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn graphics_statement(&mut self, keyword: &str, parser: &mut Parser) -> Result<(), BasicError> {
+        let mut x = graphics_coordinate(self.int_expression(parser)?)?;
+        parser.expect(&Token::Comma)?;
+        let mut y = graphics_coordinate(self.int_expression(parser)?)?;
+        if parser.accept(&Token::Comma) {
+            let pen = self.color_expression(parser)?;
+            self.screen.set_graphics_pen(pen);
+        }
+
+        if keyword.ends_with('R') {
+            let (cursor_x, cursor_y) = self.screen.get_graphics_cursor();
+            x = graphics_coordinate((x + cursor_x) as i64)?;
+            y = graphics_coordinate((y + cursor_y) as i64)?;
+        }
+
+        match keyword {
+            "PLOT" | "PLOTR" => self.screen.plot(x, y),
+            "DRAW" | "DRAWR" => self.screen.draw_to(x, y),
+            _ => self.screen.move_to(x, y),
+        }
+        Ok(())
+    }
+
+    /// SOUND channel, period, duration: the period is converted to a frequency the way the
+    /// CPC's sound chip does, the duration is in 1/100 of a second. Volume and envelopes
+    /// are accepted but the square wave generator plays them all the same.
+    fn sound(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let mut arguments = vec![self.int_expression(parser)?];
+        while parser.accept(&Token::Comma) {
+            arguments.push(self.int_expression(parser)?);
+        }
+        if arguments.len() < 2 {
+            return Err(BasicError::Syntax);
+        }
+
+        let period = arguments[1];
+        let duration = arguments.get(2).copied().unwrap_or(20);
+        if !(0..=4095).contains(&period) || duration < 0 {
+            return Err(BasicError::ImproperArgument);
+        }
+
+        let frequency = match period {
+            0 => None,
+            period => Some(125000.0 / period as f32),
+        };
+        // At 60 beats per minute, a note lasts one second
+        play::play(60.0, vec![(frequency, duration as f32 / 100.0)], Vec::new());
+        Ok(())
+    }
+
+    // ********************************* Program ************************************* //
+
+    fn list(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let mut from = 0;
+        let mut to = u16::MAX;
+        if let Some(Token::Number(_)) = parser.peek() {
+            from = parser.line_number()?;
+            to = from;
+        }
+        if parser.accept(&Token::Minus) {
+            to = match parser.peek() {
+                Some(Token::Number(_)) => parser.line_number()?,
+                _ => u16::MAX,
+            };
+        }
+
+        self.screen.end_line();
+        for (number, line) in self.program.range(from..=to.max(from)) {
+            self.screen.print_line(&format!("{} {}", number, line.text));
+        }
+        Ok(())
+    }
+
+    fn run_statement(&mut self, parser: &mut Parser) -> Result<(), BasicError> {
+        let mut start = None;
+        match parser.peek() {
+            Some(Token::Number(_)) => start = Some(parser.line_number()?),
+            Some(Token::Str(_)) => {
+                let name = self.string_expression(parser)?;
+                self.load(&name)?;
+            }
+            _ => (),
+        }
+
+        self.clear_variables();
+        self.collect_data();
+        self.keys.clear();
+
+        match start {
+            Some(line) => self.goto(line),
+            None => {
+                match self.program.keys().next() {
+                    Some(first) => {
+                        self.jump = Some(Location {
+                            line: Some(*first),
+                            position: 0,
+                        })
+                    }
+                    None => {
+                        parser.skip_to_end_of_line();
+                        self.ready();
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn file_system(&self) -> Result<FileSystemHandle, BasicError> {
+        self.file_system
+            .clone()
+            .ok_or(BasicError::Disc(String::from("No disc")))
+    }
+
+    /// Reads a program saved as text, ex: by SAVE. The current program is replaced.
+    fn load(&mut self, name: &str) -> Result<(), BasicError> {
+        let file_system = self.file_system()?;
+        let file_system = file_system.borrow();
+        // GAME finds GAME.BAS, or GAME if there is no GAME.BAS
+        let text = file_system
+            .read_to_string(&program_file_name(name))
+            .or_else(|error| match program_file_name(name) != name {
+                true => file_system.read_to_string(name),
+                false => Err(error),
+            })
+            .map_err(|error| BasicError::Disc(error.to_string()))?;
+
+        let mut program = BTreeMap::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (number, text) = split_line_number(line).ok_or(BasicError::BadFile)?;
+            program.insert(
+                number,
+                ProgramLine {
+                    text: text.to_string(),
+                    tokens: tokenize(text).into(),
+                },
+            );
+        }
+
+        self.program = program;
+        self.clear_variables();
+        Ok(())
+    }
+
+    /// Saves the program as text with CR LF line endings, so it can be loaded
+    /// on a CPC with LOAD or opened with a text editor.
+    fn save(&mut self, name: &str) -> Result<(), BasicError> {
+        let text: String = self
+            .program
+            .iter()
+            .map(|(number, line)| format!("{} {}\r\n", number, line.text))
+            .collect();
+
+        self.file_system()?
+            .borrow_mut()
+            .write(&program_file_name(name), text.as_bytes())
+            .map_err(|error| BasicError::Disc(error.to_string()))
+    }
+
+    /// Lists the files of the current directory.
+    fn cat(&mut self) -> Result<(), BasicError> {
+        let file_system = self.file_system()?;
+        let file_system = file_system.borrow();
+        let entries = file_system
+            .read_dir("")
+            .map_err(|error| BasicError::Disc(error.to_string()))?;
+
+        self.screen.end_line();
+        self.screen.print_line(&file_system.get_current_dir());
+        for entry in entries.iter().filter(|entry| !entry.is_dir) {
+            self.screen.print_line(&format!(
+                "{:<14}{:>4}K",
+                entry.name,
+                entry.size.div_ceil(1024)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Functions that take their arguments between parenthesis.
+fn is_function(name: &str) -> bool {
+    const FUNCTIONS: &[&str] = &[
+        "ABS", "INT", "FIX", "CINT", "SGN", "SQR", "SIN", "COS", "TAN", "ATN", "EXP", "LOG",
+        "LOG10", "RND", "ROUND", "MAX", "MIN", "TEST", "CHR$", "STR$", "HEX$", "BIN$", "SPACE$",
+        "STRING$", "LEN", "ASC", "VAL", "UPPER$", "LOWER$", "LEFT$", "RIGHT$", "MID$", "INSTR",
+    ];
+    FUNCTIONS.contains(&name)
+}
+
+/// VAL: the number at the start of the text, 0 if there is none.
+fn parse_number(text: &str) -> f64 {
+    let text = text.trim_start();
+    (1..=text.len())
+        .rev()
+        .filter(|end| text.is_char_boundary(*end))
+        .find_map(|end| text[..end].parse::<f64>().ok())
+        .unwrap_or(0.0)
+}
+
+/// One item of a DATA statement: a number, a quoted string or unquoted text.
+fn data_value(tokens: &[&Token]) -> Value {
+    match tokens {
+        [Token::Number(number)] => Value::Number(*number),
+        [Token::Minus, Token::Number(number)] => Value::Number(-number),
+        [Token::Str(text)] => Value::Str(text.clone()),
+        tokens => Value::Str(
+            tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Word(word) => word.clone(),
+                    Token::Number(number) => format_number(*number).trim().to_string(),
+                    Token::Str(text) => text.clone(),
+                    _ => String::new(),
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+        ),
+    }
+}
+
+/// Adds .BAS to names without extension.
+fn program_file_name(name: &str) -> String {
+    let file_name = name.rsplit(['/', '\\', ':']).next().unwrap_or(name);
+    match file_name.contains('.') {
+        true => name.to_string(),
+        false => format!("{}{}", name, PROGRAM_EXTENSION),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Enters the lines and runs until the interpreter is ready again, returning the first
+    /// error instead of printing it.
+    fn execute(interpreter: &mut Interpreter, lines: &[&str]) -> Result<(), BasicError> {
+        for line in lines {
+            interpreter.enter_line(line);
+            while interpreter.state == State::Running {
+                interpreter.step()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_program(lines: &[&str]) -> Result<Interpreter, BasicError> {
+        let mut interpreter = Interpreter::new();
+        execute(&mut interpreter, lines)?;
+        execute(&mut interpreter, &["RUN"])?;
+        Ok(interpreter)
+    }
+
+    fn variable(interpreter: &Interpreter, name: &str) -> Value {
+        interpreter.variables.get(name).cloned().unwrap()
+    }
+
+    fn evaluate(expression: &str) -> Result<Value, BasicError> {
+        let mut interpreter = Interpreter::new();
+        execute(&mut interpreter, &[&format!("R = {}", expression)])?;
+        Ok(variable(&interpreter, "R"))
+    }
+
+    fn evaluate_string(expression: &str) -> Result<Value, BasicError> {
+        let mut interpreter = Interpreter::new();
+        execute(&mut interpreter, &[&format!("R$ = {}", expression)])?;
+        Ok(variable(&interpreter, "R$"))
+    }
+
+    #[test]
+    fn operator_precedence() {
+        assert_eq!(evaluate("2 + 3 * 4"), Ok(Value::Number(14.0)));
+        assert_eq!(evaluate("(2 + 3) * 4"), Ok(Value::Number(20.0)));
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), Ok(Value::Number(512.0)));
+        assert_eq!(evaluate("-2 ^ 2"), Ok(Value::Number(-4.0)));
+        assert_eq!(evaluate("7 \\ 2 * 2"), Ok(Value::Number(1.0)));
+        assert_eq!(evaluate("10 MOD 4 + 1"), Ok(Value::Number(3.0)));
+        assert_eq!(evaluate("1 + 2 = 3 AND 2 < 1"), Ok(Value::Number(0.0)));
+        assert_eq!(evaluate("NOT 0 OR 0"), Ok(Value::Number(-1.0)));
+    }
+
+    #[test]
+    fn for_next_loops() {
+        let interpreter = run_program(&[
+            "10 S = 0",
+            "20 FOR I = 1 TO 10 STEP 2",
+            "30 FOR J = 3 TO 1 STEP -1: S = S + J: NEXT J",
+            "40 NEXT I",
+        ])
+        .unwrap();
+        assert_eq!(variable(&interpreter, "S"), Value::Number(30.0));
+        assert_eq!(variable(&interpreter, "I"), Value::Number(11.0));
+    }
+
+    #[test]
+    fn gosub_and_return() {
+        let interpreter = run_program(&[
+            "10 N = 0",
+            "20 GOSUB 100: GOSUB 100",
+            "30 ON 2 GOSUB 100, 200",
+            "40 END",
+            "100 N = N + 1: RETURN",
+            "200 N = N * 10: RETURN",
+        ])
+        .unwrap();
+        assert_eq!(variable(&interpreter, "N"), Value::Number(20.0));
+        assert!(interpreter.gosub_stack.is_empty());
+    }
+
+    #[test]
+    fn string_functions() {
+        let text = |value: &str| Ok(Value::Str(value.to_string()));
+        assert_eq!(evaluate_string("LEFT$(\"HELLO\", 2)"), text("HE"));
+        assert_eq!(evaluate_string("RIGHT$(\"HELLO\", 3)"), text("LLO"));
+        assert_eq!(evaluate_string("MID$(\"HELLO\", 2, 3)"), text("ELL"));
+        assert_eq!(evaluate_string("UPPER$(\"a\") + LOWER$(\"B\")"), text("Ab"));
+        assert_eq!(evaluate("LEN(\"HELLO\")"), Ok(Value::Number(5.0)));
+        assert_eq!(evaluate("INSTR(\"HELLO\", \"L\")"), Ok(Value::Number(3.0)));
+        assert_eq!(evaluate("ASC(\"A\")"), Ok(Value::Number(65.0)));
+        assert_eq!(evaluate("ASC(\"\")"), Err(BasicError::ImproperArgument));
+        assert_eq!(evaluate("LEN(1)"), Err(BasicError::TypeMismatch));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(evaluate("1 / 0"), Err(BasicError::DivisionByZero));
+        assert_eq!(evaluate("1 \\ 0"), Err(BasicError::DivisionByZero));
+        assert_eq!(evaluate("1 MOD 0"), Err(BasicError::DivisionByZero));
+    }
+
+    #[test]
+    fn integer_overflow() {
+        assert_eq!(evaluate("-1E30 \\ -1"), Err(BasicError::Overflow));
+        assert_eq!(evaluate("-1E30 MOD -1"), Err(BasicError::Overflow));
+    }
+
+    #[test]
+    fn subscript_out_of_range() {
+        let error = run_program(&["10 DIM A(5)", "20 A(6) = 1"]).err();
+        assert_eq!(error, Some(BasicError::SubscriptOutOfRange));
+        let error = run_program(&["10 DIM A(5)", "20 X = A(-1)"]).err();
+        assert_eq!(error, Some(BasicError::SubscriptOutOfRange));
+    }
+
+    #[test]
+    fn bad_locate() {
+        let mut interpreter = Interpreter::new();
+        let error = execute(&mut interpreter, &["LOCATE 0, 1"]);
+        assert_eq!(error, Err(BasicError::ImproperArgument));
+        let mut interpreter = Interpreter::new();
+        execute(&mut interpreter, &["LOCATE 1000, 1000"]).unwrap();
+    }
+
+    #[test]
+    fn deep_expressions_are_an_error() {
+        let depth = MAX_EXPRESSION_NESTING - 2;
+        let nested = format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(evaluate(&nested), Ok(Value::Number(1.0)));
+
+        let depth = 100_000;
+        let nested = format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(evaluate(&nested), Err(BasicError::MemoryFull));
+        let negated = format!("{}1", "-".repeat(depth));
+        assert_eq!(evaluate(&negated), Err(BasicError::MemoryFull));
+        let not = format!("{}1", "NOT ".repeat(depth));
+        assert_eq!(evaluate(&not), Err(BasicError::MemoryFull));
+    }
+
+    #[test]
+    fn gosub_and_for_stacks_are_limited() {
+        let error = run_program(&["10 GOSUB 10"]).err();
+        assert_eq!(error, Some(BasicError::MemoryFull));
+        let error = run_program(&["10 GOSUB 20", "20 FOR I = 1 TO 2: GOSUB 10"]).err();
+        assert_eq!(error, Some(BasicError::MemoryFull));
+    }
+}
@@ -0,0 +1,176 @@
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Str(String),
+    /// Keywords, functions and variable names, in capitals.
+    /// Variable names keep their $ or % suffix.
+    Word(String),
+    /// Comment after REM or ', up to the end of the line
+    Rem(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Backslash,
+    Caret,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    LeftParen,
+    RightParen,
+    Comma,
+    Semicolon,
+    Colon,
+    Hash,
+    /// A character that has no meaning in BASIC, reported as a syntax error when executed
+    Other(char),
+}
+
+/// Splits a line of BASIC into tokens.
+/// A string that isn't closed ends with the line, like on the CPC.
+pub fn tokenize(line: &str) -> Vec<Token> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+
+        let token = match c {
+            ' ' | '\t' => continue,
+            '"' => {
+                let start = index;
+                while index < chars.len() && chars[index] != '"' {
+                    index += 1;
+                }
+                let text: String = chars[start..index].iter().collect();
+                index += 1;
+                Token::Str(text)
+            }
+            '\'' => {
+                let comment: String = chars[index..].iter().collect();
+                index = chars.len();
+                Token::Rem(comment)
+            }
+            '0'..='9' | '.' => {
+                let start = index - 1;
+                while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.')
+                {
+                    index += 1;
+                }
+                // Exponent, ex: 1.5E3
+                if index + 1 < chars.len()
+                    && chars[index].eq_ignore_ascii_case(&'e')
+                    && (chars[index + 1].is_ascii_digit() || "+-".contains(chars[index + 1]))
+                {
+                    index += 2;
+                    while index < chars.len() && chars[index].is_ascii_digit() {
+                        index += 1;
+                    }
+                }
+                let text: String = chars[start..index].iter().collect();
+                match text.parse::<f64>() {
+                    Ok(value) => Token::Number(value),
+                    Err(_) => Token::Other(c),
+                }
+            }
+            '&' => {
+                // Hexadecimal &FF or binary &X1010
+                let radix = match chars.get(index) {
+                    Some('x' | 'X') => {
+                        index += 1;
+                        2
+                    }
+                    Some('h' | 'H') => {
+                        index += 1;
+                        16
+                    }
+                    _ => 16,
+                };
+                let start = index;
+                while index < chars.len() && chars[index].is_digit(radix) {
+                    index += 1;
+                }
+                let text: String = chars[start..index].iter().collect();
+                match i64::from_str_radix(&text, radix) {
+                    Ok(value) => Token::Number(value as f64),
+                    Err(_) => Token::Other(c),
+                }
+            }
+            c if c.is_ascii_alphabetic() => {
+                let start = index - 1;
+                while index < chars.len()
+                    && (chars[index].is_ascii_alphanumeric() || chars[index] == '.')
+                {
+                    index += 1;
+                }
+                if index < chars.len() && (chars[index] == '$' || chars[index] == '%') {
+                    index += 1;
+                }
+                let word: String = chars[start..index]
+                    .iter()
+                    .collect::<String>()
+                    .to_uppercase();
+                if word == "REM" {
+                    let comment: String = chars[index..].iter().collect();
+                    index = chars.len();
+                    Token::Rem(comment)
+                } else {
+                    Token::Word(word)
+                }
+            }
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '\\' => Token::Backslash,
+            '^' => Token::Caret,
+            '=' => Token::Equal,
+            '<' => match chars.get(index) {
+                Some('>') => {
+                    index += 1;
+                    Token::NotEqual
+                }
+                Some('=') => {
+                    index += 1;
+                    Token::LessEqual
+                }
+                _ => Token::Less,
+            },
+            '>' => match chars.get(index) {
+                Some('=') => {
+                    index += 1;
+                    Token::GreaterEqual
+                }
+                _ => Token::Greater,
+            },
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            ':' => Token::Colon,
+            '#' => Token::Hash,
+            other => Token::Other(other),
+        };
+
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// Splits "10 PRINT A" into its line number and the rest of the line.
+/// Returns None if the line doesn't start with a number.
+pub fn split_line_number(line: &str) -> Option<(u16, &str)> {
+    let line = line.trim_start();
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    let number = line[..digits].parse::<u16>().ok()?;
+    Some((number, line[digits..].trim()))
+}
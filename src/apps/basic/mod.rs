@@ -0,0 +1,4 @@
+pub mod app;
+pub mod interpreter;
+pub mod lexer;
+pub mod screen;
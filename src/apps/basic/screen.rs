@@ -0,0 +1,300 @@
+use fantasy_cpc_display_controller::{
+    color_palettes::{DEFAULT_COLOR_PALETTE, PALETE_SIZE, TRUE_BLUE, YELLOW},
+    config::{TEXT_COLUMNS, TEXT_ROWS, VIRTUAL_WIDTH},
+    text_layer::text_layer::{text_coord_to_frame_coord, TextLayerChar},
+    DisplayController,
+};
+
+const DEFAULT_PEN: usize = YELLOW;
+const DEFAULT_PAPER: usize = TRUE_BLUE;
+
+/// Size of the graphics area in pixels, it's under the text area
+const CANVAS_WIDTH: usize = TEXT_COLUMNS * 8;
+const CANVAS_HEIGHT: usize = TEXT_ROWS * 8;
+
+/// Size of the CPC graphics screen in graphics coordinates, (0, 0) being the bottom left corner.
+/// PLOT and DRAW use those coordinates, scaled down to the canvas.
+const GRAPHICS_WIDTH: isize = 640;
+const GRAPHICS_HEIGHT: isize = 400;
+
+/// Width of the print zones used when items are separated by commas in PRINT
+pub const PRINT_ZONE: usize = 13;
+
+/// What BASIC programs draw on: a text grid over a graphics canvas, with the pen,
+/// paper and ink settings of the CPC firmware.
+/// Text scrolls the whole screen up, graphics included, like on the CPC.
+pub struct Screen {
+    cells: Vec<Option<TextLayerChar>>,
+    cursor: (usize, usize),
+    pen: usize,
+    paper: usize,
+    border: usize,
+    canvas: Vec<usize>,
+    graphics_cursor: (isize, isize),
+    graphics_pen: usize,
+    /// Color shown for each pen, changed with INK
+    inks: [usize; PALETE_SIZE],
+    inks_changed: bool,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen {
+            cells: vec![None; TEXT_COLUMNS * TEXT_ROWS],
+            cursor: (0, 0),
+            pen: DEFAULT_PEN,
+            paper: DEFAULT_PAPER,
+            border: DEFAULT_PAPER,
+            canvas: vec![DEFAULT_PAPER; CANVAS_WIDTH * CANVAS_HEIGHT],
+            graphics_cursor: (0, 0),
+            graphics_pen: DEFAULT_PEN,
+            inks: std::array::from_fn(|pen| pen),
+            inks_changed: true,
+        }
+    }
+
+    /// MODE: clears the screen and brings back the default pens and inks.
+    /// The text layer always has the same number of columns, whatever the mode.
+    pub fn reset(&mut self) {
+        *self = Screen::new();
+    }
+
+    pub fn get_cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// LOCATE, coordinates start at 0 here, they are clamped to the screen.
+    pub fn set_cursor(&mut self, x: usize, y: usize) {
+        self.cursor = (x.min(TEXT_COLUMNS - 1), y.min(TEXT_ROWS - 1));
+    }
+
+    pub fn set_pen(&mut self, pen: usize) {
+        self.pen = pen % PALETE_SIZE;
+    }
+
+    pub fn set_paper(&mut self, paper: usize) {
+        self.paper = paper % PALETE_SIZE;
+    }
+
+    pub fn set_border(&mut self, border: usize) {
+        self.border = border % PALETE_SIZE;
+    }
+
+    pub fn set_graphics_pen(&mut self, pen: usize) {
+        self.graphics_pen = pen % PALETE_SIZE;
+    }
+
+    pub fn set_ink(&mut self, pen: usize, color: usize) {
+        self.inks[pen % PALETE_SIZE] = color % PALETE_SIZE;
+        self.inks_changed = true;
+    }
+
+    /// CLS: clears the text and the graphics with the paper color.
+    pub fn clear(&mut self) {
+        self.cells.fill(None);
+        self.canvas.fill(self.paper);
+        self.cursor = (0, 0);
+    }
+
+    /// CLG: clears the graphics only.
+    pub fn clear_graphics(&mut self) {
+        self.canvas.fill(self.paper);
+    }
+
+    pub fn print_char(&mut self, c: char) {
+        match c {
+            '\r' | '\n' => {
+                self.new_line();
+                return;
+            }
+            c if c.is_control() => return,
+            _ => (),
+        }
+
+        if self.cursor.0 >= TEXT_COLUMNS {
+            self.new_line();
+        }
+
+        self.cells[self.cursor.1 * TEXT_COLUMNS + self.cursor.0] = Some(TextLayerChar {
+            c,
+            color: self.pen,
+            bkg_color: self.paper,
+            swap: false,
+            blink: false,
+            shadowed: false,
+        });
+        self.cursor.0 += 1;
+    }
+
+    pub fn print(&mut self, text: &str) {
+        for c in text.chars() {
+            self.print_char(c);
+        }
+    }
+
+    pub fn print_line(&mut self, text: &str) {
+        self.print(text);
+        self.new_line();
+    }
+
+    pub fn new_line(&mut self) {
+        self.cursor.0 = 0;
+        if self.cursor.1 + 1 < TEXT_ROWS {
+            self.cursor.1 += 1;
+        } else {
+            self.scroll_up();
+        }
+    }
+
+    /// Moves the cursor to the next line if it isn't at the start of one.
+    pub fn end_line(&mut self) {
+        if self.cursor.0 > 0 {
+            self.new_line();
+        }
+    }
+
+    fn scroll_up(&mut self) {
+        self.cells.drain(0..TEXT_COLUMNS);
+        self.cells.resize(TEXT_COLUMNS * TEXT_ROWS, None);
+        self.canvas.drain(0..CANVAS_WIDTH * 8);
+        self.canvas.resize(CANVAS_WIDTH * CANVAS_HEIGHT, self.paper);
+    }
+
+    /// Converts CPC graphics coordinates to a pixel of the canvas.
+    fn to_canvas(x: isize, y: isize) -> (isize, isize) {
+        (
+            x * CANVAS_WIDTH as isize / GRAPHICS_WIDTH,
+            CANVAS_HEIGHT as isize - 1 - y * CANVAS_HEIGHT as isize / GRAPHICS_HEIGHT,
+        )
+    }
+
+    fn set_canvas_pixel(&mut self, x: isize, y: isize) {
+        if (0..CANVAS_WIDTH as isize).contains(&x) && (0..CANVAS_HEIGHT as isize).contains(&y) {
+            self.canvas[y as usize * CANVAS_WIDTH + x as usize] = self.graphics_pen;
+        }
+    }
+
+    pub fn get_graphics_cursor(&self) -> (isize, isize) {
+        self.graphics_cursor
+    }
+
+    /// MOVE
+    pub fn move_to(&mut self, x: isize, y: isize) {
+        self.graphics_cursor = (x, y);
+    }
+
+    /// PLOT
+    pub fn plot(&mut self, x: isize, y: isize) {
+        self.graphics_cursor = (x, y);
+        let (x, y) = Screen::to_canvas(x, y);
+        self.set_canvas_pixel(x, y);
+    }
+
+    /// DRAW, a line from the graphics cursor.
+    pub fn draw_to(&mut self, x: isize, y: isize) {
+        let (mut x1, mut y1) = Screen::to_canvas(self.graphics_cursor.0, self.graphics_cursor.1);
+        let (x2, y2) = Screen::to_canvas(x, y);
+        self.graphics_cursor = (x, y);
+
+        let dx = (x2 - x1).abs();
+        let dy = -(y2 - y1).abs();
+        let step_x = if x1 < x2 { 1 } else { -1 };
+        let step_y = if y1 < y2 { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        loop {
+            self.set_canvas_pixel(x1, y1);
+            if x1 == x2 && y1 == y2 {
+                break;
+            }
+            let double_error = 2 * error;
+            if double_error >= dy {
+                error += dy;
+                x1 += step_x;
+            }
+            if double_error <= dx {
+                error += dx;
+                y1 += step_y;
+            }
+        }
+    }
+
+    /// TEST, the pen of a pixel, the paper outside of the screen.
+    pub fn test(&self, x: isize, y: isize) -> usize {
+        let (x, y) = Screen::to_canvas(x, y);
+        if (0..CANVAS_WIDTH as isize).contains(&x) && (0..CANVAS_HEIGHT as isize).contains(&y) {
+            self.canvas[y as usize * CANVAS_WIDTH + x as usize]
+        } else {
+            self.paper
+        }
+    }
+
+    /// Copies the screen to the display controller. The line being typed is shown
+    /// at the text cursor, with a blinking cursor if show_cursor is true.
+    pub fn render(
+        &mut self,
+        display_controller: &mut DisplayController,
+        input_line: &[char],
+        input_cursor_offset: usize,
+        show_cursor: bool,
+    ) {
+        let (canvas_x, canvas_y) = text_coord_to_frame_coord(0, 0);
+        let frame = display_controller.get_frame_mut();
+        for (row, canvas_row) in self.canvas.chunks(CANVAS_WIDTH).enumerate() {
+            let start = (canvas_y + row) * VIRTUAL_WIDTH + canvas_x;
+            frame[start..start + CANVAS_WIDTH].copy_from_slice(canvas_row);
+        }
+        display_controller.set_overscan_color(self.border);
+
+        if self.inks_changed {
+            self.inks_changed = false;
+            display_controller.clear_raster_interrupts();
+            let inks = self.inks;
+            display_controller.add_raster_interrupt(
+                0,
+                Box::new(move |_line, raster_state| {
+                    for (pen, color) in inks.iter().enumerate() {
+                        raster_state.set_ink(pen, DEFAULT_COLOR_PALETTE[*color]);
+                    }
+                }),
+            );
+        }
+
+        let text_layer = display_controller.get_text_layer_mut();
+        let char_map = text_layer.get_char_map_mut();
+        char_map.copy_from_slice(&self.cells);
+
+        if !show_cursor {
+            return;
+        }
+
+        // The line being typed, wrapped, followed by the cursor
+        let start = self.cursor.1 * TEXT_COLUMNS + self.cursor.0;
+        let cursor_index = input_line.len() - input_cursor_offset.min(input_line.len());
+        for (index, c) in input_line.iter().chain(std::iter::once(&' ')).enumerate() {
+            if let Some(cell) = char_map.get_mut(start + index) {
+                *cell = Some(TextLayerChar {
+                    c: *c,
+                    color: self.pen,
+                    bkg_color: self.paper,
+                    swap: index == cursor_index,
+                    blink: false,
+                    shadowed: false,
+                });
+            }
+        }
+    }
+
+    /// Installs the raster interrupt of the inks again at the next render, the display
+    /// controller drops it when another app has the focus.
+    pub fn reinstall_inks(&mut self) {
+        self.inks_changed = true;
+    }
+
+    /// Puts the inks back to their default colors, used when leaving BASIC.
+    pub fn restore_inks(&mut self, display_controller: &mut DisplayController) {
+        display_controller.clear_raster_interrupts();
+        self.inks = std::array::from_fn(|pen| pen);
+        self.inks_changed = true;
+    }
+}
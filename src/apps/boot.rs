@@ -1,11 +1,12 @@
-use fantasy_cpc_app::{AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams};
+use fantasy_cpc_app::{
+    input::{InputState, Key},
+    AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams,
+};
 use fantasy_cpc_display_controller::{
     color_palettes::{BLACK, WHITE},
     DisplayController,
 };
 use std::time::Duration;
-use winit::event::VirtualKeyCode;
-use winit_input_helper::WinitInputHelper;
 
 use crate::sound::{notes::*, play::play};
 
@@ -18,7 +19,7 @@ pub struct Boot {
 impl Boot {
     pub fn new() -> Boot {
         Self {
-            app_params: FantasyCppAppDefaultParams::new(String::from("reboot"), true),
+            app_params: FantasyCppAppDefaultParams::new(String::from("boot"), true),
             frame_count: 0,
             starting_time: Duration::new(0, 0),
         }
@@ -52,14 +53,14 @@ impl FantasyCpcApp for Boot {
 
     fn update_app(
         &mut self,
-        inputs: Option<&WinitInputHelper>,
+        inputs: Option<&InputState>,
         clock: &fantasy_cpc_clock::Clock,
     ) -> Option<fantasy_cpc_app::AppResponse> {
         if clock.total_running_time - self.starting_time >= Duration::new(6, 0) {
             self.get_app_params().change_status(AppStatus::Stopped);
         }
 
-        if inputs.is_some() && inputs.unwrap().key_pressed(VirtualKeyCode::Escape) {
+        if inputs.is_some() && inputs.unwrap().key_pressed(Key::Escape) {
             self.get_app_params().change_status(AppStatus::Stopped);
         }
 
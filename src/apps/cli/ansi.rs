@@ -0,0 +1,327 @@
+use fantasy_cpc_display_controller::color_palettes::*;
+
+/// The 16 ANSI colors (normal then bright) on the palette
+const ANSI_COLORS: [usize; 16] = [
+    BLACK,
+    DARK_RED,
+    DARK_GREEN,
+    ORANGE,
+    TRUE_BLUE,
+    DARK_PURPLE,
+    BLUE_GREEN,
+    LIGHT_GREY,
+    DARK_GREY,
+    RED,
+    GREEN,
+    YELLOW,
+    LAVENDER,
+    PINK,
+    BLUE,
+    WHITE,
+];
+
+/// Parameters kept for a CSI sequence, longer sequences are dropped
+const MAX_PARAMS: usize = 16;
+
+/// Intensity of the 6 levels of the 256 colors cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// What a character sent to the terminal means once escape sequences are decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnsiAction {
+    /// A character to display, or ENTER and BACKSPACE
+    Print(char),
+    /// ESC [ params final, ex: ESC [ 1 ; 31 m
+    Csi { params: Vec<u16>, final_char: char },
+    /// ESC 7
+    SaveCursor,
+    /// ESC 8
+    RestoreCursor,
+    /// ESC c, back to the initial state
+    Reset,
+}
+
+enum State {
+    Ground,
+    Escape,
+    Csi,
+}
+
+/// Decodes the ANSI/VT100 escape sequences of a stream of characters.
+/// CR LF counts as a single ENTER, a lone LF is an ENTER too.
+///
+/// In TTY mode, control characters act like on a real terminal: CR goes back to
+/// the first column, LF goes to the next row, BS moves the cursor left without
+/// erasing and TAB moves it to the next tab stop.
+pub struct AnsiParser {
+    state: State,
+    params: Vec<u16>,
+    current_param: Option<u16>,
+    /// The sequence has more than MAX_PARAMS parameters
+    too_many_params: bool,
+    after_enter: bool,
+    tty: bool,
+}
+
+impl AnsiParser {
+    pub const fn new() -> AnsiParser {
+        AnsiParser {
+            state: State::Ground,
+            params: Vec::new(),
+            current_param: None,
+            too_many_params: false,
+            after_enter: false,
+            tty: false,
+        }
+    }
+
+    pub fn set_tty(&mut self, tty: bool) {
+        self.tty = tty;
+    }
+
+    /// A cursor movement standing for a control character.
+    fn control(final_char: char) -> Option<AnsiAction> {
+        Some(AnsiAction::Csi {
+            params: Vec::new(),
+            final_char,
+        })
+    }
+
+    fn push_param(&mut self, param: u16) {
+        match self.params.len() < MAX_PARAMS {
+            true => self.params.push(param),
+            false => self.too_many_params = true,
+        }
+    }
+
+    /// Takes the next character, returns an action once a character or a sequence is complete.
+    pub fn feed(&mut self, c: char) -> Option<AnsiAction> {
+        match self.state {
+            State::Ground => {
+                let after_enter = self.after_enter;
+                self.after_enter = c == unicode::ENTER;
+                match c {
+                    unicode::ESCAPE => {
+                        self.state = State::Escape;
+                        None
+                    }
+                    unicode::ENTER if self.tty => Self::control('G'),
+                    '\u{0008}' if self.tty => Self::control('D'),
+                    unicode::TAB if self.tty => Self::control('I'),
+                    '\n' if after_enter && !self.tty => None,
+                    '\n' => Some(AnsiAction::Print(unicode::ENTER)),
+                    // Bell
+                    '\u{0007}' => None,
+                    _ => Some(AnsiAction::Print(c)),
+                }
+            }
+            State::Escape => {
+                self.state = State::Ground;
+                match c {
+                    '[' => {
+                        self.state = State::Csi;
+                        self.params.clear();
+                        self.current_param = None;
+                        self.too_many_params = false;
+                        None
+                    }
+                    '7' => Some(AnsiAction::SaveCursor),
+                    '8' => Some(AnsiAction::RestoreCursor),
+                    'c' => Some(AnsiAction::Reset),
+                    _ => None,
+                }
+            }
+            State::Csi => match c {
+                '0'..='9' => {
+                    let digit = c as u16 - '0' as u16;
+                    let param = self.current_param.unwrap_or(0);
+                    self.current_param = Some(param.saturating_mul(10).saturating_add(digit));
+                    None
+                }
+                ';' => {
+                    let param = self.current_param.take().unwrap_or(0);
+                    self.push_param(param);
+                    None
+                }
+                // Private sequences like ESC [ ? 25 l are read but not told apart
+                '?' | '>' | '=' | ' ' => None,
+                '\u{0040}'..='\u{007E}' => {
+                    self.state = State::Ground;
+                    if let Some(param) = self.current_param.take() {
+                        self.push_param(param);
+                    }
+                    if self.too_many_params {
+                        self.params.clear();
+                        return None;
+                    }
+                    Some(AnsiAction::Csi {
+                        params: std::mem::take(&mut self.params),
+                        final_char: c,
+                    })
+                }
+                // Not a valid sequence, dropped
+                _ => {
+                    self.state = State::Ground;
+                    None
+                }
+            },
+        }
+    }
+}
+
+/// Color of the palette closest to an RGB color.
+pub fn nearest_color(r: u8, g: u8, b: u8) -> usize {
+    let distance = |(pr, pg, pb): (u8, u8, u8)| {
+        let dr = pr as i32 - r as i32;
+        let dg = pg as i32 - g as i32;
+        let db = pb as i32 - b as i32;
+        dr * dr + dg * dg + db * db
+    };
+
+    (0..PALETE_SIZE)
+        .min_by_key(|color| distance(DEFAULT_COLOR_PALETTE[*color]))
+        .unwrap_or(BLACK)
+}
+
+/// Color of the palette for a color of the 256 colors mode, the first 16 being the ANSI colors.
+pub fn ansi_color(index: u16) -> usize {
+    match index {
+        0..=15 => ANSI_COLORS[index as usize],
+        16..=231 => {
+            let cube = index - 16;
+            nearest_color(
+                CUBE_LEVELS[(cube / 36) as usize],
+                CUBE_LEVELS[(cube / 6 % 6) as usize],
+                CUBE_LEVELS[(cube % 6) as usize],
+            )
+        }
+        _ => {
+            let level = (8 + 10 * (index.min(255) - 232)) as u8;
+            nearest_color(level, level, level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(parser: &mut AnsiParser, text: &str) -> Vec<AnsiAction> {
+        text.chars().filter_map(|c| parser.feed(c)).collect()
+    }
+
+    fn parse(text: &str) -> Vec<AnsiAction> {
+        feed(&mut AnsiParser::new(), text)
+    }
+
+    fn csi(params: &[u16], final_char: char) -> AnsiAction {
+        AnsiAction::Csi {
+            params: params.to_vec(),
+            final_char,
+        }
+    }
+
+    #[test]
+    fn sgr() {
+        assert_eq!(
+            parse("\u{1b}[1;31mA"),
+            vec![csi(&[1, 31], 'm'), AnsiAction::Print('A')]
+        );
+        assert_eq!(parse("\u{1b}[m"), vec![csi(&[], 'm')]);
+        assert_eq!(parse("\u{1b}[;4m"), vec![csi(&[0, 4], 'm')]);
+        assert_eq!(
+            parse("\u{1b}[38;2;255;128;0m"),
+            vec![csi(&[38, 2, 255, 128, 0], 'm')]
+        );
+        assert_eq!(parse("\u{1b}[99999m"), vec![csi(&[u16::MAX], 'm')]);
+    }
+
+    #[test]
+    fn cursor_movement() {
+        assert_eq!(parse("\u{1b}[10;5H"), vec![csi(&[10, 5], 'H')]);
+        assert_eq!(
+            parse("\u{1b}[3A\u{1b}[C"),
+            vec![csi(&[3], 'A'), csi(&[], 'C')]
+        );
+        assert_eq!(parse("\u{1b}[?25l"), vec![csi(&[25], 'l')]);
+        assert_eq!(
+            parse("\u{1b}7\u{1b}8\u{1b}c"),
+            vec![
+                AnsiAction::SaveCursor,
+                AnsiAction::RestoreCursor,
+                AnsiAction::Reset
+            ]
+        );
+    }
+
+    #[test]
+    fn sequences_can_be_split_between_feeds() {
+        let mut parser = AnsiParser::new();
+        assert_eq!(feed(&mut parser, "a\u{1b}"), vec![AnsiAction::Print('a')]);
+        assert_eq!(feed(&mut parser, "[1"), vec![]);
+        assert_eq!(feed(&mut parser, "2;"), vec![]);
+        assert_eq!(
+            feed(&mut parser, "3Hb"),
+            vec![csi(&[12, 3], 'H'), AnsiAction::Print('b')]
+        );
+    }
+
+    #[test]
+    fn too_many_params_drop_the_sequence() {
+        let params = vec!["1"; MAX_PARAMS].join(";");
+        assert_eq!(
+            parse(&format!("\u{1b}[{}m", params)),
+            vec![csi(&[1; MAX_PARAMS], 'm')]
+        );
+
+        let params = vec!["1"; MAX_PARAMS * 100].join(";");
+        let mut parser = AnsiParser::new();
+        assert_eq!(feed(&mut parser, &format!("\u{1b}[{}m", params)), vec![]);
+        assert!(parser.params.len() <= MAX_PARAMS);
+        assert_eq!(
+            feed(&mut parser, "\u{1b}[2mx"),
+            vec![csi(&[2], 'm'), AnsiAction::Print('x')]
+        );
+    }
+
+    #[test]
+    fn invalid_sequences_are_dropped() {
+        assert_eq!(parse("\u{1b}[1\u{7}x"), vec![AnsiAction::Print('x')]);
+        assert_eq!(parse("\u{1b}Zx"), vec![AnsiAction::Print('x')]);
+    }
+
+    #[test]
+    fn line_endings() {
+        let enter = AnsiAction::Print(unicode::ENTER);
+        assert_eq!(
+            parse("a\r\nb\n"),
+            vec![
+                AnsiAction::Print('a'),
+                enter.clone(),
+                AnsiAction::Print('b'),
+                enter
+            ]
+        );
+
+        let mut parser = AnsiParser::new();
+        parser.set_tty(true);
+        assert_eq!(
+            feed(&mut parser, "\r\n\u{8}\t"),
+            vec![
+                csi(&[], 'G'),
+                AnsiAction::Print(unicode::ENTER),
+                csi(&[], 'D'),
+                csi(&[], 'I')
+            ]
+        );
+    }
+
+    #[test]
+    fn ansi_colors() {
+        assert_eq!(ansi_color(1), DARK_RED);
+        assert_eq!(ansi_color(15), WHITE);
+        assert_eq!(ansi_color(16), BLACK);
+        assert_eq!(ansi_color(231), nearest_color(255, 255, 255));
+        assert_eq!(ansi_color(1000), ansi_color(255));
+    }
+}
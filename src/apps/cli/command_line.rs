@@ -0,0 +1,422 @@
+use std::{collections::BTreeMap, fmt, iter::Peekable, str::Chars};
+
+use fantasy_cpc_app::AppResponse;
+
+use super::shell::Shell;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TokenizeError {
+    UnterminatedQuote(char),
+    TrailingEscape,
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizeError::UnterminatedQuote(quote) => write!(f, "Missing closing {}", quote),
+            TokenizeError::TrailingEscape => write!(f, "Nothing to escape after \\"),
+        }
+    }
+}
+
+/// Splits a command line into words separated by spaces.
+/// - $NAME and ${NAME} are replaced with the value of the shell variable, unknown variables
+///   are empty. The value stays in its word, spaces, quotes and \ in it aren't interpreted.
+/// - Text between double quotes is kept in one word, variables are replaced and
+///   \", \\ and \$ can be used inside.
+/// - Text between single quotes is kept as is, with no escapes or variables.
+/// - Outside of quotes, \ escapes the next character, so "\ " is a space inside a word.
+///
+/// Quoted and unquoted parts next to each other make a single word: a"b c" gives [ab c].
+pub fn tokenize(
+    line: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<Vec<String>, TokenizeError> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => {
+                if let Some(token) = current.take() {
+                    tokens.push(token);
+                }
+            }
+            '\\' => {
+                let escaped = chars.next().ok_or(TokenizeError::TrailingEscape)?;
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            '$' => {
+                // An empty variable out of quotes makes no word
+                let value = expand_variable(&mut chars, variables);
+                if !value.is_empty() {
+                    current.get_or_insert_with(String::new).push_str(&value);
+                }
+            }
+            '"' => {
+                let token = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ ('"' | '\\' | '$')) => token.push(escaped),
+                            Some(other) => {
+                                token.push('\\');
+                                token.push(other);
+                            }
+                            None => return Err(TokenizeError::UnterminatedQuote('"')),
+                        },
+                        Some('$') => token.push_str(&expand_variable(&mut chars, variables)),
+                        Some(other) => token.push(other),
+                        None => return Err(TokenizeError::UnterminatedQuote('"')),
+                    }
+                }
+            }
+            '\'' => {
+                let token = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(other) => token.push(other),
+                        None => return Err(TokenizeError::UnterminatedQuote('\'')),
+                    }
+                }
+            }
+            _ => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(token) = current {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Reads NAME or {NAME} after a $ and returns the value of the shell variable,
+/// empty for an unknown variable. Anything else isn't a variable and is returned as typed.
+fn expand_variable(chars: &mut Peekable<Chars>, variables: &BTreeMap<String, String>) -> String {
+    let braces = chars.next_if_eq(&'{').is_some();
+    let mut name = String::new();
+    while let Some(name_char) = chars.next_if(|next| next.is_ascii_alphanumeric() || *next == '_') {
+        name.push(name_char);
+    }
+    let closed = !braces || chars.next_if_eq(&'}').is_some();
+
+    if name.is_empty() || !closed {
+        let opening = if braces { "${" } else { "$" };
+        return format!("{}{}", opening, name);
+    }
+
+    variables
+        .get(&name.to_uppercase())
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub enum ArgType {
+    /// A whole number between min and max, both included.
+    Int { min: i64, max: i64 },
+    /// Any word.
+    Text,
+    /// One of the listed words, case insensitive.
+    Choice(&'static [&'static str]),
+    /// All the remaining words, joined with spaces. Only for the last argument.
+    Rest,
+}
+
+/// Declares one argument of a command.
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub arg_type: ArgType,
+    pub optional: bool,
+}
+
+impl ArgSpec {
+    fn usage(&self) -> String {
+        let name = match self.arg_type {
+            ArgType::Rest => format!("{}...", self.name),
+            _ => self.name.to_string(),
+        };
+        if self.optional {
+            format!("[{}]", name)
+        } else {
+            format!("<{}>", name)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgValue {
+    Int(i64),
+    Text(String),
+}
+
+/// The arguments of a command, parsed and checked against its ArgSpecs.
+pub struct Args {
+    values: Vec<(&'static str, ArgValue)>,
+}
+
+impl Args {
+    pub fn get(&self, name: &str) -> Option<&ArgValue> {
+        self.values
+            .iter()
+            .find(|(arg_name, _)| *arg_name == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.get(name) {
+            Some(ArgValue::Int(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_text(&self, name: &str) -> Option<&str> {
+        match self.get(name) {
+            Some(ArgValue::Text(value)) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+pub type CommandHandler = fn(&mut Shell, &Args) -> AppResponse;
+
+/// A command known by the shell.
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: &'static [ArgSpec],
+    pub help: &'static str,
+    pub handler: CommandHandler,
+}
+
+impl Command {
+    pub fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+
+    /// One line summary of the command and its arguments, ex: "dist <level>".
+    pub fn usage(&self) -> String {
+        let mut usage = String::from(self.name);
+        for arg in self.args {
+            usage.push(' ');
+            usage.push_str(&arg.usage());
+        }
+        usage
+    }
+
+    /// Checks the words following the command name against the command's ArgSpecs.
+    /// The error message is meant to be printed to the user.
+    pub fn parse_args(&self, words: &[String]) -> Result<Args, String> {
+        let takes_rest = matches!(
+            self.args.last(),
+            Some(ArgSpec {
+                arg_type: ArgType::Rest,
+                ..
+            })
+        );
+        if words.len() > self.args.len() && !takes_rest {
+            return Err(format!("Too many arguments\u{000D}Usage: {}", self.usage()));
+        }
+
+        let mut values = Vec::new();
+
+        for (index, spec) in self.args.iter().enumerate() {
+            let word = match words.get(index) {
+                Some(word) => word,
+                None if spec.optional => continue,
+                None => {
+                    return Err(format!(
+                        "Missing {}\u{000D}Usage: {}",
+                        spec.name,
+                        self.usage()
+                    ))
+                }
+            };
+
+            let value = match spec.arg_type {
+                ArgType::Int { min, max } => match word.parse::<i64>() {
+                    Ok(value) if (min..=max).contains(&value) => ArgValue::Int(value),
+                    _ => {
+                        return Err(format!(
+                            "{} must be a number from {} to {}\u{000D}Usage: {}",
+                            spec.name,
+                            min,
+                            max,
+                            self.usage()
+                        ))
+                    }
+                },
+                ArgType::Text => ArgValue::Text(word.clone()),
+                ArgType::Rest => ArgValue::Text(words[index..].join(" ")),
+                ArgType::Choice(choices) => {
+                    match choices.iter().find(|choice| choice.eq_ignore_ascii_case(word)) {
+                        Some(choice) => ArgValue::Text(choice.to_string()),
+                        None => {
+                            return Err(format!(
+                                "{} must be one of {}\u{000D}Usage: {}",
+                                spec.name,
+                                choices.join(", "),
+                                self.usage()
+                            ))
+                        }
+                    }
+                }
+            };
+
+            values.push((spec.name, value));
+        }
+
+        Ok(Args { values })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (String::from("X"), String::from("a b")),
+            (String::from("Q"), String::from("\"it's\" \\n")),
+        ])
+    }
+
+    fn words(line: &str) -> Result<Vec<String>, TokenizeError> {
+        tokenize(line, &variables())
+    }
+
+    fn expected(words: &[&str]) -> Result<Vec<String>, TokenizeError> {
+        Ok(strings(words))
+    }
+
+    fn handler(_shell: &mut Shell, _args: &Args) -> AppResponse {
+        AppResponse::new()
+    }
+
+    const ARGS: &[ArgSpec] = &[
+        ArgSpec {
+            name: "size",
+            arg_type: ArgType::Int { min: 1, max: 10 },
+            optional: false,
+        },
+        ArgSpec {
+            name: "mode",
+            arg_type: ArgType::Choice(&["on", "off"]),
+            optional: true,
+        },
+    ];
+
+    const COMMAND: Command = Command {
+        name: "test",
+        aliases: &["t"],
+        args: ARGS,
+        help: "",
+        handler,
+    };
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn words_are_split_on_spaces() {
+        assert_eq!(words("  echo\ta   b "), expected(&["echo", "a", "b"]));
+        assert_eq!(words(""), expected(&[]));
+    }
+
+    #[test]
+    fn quotes_keep_spaces() {
+        assert_eq!(words("a\"b c\"d 'e f'"), expected(&["ab cd", "e f"]));
+        assert_eq!(words("\"\" ''"), expected(&["", ""]));
+        assert_eq!(words("'\"' \"'\""), expected(&["\"", "'"]));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(words("a\\ b \\'"), expected(&["a b", "'"]));
+        assert_eq!(words("\"\\\" \\\\ \\n\""), expected(&["\" \\ \\n"]));
+        assert_eq!(words("'\\n'"), expected(&["\\n"]));
+    }
+
+    #[test]
+    fn unterminated_quotes_and_escapes_are_errors() {
+        assert_eq!(words("\"a"), Err(TokenizeError::UnterminatedQuote('"')));
+        assert_eq!(words("'a"), Err(TokenizeError::UnterminatedQuote('\'')));
+        assert_eq!(words("a\\"), Err(TokenizeError::TrailingEscape));
+    }
+
+    #[test]
+    fn variables_are_expanded_in_their_word() {
+        assert_eq!(words("echo $X"), expected(&["echo", "a b"]));
+        assert_eq!(words("${x}c"), expected(&["a bc"]));
+        assert_eq!(words("\"don't $X\""), expected(&["don't a b"]));
+        assert_eq!(words("$Q"), expected(&["\"it's\" \\n"]));
+    }
+
+    #[test]
+    fn variables_are_not_expanded_when_quoted_or_escaped() {
+        assert_eq!(words("'$X' \\$X \"\\$X\""), expected(&["$X", "$X", "$X"]));
+        assert_eq!(words("$ ${X $1a"), expected(&["$", "${X"]));
+    }
+
+    #[test]
+    fn undefined_variables_are_empty() {
+        assert_eq!(words("a $NOPE b"), expected(&["a", "b"]));
+        assert_eq!(words("\"$NOPE\""), expected(&[""]));
+    }
+
+    #[test]
+    fn expand_variable_reads_one_name() {
+        let mut chars = "X_1-".chars().peekable();
+        let variables = BTreeMap::from([(String::from("X_1"), String::from("v"))]);
+        assert_eq!(expand_variable(&mut chars, &variables), "v");
+        assert_eq!(chars.next(), Some('-'));
+
+        let mut chars = "{X".chars().peekable();
+        assert_eq!(expand_variable(&mut chars, &variables), "${X");
+    }
+
+    #[test]
+    fn arguments_are_parsed_by_type() {
+        let args = COMMAND.parse_args(&strings(&["3", "ON"])).unwrap();
+        assert_eq!(args.get_int("size"), Some(3));
+        assert_eq!(args.get_text("mode"), Some("on"));
+
+        let args = COMMAND.parse_args(&strings(&["10"])).unwrap();
+        assert_eq!(args.get("mode"), None);
+    }
+
+    #[test]
+    fn wrong_arguments_are_errors() {
+        let error = |words: &[&str]| COMMAND.parse_args(&strings(words)).err().unwrap();
+        assert!(error(&[]).starts_with("Missing size"));
+        assert!(error(&["1", "on", "x"]).starts_with("Too many arguments"));
+        assert!(error(&["0"]).starts_with("size must be a number from 1 to 10"));
+        assert!(error(&["two"]).starts_with("size must be a number"));
+        assert!(error(&["1", "maybe"]).starts_with("mode must be one of on, off"));
+        assert!(error(&[]).ends_with("Usage: test <size> [mode]"));
+    }
+
+    #[test]
+    fn rest_takes_the_remaining_words() {
+        const REST: &[ArgSpec] = &[ArgSpec {
+            name: "text",
+            arg_type: ArgType::Rest,
+            optional: false,
+        }];
+        let command = Command {
+            args: REST,
+            ..COMMAND
+        };
+        let args = command.parse_args(&strings(&["a", "b c"])).unwrap();
+        assert_eq!(args.get_text("text"), Some("a b c"));
+        assert_eq!(command.usage(), "test <text...>");
+    }
+}
@@ -0,0 +1,92 @@
+use fantasy_cpc_app::input::{InputState, Key, TextChar};
+use unicode::keys;
+
+/// Keys sent as escape sequences, like a VT100 keyboard, and their character.
+/// F12 belongs to the system, it switches apps.
+const SPECIAL_KEYS: [(Key, char); 24] = [
+    (Key::Return, unicode::ENTER),
+    (Key::Tab, unicode::TAB),
+    (Key::Escape, unicode::ESCAPE),
+    (Key::Up, keys::UP),
+    (Key::Down, keys::DOWN),
+    (Key::Right, keys::RIGHT),
+    (Key::Left, keys::LEFT),
+    (Key::Home, keys::HOME),
+    (Key::End, keys::END),
+    (Key::Insert, keys::INSERT),
+    (Key::Delete, keys::DELETE),
+    (Key::PageUp, keys::PAGE_UP),
+    (Key::PageDown, keys::PAGE_DOWN),
+    (Key::F1, keys::F1),
+    (Key::F2, keys::F2),
+    (Key::F3, keys::F3),
+    (Key::F4, keys::F4),
+    (Key::F5, keys::F5),
+    (Key::F6, keys::F6),
+    (Key::F7, keys::F7),
+    (Key::F8, keys::F8),
+    (Key::F9, keys::F9),
+    (Key::F10, keys::F10),
+    (Key::F11, keys::F11),
+];
+
+/// Letters that give a control character with Ctrl, from A to Z
+const CONTROL_LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+/// What was typed since the last frame, as the bytes a terminal would send
+/// to a program: UTF-8 text, control characters and escape sequences.
+pub fn get_typed_bytes(inputs: &InputState) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    if inputs.held_control() {
+        for (letter, key) in ('A'..='Z').zip(CONTROL_LETTERS) {
+            if let (true, Some(control)) = (inputs.key_pressed_os(key), keys::control(letter)) {
+                bytes.push(control as u8);
+            }
+        }
+    } else {
+        for text_char in inputs.text() {
+            match text_char {
+                // Enter, Tab, Escape and Delete are sent from the keys below
+                TextChar::Char(c) if c.is_control() => (),
+                TextChar::Char(c) => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                TextChar::Back => bytes.push(0x7F),
+            }
+        }
+    }
+
+    for (key, c) in SPECIAL_KEYS {
+        if let (true, Some(sequence)) = (inputs.key_pressed_os(key), keys::vt100_sequence(c)) {
+            bytes.extend_from_slice(sequence.as_bytes());
+        }
+    }
+
+    bytes
+}
@@ -0,0 +1,354 @@
+use std::{cell::RefCell, rc::Rc};
+
+use fantasy_cpc_vfs::{FileSystem, VfsError};
+
+/// Maximum number of commands kept in the history and in the history file
+const MAX_HISTORY_SIZE: usize = 100;
+
+/// Result of a tab completion
+pub enum Completion {
+    /// Nothing starts with the word under the cursor
+    None,
+    /// The word was completed, or extended to the longest common prefix of the candidates
+    Completed,
+    /// Several candidates and nothing more to complete, the list is meant to be shown to the user
+    Ambiguous(Vec<String>),
+}
+
+/// Lines typed, from the oldest to the last one. Line editors can share it,
+/// like the windows of the shell.
+pub type History = Rc<RefCell<Vec<String>>>;
+
+/// The line being typed in the shell: the characters, the position of the cursor
+/// inside it, and the history of the previous lines.
+pub struct LineEditor {
+    line: Vec<char>,
+    cursor: usize,
+    history: History,
+    history_index: Option<usize>,
+    edited_line: Vec<char>,
+    max_length: usize,
+}
+
+impl LineEditor {
+    pub fn new() -> LineEditor {
+        LineEditor::with_history(History::default())
+    }
+
+    /// A line editor that adds the lines to that history, and goes through it with
+    /// history_up() and history_down().
+    pub fn with_history(history: History) -> LineEditor {
+        LineEditor {
+            line: Vec::new(),
+            cursor: 0,
+            history,
+            history_index: None,
+            edited_line: Vec::new(),
+            max_length: usize::MAX,
+        }
+    }
+
+    pub fn get_history(&self) -> History {
+        self.history.clone()
+    }
+
+    pub fn get_line(&self) -> &[char] {
+        &self.line
+    }
+
+    /// Number of characters between the cursor and the end of the line
+    pub fn get_cursor_offset(&self) -> usize {
+        self.line.len() - self.cursor
+    }
+
+    /// Longest line that can be typed, a longer line is cut.
+    pub fn set_max_length(&mut self, max_length: usize) {
+        self.max_length = max_length;
+        self.cut_to_max_length();
+    }
+
+    fn cut_to_max_length(&mut self) {
+        self.line.truncate(self.max_length);
+        self.cursor = self.cursor.min(self.line.len());
+    }
+
+    /// Inserts at the cursor, nothing is inserted when the line is full.
+    pub fn insert(&mut self, c: char) {
+        if self.line.len() >= self.max_length {
+            return;
+        }
+        self.line.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    /// Deletes the character before the cursor
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.line.remove(self.cursor);
+        }
+    }
+
+    /// Deletes the character under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.line.len() {
+            self.line.remove(self.cursor);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.line.len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.line.len();
+    }
+
+    /// Deletes everything from the cursor to the end of the line
+    pub fn kill_to_end(&mut self) {
+        self.line.truncate(self.cursor);
+    }
+
+    /// Deletes everything from the start of the line to the cursor
+    pub fn kill_to_start(&mut self) {
+        self.line.drain(0..self.cursor);
+        self.cursor = 0;
+    }
+
+    /// Empties the line without adding it to the history
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.cursor = 0;
+        self.history_index = None;
+    }
+
+    /// Returns the line and empties it, adding it to the history if it isn't empty
+    /// or the same as the last line of the history.
+    pub fn take_line(&mut self) -> String {
+        let line: String = self.line.iter().collect();
+        self.clear();
+
+        let mut history = self.history.borrow_mut();
+        if !line.trim().is_empty() && history.last() != Some(&line) {
+            history.push(line.clone());
+            if history.len() > MAX_HISTORY_SIZE {
+                history.remove(0);
+            }
+        }
+        drop(history);
+
+        line
+    }
+
+    /// Replaces the line with the previous one in the history.
+    /// The line being typed is kept, to come back to it with history_down().
+    pub fn history_up(&mut self) {
+        let history_len = self.history.borrow().len();
+        let index = match self.history_index {
+            None if history_len == 0 => return,
+            None => {
+                self.edited_line = self.line.clone();
+                history_len - 1
+            }
+            // load_history() can empty the history while going through it
+            Some(_) if history_len == 0 => {
+                self.history_down();
+                return;
+            }
+            Some(index) => index.min(history_len - 1).saturating_sub(1),
+        };
+
+        self.history_index = Some(index);
+        let line = self.history.borrow()[index].chars().collect();
+        self.set_line(line);
+    }
+
+    /// Replaces the line with the next one in the history, or with the line
+    /// that was being typed when reaching the end of the history.
+    pub fn history_down(&mut self) {
+        let history_len = self.history.borrow().len();
+        match self.history_index {
+            None => (),
+            Some(index) if index + 1 < history_len => {
+                self.history_index = Some(index + 1);
+                let line = self.history.borrow()[index + 1].chars().collect();
+                self.set_line(line);
+            }
+            Some(_) => {
+                self.history_index = None;
+                let edited_line = std::mem::take(&mut self.edited_line);
+                self.set_line(edited_line);
+            }
+        }
+    }
+
+    fn set_line(&mut self, line: Vec<char>) {
+        self.line = line;
+        self.cursor = self.line.len();
+        self.cut_to_max_length();
+    }
+
+    /// Completes the first word of the line with the candidates starting with it.
+    /// Only the first word, the command or app name, is completed, and only
+    /// when the cursor is at its end.
+    pub fn complete(&mut self, candidates: &[&str]) -> Completion {
+        let word_end = self
+            .line
+            .iter()
+            .position(|c| *c == ' ')
+            .unwrap_or(self.line.len());
+
+        if self.cursor != word_end {
+            return Completion::None;
+        }
+
+        let word: String = self.line[..word_end].iter().collect();
+        let mut matches: Vec<&str> = candidates
+            .iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&word.to_lowercase()))
+            .copied()
+            .collect();
+        matches.sort_unstable();
+        matches.dedup();
+
+        let Some(first) = matches.first() else {
+            return Completion::None;
+        };
+
+        let common_prefix: String = first
+            .chars()
+            .enumerate()
+            .take_while(|(index, c)| {
+                matches
+                    .iter()
+                    .all(|candidate| candidate.chars().nth(*index) == Some(*c))
+            })
+            .map(|(_, c)| c)
+            .collect();
+
+        let mut completion: Vec<char> = common_prefix.chars().collect();
+        if matches.len() == 1 && word_end == self.line.len() {
+            completion.push(' ');
+        }
+
+        if matches.len() > 1 && completion.len() <= word_end {
+            return Completion::Ambiguous(matches.iter().map(|m| m.to_string()).collect());
+        }
+
+        self.cursor = completion.len();
+        self.line.splice(0..word_end, completion);
+        self.cut_to_max_length();
+        Completion::Completed
+    }
+
+    /// Loads the history saved by save_history(), missing files are ignored.
+    pub fn load_history(&mut self, file_system: &FileSystem, path: &str) {
+        if let Ok(content) = file_system.read_to_string(path) {
+            let mut history = self.history.borrow_mut();
+            *history = content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(String::from)
+                .collect();
+            let excess = history.len().saturating_sub(MAX_HISTORY_SIZE);
+            history.drain(0..excess);
+        }
+    }
+
+    /// Writes the history to a text file, one command per line.
+    pub fn save_history(&self, file_system: &mut FileSystem, path: &str) -> Result<(), VfsError> {
+        let mut content = self.history.borrow().join("\n");
+        content.push('\n');
+        file_system.write(path, content.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fantasy_cpc_vfs::host_drive::HostDrive;
+
+    use super::*;
+
+    fn type_line(editor: &mut LineEditor, text: &str) -> String {
+        text.chars().for_each(|c| editor.insert(c));
+        editor.take_line()
+    }
+
+    fn line(editor: &LineEditor) -> String {
+        editor.get_line().iter().collect()
+    }
+
+    #[test]
+    fn history_goes_up_and_down() {
+        let mut editor = LineEditor::new();
+        type_line(&mut editor, "one");
+        type_line(&mut editor, "two");
+        editor.insert('x');
+
+        editor.history_up();
+        assert_eq!(line(&editor), "two");
+        editor.history_up();
+        editor.history_up();
+        assert_eq!(line(&editor), "one");
+        editor.history_down();
+        assert_eq!(line(&editor), "two");
+        editor.history_down();
+        assert_eq!(line(&editor), "x");
+    }
+
+    #[test]
+    fn empty_and_repeated_lines_are_not_kept() {
+        let mut editor = LineEditor::new();
+        type_line(&mut editor, "a");
+        type_line(&mut editor, "a");
+        type_line(&mut editor, "  ");
+        assert_eq!(*editor.get_history().borrow(), vec![String::from("a")]);
+    }
+
+    #[test]
+    fn history_can_be_emptied_while_going_through_it() {
+        let mut editor = LineEditor::new();
+        type_line(&mut editor, "one");
+        editor.history_up();
+
+        editor.get_history().borrow_mut().clear();
+        editor.history_up();
+        editor.history_down();
+        assert_eq!(line(&editor), "");
+    }
+
+    #[test]
+    fn history_is_saved_and_loaded() {
+        let root = std::env::temp_dir().join(format!("fantasy_cpc_history_{}", std::process::id()));
+        let mut file_system = FileSystem::new();
+        file_system.mount('C', Box::new(HostDrive::new(&root).unwrap()));
+
+        let mut editor = LineEditor::new();
+        editor.load_history(&file_system, "C:/history.txt");
+        assert!(editor.get_history().borrow().is_empty());
+        for index in 0..MAX_HISTORY_SIZE + 5 {
+            type_line(&mut editor, &format!("command {}", index));
+        }
+        editor
+            .save_history(&mut file_system, "C:/history.txt")
+            .unwrap();
+
+        let mut loaded = LineEditor::new();
+        loaded.load_history(&file_system, "C:/history.txt");
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!(
+            *loaded.get_history().borrow(),
+            *editor.get_history().borrow()
+        );
+        loaded.history_up();
+        assert_eq!(line(&loaded), format!("command {}", MAX_HISTORY_SIZE + 4));
+    }
+}
@@ -1,2 +1,7 @@
+pub mod ansi;
+pub mod command_line;
+pub mod keyboard;
+pub mod line_editor;
 pub mod shell;
-pub mod terminal;
\ No newline at end of file
+pub mod terminal;
+pub mod window_manager;
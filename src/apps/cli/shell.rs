@@ -1,12 +1,25 @@
-use fantasy_cpc_app::{AppResponse, FantasyCpcApp, FantasyCppAppDefaultParams};
+use std::collections::BTreeMap;
+
+use fantasy_cpc_app::{
+    input::{InputState, Key, TextChar},
+    joystick::JoystickButton,
+    mouse::MouseButton,
+    AppResponse, CrtParameter, FantasyCpcApp, FantasyCppAppDefaultParams, RebootKind,
+    SystemCommand,
+};
 use fantasy_cpc_clock::Clock;
 use fantasy_cpc_display_controller::{
     DisplayController, color_palettes::{BLUE, TRUE_BLUE, YELLOW}, config::{TEXT_COLUMNS, TEXT_ROWS}, text_layer::text_layer::TextLayerChar
 };
-use winit::{event::VirtualKeyCode, event_loop::ControlFlow};
-use winit_input_helper::{TextChar, WinitInputHelper};
+use fantasy_cpc_vfs::{path_to_string, FileSystem, VfsError};
+use unicode::compose::Composer;
 
-use super::terminal::Terminal;
+use super::{
+    command_line::{tokenize, ArgSpec, ArgType, Args, Command},
+    line_editor::{Completion, LineEditor},
+    terminal::Terminal,
+    window_manager::WindowManager,
+};
 
 const SPLASH: &str = "\u{000D} Fantasy CPC Microcomputer V(0.6.0)\u{000D}\u{000D} 2023 Damien Torreilles\u{000D}\u{000D}";
 const SHELL_START_MESSAGE: &str = "SHELL 0.1\u{000D}Ready\u{000D}";
@@ -14,14 +27,41 @@ const SHELL_START_MESSAGE: &str = "SHELL 0.1\u{000D}Ready\u{000D}";
 const DEFAULT_BKG_COLOR: usize = TRUE_BLUE;
 const DEFAULT_COLOR: usize = YELLOW;
 
+/// Files with that extension are run by the script host app
+const SCRIPT_EXTENSION: &str = ".rhai";
+const SCRIPT_HOST: &str = "script";
+
+/// Files with that extension are run by the shell, one command per line
+const BATCH_EXTENSION: &str = ".bat";
+
+/// Batch file executed when the shell starts, after the boot
+const AUTOEXEC_FILE: &str = "C:/autoexec.bat";
+
+/// Rows scrolled in the scrollback for one step of the mouse wheel
+const WHEEL_SCROLL_ROWS: f32 = 3.0;
+
+/// Batch files can run other batch files, up to that depth
+const MAX_BATCH_DEPTH: usize = 8;
+
+/// Where the command history is kept between sessions, saved when quitting or rebooting
+const HISTORY_FILE: &str = "C:/history.txt";
+
 pub struct Shell {
     app_params: FantasyCppAppDefaultParams,
     color: usize,
     bkg_color: usize,
     clear_text_layer: bool,
-    command: Vec<char>,
-    // command_history: Vec<String>,
-    terminal: Terminal,
+    app_names: Vec<String>,
+    /// Each window has its own terminal and command line, the commands typed apply
+    /// to the window that has the focus
+    windows: WindowManager,
+    variables: BTreeMap<String, String>,
+    batch_depth: usize,
+    autoexec_pending: bool,
+    /// Mouse wheel movement that didn't add up to a whole row yet
+    wheel_scroll: f32,
+    /// Accented letters typed with dead keys that the host didn't compose
+    composer: Composer,
 }
 
 #[derive(Copy, Clone)]
@@ -48,12 +88,32 @@ impl Shell {
             bkg_color: DEFAULT_BKG_COLOR,
             //last_character_received: None,
             clear_text_layer: false,
-            command: Vec::new(),
-            // command_history,
-            terminal: Terminal::new(),
+            app_names: Vec::new(),
+            windows: WindowManager::new(),
+            variables: BTreeMap::new(),
+            batch_depth: 0,
+            autoexec_pending: false,
+            wheel_scroll: 0.0,
+            composer: Composer::new(),
         }
     }
 
+    fn terminal(&self) -> &Terminal {
+        &self.windows.get_focused().terminal
+    }
+
+    fn terminal_mut(&mut self) -> &mut Terminal {
+        &mut self.windows.get_focused_mut().terminal
+    }
+
+    fn editor(&self) -> &LineEditor {
+        &self.windows.get_focused().editor
+    }
+
+    fn editor_mut(&mut self) -> &mut LineEditor {
+        &mut self.windows.get_focused_mut().editor
+    }
+
     fn style_a_char(&self, c: char, style: Style) -> StyledChar {
         match style {
             Style::Default => StyledChar::Default(c),
@@ -79,35 +139,852 @@ impl Shell {
         }
     }
 
+    /// Names of the apps that can be launched by typing their name.
+    pub fn set_app_names(&mut self, app_names: Vec<String>) {
+        self.app_names = app_names;
+    }
+
+    /// Redraws the line being edited after a change.
+    /// previous_len is the number of characters of the line before the change.
+    fn refresh_command_line(&mut self, previous_len: usize) {
+        let text_layer_chars: Vec<TextLayerChar> = self
+            .editor()
+            .get_line()
+            .iter()
+            .map(|c| self.get_text_layer_char_from_style(self.style_a_char(*c, Style::Default)))
+            .collect();
+        self.terminal_mut().cursor_offset = self.editor().get_cursor_offset();
+        self.terminal_mut()
+            .replace_last_chars(previous_len, &text_layer_chars);
+    }
+
+    fn complete_command_line(&mut self) {
+        let mut candidates: Vec<&str> = COMMANDS
+            .iter()
+            .flat_map(|command| std::iter::once(&command.name).chain(command.aliases))
+            .copied()
+            .collect();
+        candidates.extend(self.app_names.iter().map(String::as_str));
+
+        if let Completion::Ambiguous(matches) =
+            self.windows.get_focused_mut().editor.complete(&candidates)
+        {
+            // Lists the candidates and prints the prompt and the line again below
+            let line: String = self.editor().get_line().iter().collect();
+            self.terminal_mut().cursor_offset = 0;
+            self.terminal_mut().push_char('\u{000D}');
+            self.print(&matches.join(" "));
+            self.terminal_mut().push_char('>');
+            self.terminal_mut().push_string(&line);
+        }
+    }
+
+    /// Prints a message from the system or another app above the prompt,
+    /// the line being typed is kept.
+    pub fn print_message(&mut self, text: &str) {
+        let line_len = self.editor().get_line().len();
+        self.terminal_mut().cursor_offset = 0;
+        self.terminal_mut().replace_last_chars(line_len + 1, &[]);
+        self.print(text);
+        self.terminal_mut().push_char('>');
+        self.refresh_command_line(0);
+    }
+
+    /// Runs a command received from the network console as if it was typed,
+    /// what's being typed on the machine is kept.
+    pub fn run_remote_command(&mut self, command: &str) -> AppResponse {
+        let window_id = self.windows.get_focused().id;
+        let line_len = self.editor().get_line().len();
+        self.terminal_mut().scroll_to_bottom();
+        self.terminal_mut().cursor_offset = 0;
+        self.terminal_mut().replace_last_chars(line_len, &[]);
+        self.print(command);
+
+        let response = self.interpret_command(command.to_string());
+        self.print_prompt(window_id);
+        if self.windows.get_focused().id == window_id {
+            self.refresh_command_line(0);
+        }
+        response
+    }
+
+    /// What the windows printed since the last call, for the network console.
+    pub fn take_output(&mut self) -> String {
+        self.windows
+            .get_windows_mut()
+            .map(|window| window.terminal.take_transcript())
+            .collect()
+    }
+
+    /// Writes a line of text to the terminal.
+    fn print(&mut self, text: &str) {
+        self.terminal_mut().push_string(text);
+        self.terminal_mut().push_char('\u{000D}');
+    }
+
     fn interpret_command(&mut self, command: String) -> AppResponse {
         let mut response: AppResponse = AppResponse::new();
 
-        if !command.is_empty() {
-            println!("Command: '{}'", command);
-            if command == "help" {
-                response.set_message(String::from(
-                    "Type [clear] to clear screen.\u{000D}Type [quit] or [exit] to exit.",
-                ));
-            } else if command == "clear" {
-                self.command.clear();
-                self.clear_text_layer = true;
-            } else if command == "ps" {
-            // } else if command == "test" {
-            //     let mut toto = Vec::new();
-            //     for char in CHAR_TABLE {
-            //         toto.push(char);
-            //     }
-            //     let titi = toto.iter().cloned().collect::<String>();
-            //     response.set_message(titi);
-            } else if command == "quit" || command == "exit" {
-                response.event = Some(ControlFlow::Exit);
-                response.set_message(String::from("Command 'quit' or 'exit' received; stopping."));
-            } else {
-                response.set_message(command);
+        let words = match tokenize(&command, &self.variables) {
+            Ok(words) => words,
+            Err(error) => {
+                self.print(&error.to_string());
+                return response;
+            }
+        };
+
+        let Some((name, args)) = words.split_first() else {
+            return response;
+        };
+
+        println!("Command: '{}' {:?}", name, args);
+
+        if let Some(command) = COMMANDS.iter().find(|command| command.matches(name)) {
+            match command.parse_args(args) {
+                Ok(args) => response = (command.handler)(self, &args),
+                Err(usage_error) => self.print(&usage_error),
+            }
+        } else if self.app_names.iter().any(|app_name| app_name == name) {
+            response.push_command(SystemCommand::LaunchApp(name.clone()));
+        } else {
+            self.print(&format!("Unknown command '{}'", name));
+        }
+
+        response
+    }
+
+    /// Shift+PageUp/PageDown and the mouse wheel move through the scrollback.
+    fn scroll_terminal(&mut self, inputs: &InputState) {
+        if inputs.held_shift() {
+            if inputs.key_pressed_os(Key::PageUp) {
+                let page_rows = self.terminal().get_page_rows();
+                self.terminal_mut().scroll_up(page_rows);
+            }
+            if inputs.key_pressed_os(Key::PageDown) {
+                let page_rows = self.terminal().get_page_rows();
+                self.terminal_mut().scroll_down(page_rows);
+            }
+        }
+
+        self.wheel_scroll += inputs.get_mouse().wheel * WHEEL_SCROLL_ROWS;
+        let rows = self.wheel_scroll.trunc();
+        self.wheel_scroll -= rows;
+        if rows > 0.0 {
+            self.terminal_mut().scroll_up(rows as usize);
+        } else if rows < 0.0 {
+            self.terminal_mut().scroll_down(-rows as usize);
+        }
+    }
+
+    /// Runs every line of a text file as a command.
+    /// Empty lines and lines starting with # are skipped.
+    fn exec_file(&mut self, path: &str) -> AppResponse {
+        let mut response = AppResponse::new();
+
+        if self.batch_depth >= MAX_BATCH_DEPTH {
+            self.print("Too many nested batch files");
+            return response;
+        }
+
+        let content = match self.app_params.get_file_system() {
+            Some(file_system) => file_system.borrow().read_to_string(path),
+            None => {
+                self.print("No disc");
+                return response;
+            }
+        };
+        let content = match content {
+            Ok(content) => content,
+            Err(error) => {
+                self.print(&error.to_string());
+                return response;
+            }
+        };
+
+        self.batch_depth += 1;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
             }
+            let line_response = self.interpret_command(line.to_string());
+            response.commands.extend(line_response.commands);
         }
+        self.batch_depth -= 1;
+
         response
     }
+
+    /// Writes the command history to the disc, for the next session.
+    pub fn save_history(&mut self) {
+        let Some(file_system) = self.app_params.get_file_system() else {
+            return;
+        };
+        let saved = self
+            .editor()
+            .save_history(&mut file_system.borrow_mut(), HISTORY_FILE);
+        if let Err(error) = saved {
+            println!("Could not save the shell history: {}", error);
+        }
+    }
+
+    /// Runs the autoexec file if there is one on the disc.
+    fn run_autoexec(&mut self) -> AppResponse {
+        let exists = self
+            .app_params
+            .get_file_system()
+            .is_some_and(|file_system| file_system.borrow().exists(AUTOEXEC_FILE));
+        if !exists {
+            return AppResponse::new();
+        }
+
+        // The output goes where the prompt is, the prompt comes back after
+        let window_id = self.windows.get_focused().id;
+        self.terminal_mut().cursor_offset = 0;
+        self.terminal_mut().replace_last_chars(1, &[]);
+        let response = self.exec_file(AUTOEXEC_FILE);
+        self.print_prompt(window_id);
+        response
+    }
+
+    /// Prints the prompt in a window once its command is done.
+    /// The command may have given the focus to another window or closed that one.
+    fn print_prompt(&mut self, window_id: usize) {
+        if let Some(window) = self.windows.get_window_mut(window_id) {
+            window.terminal.push_char('>');
+        }
+    }
+
+    /// Opens a window on top of the others with its own prompt.
+    fn open_window(&mut self, title: &str) {
+        let window = self.windows.open(title);
+        window.terminal.push_string(SHELL_START_MESSAGE);
+        window.terminal.push_char('>');
+    }
+}
+
+const APP_NAME_ARG: ArgSpec = ArgSpec {
+    name: "app",
+    arg_type: ArgType::Text,
+    optional: false,
+};
+
+const PATH_ARG: ArgSpec = ArgSpec {
+    name: "path",
+    arg_type: ArgType::Text,
+    optional: false,
+};
+
+const SOURCE_ARG: ArgSpec = ArgSpec {
+    name: "from",
+    arg_type: ArgType::Text,
+    optional: false,
+};
+
+const DESTINATION_ARG: ArgSpec = ArgSpec {
+    name: "to",
+    arg_type: ArgType::Text,
+    optional: false,
+};
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        aliases: &[],
+        args: &[ArgSpec {
+            name: "command",
+            arg_type: ArgType::Text,
+            optional: true,
+        }],
+        help: "Lists the commands, or explains one.",
+        handler: help,
+    },
+    Command {
+        name: "clear",
+        aliases: &["cls"],
+        args: &[],
+        help: "Clears the screen.",
+        handler: clear,
+    },
+    Command {
+        name: "echo",
+        aliases: &[],
+        args: &[ArgSpec {
+            name: "text",
+            arg_type: ArgType::Rest,
+            optional: true,
+        }],
+        help: "Prints a line of text.",
+        handler: echo,
+    },
+    Command {
+        name: "set",
+        aliases: &[],
+        args: &[ArgSpec {
+            name: "name=value",
+            arg_type: ArgType::Rest,
+            optional: true,
+        }],
+        help: "Sets a variable used as $name in commands, lists them without argument. name= removes it.",
+        handler: set,
+    },
+    Command {
+        name: "exec",
+        aliases: &[],
+        args: &[PATH_ARG],
+        help: "Runs the commands of a text file, one per line. C:/autoexec.bat runs at boot.",
+        handler: exec,
+    },
+    Command {
+        name: "window",
+        aliases: &["win"],
+        args: &[
+            ArgSpec {
+                name: "action",
+                arg_type: ArgType::Choice(&["open", "close", "next", "list", "focus", "move", "size"]),
+                optional: false,
+            },
+            ArgSpec {
+                name: "args",
+                arg_type: ArgType::Rest,
+                optional: true,
+            },
+        ],
+        help: "open [title], close, next, list, focus <n>, move <x> <y> or size <columns> <rows> the windows. Ctrl+Tab switches windows.",
+        handler: window,
+    },
+    Command {
+        name: "ps",
+        aliases: &[],
+        args: &[],
+        help: "Lists the apps and their status.",
+        handler: ps,
+    },
+    Command {
+        name: "run",
+        aliases: &[],
+        args: &[
+            APP_NAME_ARG,
+            ArgSpec {
+                name: "file",
+                arg_type: ArgType::Text,
+                optional: true,
+            },
+        ],
+        help: "Starts an app, or brings it back to the foreground. Also runs .rhai scripts and .bat files.",
+        handler: run,
+    },
+    Command {
+        name: "kill",
+        aliases: &[],
+        args: &[APP_NAME_ARG],
+        help: "Stops an app.",
+        handler: kill,
+    },
+    Command {
+        name: "bg",
+        aliases: &[],
+        args: &[APP_NAME_ARG],
+        help: "Runs an app in the background.",
+        handler: bg,
+    },
+    Command {
+        name: "fg",
+        aliases: &[],
+        args: &[APP_NAME_ARG],
+        help: "Brings a background app to the foreground. F12 switches between apps.",
+        handler: fg,
+    },
+    Command {
+        name: "dir",
+        aliases: &["ls"],
+        args: &[ArgSpec {
+            name: "path",
+            arg_type: ArgType::Text,
+            optional: true,
+        }],
+        help: "Lists the files of a directory.",
+        handler: dir,
+    },
+    Command {
+        name: "cat",
+        aliases: &[],
+        args: &[ArgSpec {
+            name: "drive",
+            arg_type: ArgType::Text,
+            optional: true,
+        }],
+        help: "Lists the files of a drive the AMSDOS way.",
+        handler: cat,
+    },
+    Command {
+        name: "|a",
+        aliases: &[],
+        args: &[],
+        help: "Selects drive A.",
+        handler: select_drive_a,
+    },
+    Command {
+        name: "|b",
+        aliases: &[],
+        args: &[],
+        help: "Selects drive B.",
+        handler: select_drive_b,
+    },
+    Command {
+        name: "type",
+        aliases: &[],
+        args: &[PATH_ARG],
+        help: "Prints the content of a text file.",
+        handler: type_file,
+    },
+    Command {
+        name: "cd",
+        aliases: &[],
+        args: &[ArgSpec {
+            name: "path",
+            arg_type: ArgType::Text,
+            optional: true,
+        }],
+        help: "Changes the current directory or drive, prints it without path.",
+        handler: cd,
+    },
+    Command {
+        name: "mkdir",
+        aliases: &["md"],
+        args: &[PATH_ARG],
+        help: "Creates a directory.",
+        handler: mkdir,
+    },
+    Command {
+        name: "del",
+        aliases: &["era", "rm"],
+        args: &[PATH_ARG],
+        help: "Deletes a file or an empty directory.",
+        handler: del,
+    },
+    Command {
+        name: "ren",
+        aliases: &["mv"],
+        args: &[SOURCE_ARG, DESTINATION_ARG],
+        help: "Renames or moves a file or directory.",
+        handler: ren,
+    },
+    Command {
+        name: "copy",
+        aliases: &["cp"],
+        args: &[SOURCE_ARG, DESTINATION_ARG],
+        help: "Copies a file.",
+        handler: copy,
+    },
+    Command {
+        name: "mode",
+        aliases: &[],
+        args: &[ArgSpec {
+            name: "mode",
+            arg_type: ArgType::Int { min: 0, max: 2 },
+            optional: false,
+        }],
+        help: "Sets the CRT shader mode.",
+        handler: mode,
+    },
+    Command {
+        name: "dist",
+        aliases: &[],
+        args: &[ArgSpec {
+            name: "level",
+            arg_type: ArgType::Int { min: 0, max: 6 },
+            optional: false,
+        }],
+        help: "Sets the screen curvature, 0 is flat.",
+        handler: dist,
+    },
+    Command {
+        name: "joy",
+        aliases: &["joystick"],
+        args: &[
+            ArgSpec {
+                name: "player",
+                arg_type: ArgType::Int { min: 1, max: 2 },
+                optional: true,
+            },
+            ArgSpec {
+                name: "button",
+                arg_type: ArgType::Choice(&["up", "down", "left", "right", "fire1", "fire2"]),
+                optional: true,
+            },
+            ArgSpec {
+                name: "key",
+                arg_type: ArgType::Text,
+                optional: true,
+            },
+        ],
+        help: "Maps a key to a joystick button, ex: joy 1 fire1 Z. Lists the keys with no arguments.",
+        handler: joy,
+    },
+    Command {
+        name: "reboot",
+        aliases: &[],
+        args: &[ArgSpec {
+            name: "kind",
+            arg_type: ArgType::Choice(&["warm", "cold"]),
+            optional: true,
+        }],
+        help: "Restarts the computer, warm by default. A cold reboot also resets apps and settings.",
+        handler: reboot,
+    },
+    Command {
+        name: "quit",
+        aliases: &["exit"],
+        args: &[],
+        help: "Quits Fantasy CPC.",
+        handler: quit,
+    },
+];
+
+fn help(shell: &mut Shell, args: &Args) -> AppResponse {
+    match args.get_text("command") {
+        Some(name) => match COMMANDS.iter().find(|command| command.matches(name)) {
+            Some(command) => {
+                shell.print(&format!("Usage: {}", command.usage()));
+                shell.print(command.help);
+            }
+            None => shell.print(&format!("Unknown command '{}'", name)),
+        },
+        None => {
+            for command in COMMANDS {
+                shell.print(&format!("{} - {}", command.usage(), command.help));
+            }
+            let apps = shell.app_names.join(", ");
+            shell.print(&format!("Apps: {}", apps));
+        }
+    }
+    AppResponse::new()
+}
+
+fn clear(shell: &mut Shell, _args: &Args) -> AppResponse {
+    shell.editor_mut().clear();
+    shell.clear_text_layer = true;
+    AppResponse::new()
+}
+
+fn echo(shell: &mut Shell, args: &Args) -> AppResponse {
+    shell.print(args.get_text("text").unwrap_or_default());
+    AppResponse::new()
+}
+
+fn set(shell: &mut Shell, args: &Args) -> AppResponse {
+    let Some(assignment) = args.get_text("name=value") else {
+        let lines: Vec<String> = shell
+            .variables
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        for line in lines {
+            shell.print(&line);
+        }
+        return AppResponse::new();
+    };
+
+    let (name, value) = assignment
+        .split_once('=')
+        .or_else(|| assignment.split_once(' '))
+        .unwrap_or((assignment, ""));
+    let name = name.trim().to_uppercase();
+    let value = value.trim();
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        shell.print(&format!("Invalid variable name '{}'", name));
+    } else if value.is_empty() {
+        shell.variables.remove(&name);
+    } else {
+        shell.variables.insert(name, value.to_string());
+    }
+    AppResponse::new()
+}
+
+fn exec(shell: &mut Shell, args: &Args) -> AppResponse {
+    let path = args.get_text("path").unwrap_or_default().to_string();
+    shell.exec_file(&path)
+}
+
+fn window(shell: &mut Shell, args: &Args) -> AppResponse {
+    let action_args = args.get_text("args").unwrap_or_default();
+    let numbers: Vec<usize> = action_args
+        .split_whitespace()
+        .filter_map(|word| word.parse().ok())
+        .collect();
+
+    match args.get_text("action").unwrap_or_default() {
+        "open" => shell.open_window(action_args),
+        "close" => {
+            let closed = shell.windows.close_focused();
+            if !closed {
+                shell.print("Can't close the last window");
+            }
+        }
+        "next" => shell.windows.focus_next(),
+        "list" => {
+            let lines: Vec<String> = shell
+                .windows
+                .get_windows()
+                .map(|window| {
+                    let title = &window.terminal.title;
+                    let (x, y) = window.terminal.get_coordinates();
+                    let (columns, rows) = window.terminal.get_size();
+                    format!("{} {} {},{} {}x{}", window.id, title, x, y, columns, rows)
+                })
+                .collect();
+            for line in lines {
+                shell.print(&line);
+            }
+        }
+        "focus" => match numbers.first() {
+            Some(id) if shell.windows.focus(*id) => (),
+            _ => shell.print("Usage: window focus <n>, see window list"),
+        },
+        "move" => match numbers[..] {
+            [x, y] => shell.terminal_mut().set_coordinates((x, y)),
+            _ => shell.print("Usage: window move <x> <y>"),
+        },
+        "size" => match numbers[..] {
+            [columns, rows] => shell.terminal_mut().set_size((columns, rows)),
+            _ => shell.print("Usage: window size <columns> <rows>"),
+        },
+        _ => (),
+    }
+    AppResponse::new()
+}
+
+fn ps(_shell: &mut Shell, _args: &Args) -> AppResponse {
+    let mut response = AppResponse::new();
+    response.push_command(SystemCommand::ListApps);
+    response
+}
+
+fn app_command(args: &Args, command: fn(String) -> SystemCommand) -> AppResponse {
+    let mut response = AppResponse::new();
+    let name = args.get_text("app").unwrap_or_default().to_string();
+    response.push_command(command(name));
+    response
+}
+
+fn run(shell: &mut Shell, args: &Args) -> AppResponse {
+    let name = args.get_text("app").unwrap_or_default();
+    if args.get_text("file").is_none() && name.to_lowercase().ends_with(BATCH_EXTENSION) {
+        return shell.exec_file(name);
+    }
+
+    let file = match args.get_text("file") {
+        Some(file) => Some((name, file)),
+        None if name.to_lowercase().ends_with(SCRIPT_EXTENSION) => Some((SCRIPT_HOST, name)),
+        None => None,
+    };
+
+    match file {
+        Some((name, file)) => {
+            let mut response = AppResponse::new();
+            response.push_command(SystemCommand::LaunchAppWithFile(
+                name.to_string(),
+                file.to_string(),
+            ));
+            response
+        }
+        None => app_command(args, SystemCommand::LaunchApp),
+    }
+}
+
+fn kill(_shell: &mut Shell, args: &Args) -> AppResponse {
+    app_command(args, SystemCommand::StopApp)
+}
+
+fn bg(_shell: &mut Shell, args: &Args) -> AppResponse {
+    app_command(args, SystemCommand::BackgroundApp)
+}
+
+fn fg(_shell: &mut Shell, args: &Args) -> AppResponse {
+    app_command(args, SystemCommand::ForegroundApp)
+}
+
+/// Runs a file system operation, errors are printed to the terminal.
+fn with_file_system<F>(shell: &mut Shell, operation: F) -> AppResponse
+where
+    F: FnOnce(&mut Shell, &mut FileSystem) -> Result<(), VfsError>,
+{
+    match shell.app_params.get_file_system() {
+        Some(file_system) => {
+            if let Err(error) = operation(shell, &mut file_system.borrow_mut()) {
+                shell.print(&error.to_string());
+            }
+        }
+        None => shell.print("No disc"),
+    }
+    AppResponse::new()
+}
+
+fn dir(shell: &mut Shell, args: &Args) -> AppResponse {
+    let path = args.get_text("path").unwrap_or("").to_string();
+    with_file_system(shell, |shell, file_system| {
+        let entries = file_system.read_dir(&path)?;
+        let (letter, dir_path) = file_system.resolve(&path)?;
+        shell.print(&format!("{}:{}", letter, path_to_string(&dir_path)));
+
+        for entry in &entries {
+            let size = match entry.is_dir {
+                true => String::from("<DIR>"),
+                false => entry.size.to_string(),
+            };
+            shell.print(&format!("{:<30}{:>8}", entry.name, size));
+        }
+
+        let dir_count = entries.iter().filter(|entry| entry.is_dir).count();
+        shell.print(&format!(
+            "{} file(s), {} dir(s)",
+            entries.len() - dir_count,
+            dir_count
+        ));
+        Ok(())
+    })
+}
+
+/// Files in columns with their size in K, then the free space, like CAT on a CPC.
+fn cat(shell: &mut Shell, args: &Args) -> AppResponse {
+    let drive = args.get_text("drive").map(String::from);
+    with_file_system(shell, |shell, file_system| {
+        let path = match drive {
+            Some(drive) => format!("{}:/", drive.trim_end_matches(':')),
+            None => String::from("/"),
+        };
+        let entries = file_system.read_dir(&path)?;
+        let (letter, _) = file_system.resolve(&path)?;
+        shell.print(&format!("Drive {}: user  0", letter));
+        shell.print("");
+
+        let columns: Vec<String> = entries
+            .iter()
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| format!("{:<12} {:>3}K", entry.name, entry.size.div_ceil(1024)))
+            .collect();
+        for line in columns.chunks(2) {
+            shell.print(&line.join("   "));
+        }
+
+        shell.print("");
+        if let Some(free) = file_system.free_space(&path)? {
+            shell.print(&format!("{}K free", free / 1024));
+        }
+        Ok(())
+    })
+}
+
+fn select_drive(shell: &mut Shell, letter: char) -> AppResponse {
+    with_file_system(shell, |_shell, file_system| {
+        file_system.change_dir(&format!("{}:/", letter))
+    })
+}
+
+fn select_drive_a(shell: &mut Shell, _args: &Args) -> AppResponse {
+    select_drive(shell, 'A')
+}
+
+fn select_drive_b(shell: &mut Shell, _args: &Args) -> AppResponse {
+    select_drive(shell, 'B')
+}
+
+fn type_file(shell: &mut Shell, args: &Args) -> AppResponse {
+    let path = args.get_text("path").unwrap_or_default().to_string();
+    with_file_system(shell, |shell, file_system| {
+        let content = file_system.read_to_string(&path)?;
+        let content = content
+            .trim_end_matches(['\r', '\n'])
+            .replace("\r\n", "\n")
+            .replace('\n', "\u{000D}")
+            .replace('\t', "    ");
+        shell.print(&content);
+        Ok(())
+    })
+}
+
+fn cd(shell: &mut Shell, args: &Args) -> AppResponse {
+    let path = args.get_text("path").map(String::from);
+    with_file_system(shell, |shell, file_system| {
+        if let Some(path) = path {
+            file_system.change_dir(&path)?;
+        }
+        shell.print(&file_system.get_current_dir());
+        Ok(())
+    })
+}
+
+fn mkdir(shell: &mut Shell, args: &Args) -> AppResponse {
+    let path = args.get_text("path").unwrap_or_default().to_string();
+    with_file_system(shell, |_shell, file_system| file_system.create_dir(&path))
+}
+
+fn del(shell: &mut Shell, args: &Args) -> AppResponse {
+    let path = args.get_text("path").unwrap_or_default().to_string();
+    with_file_system(shell, |_shell, file_system| file_system.remove(&path))
+}
+
+fn ren(shell: &mut Shell, args: &Args) -> AppResponse {
+    let from = args.get_text("from").unwrap_or_default().to_string();
+    let to = args.get_text("to").unwrap_or_default().to_string();
+    with_file_system(shell, |_shell, file_system| file_system.rename(&from, &to))
+}
+
+fn copy(shell: &mut Shell, args: &Args) -> AppResponse {
+    let from = args.get_text("from").unwrap_or_default().to_string();
+    let to = args.get_text("to").unwrap_or_default().to_string();
+    with_file_system(shell, |_shell, file_system| file_system.copy(&from, &to))
+}
+
+fn mode(_shell: &mut Shell, args: &Args) -> AppResponse {
+    let mut response = AppResponse::new();
+    let mode = args.get_int("mode").unwrap_or(0) as usize;
+    response.push_command(SystemCommand::SetCrtParameter(CrtParameter::Mode(mode)));
+    response
+}
+
+fn dist(_shell: &mut Shell, args: &Args) -> AppResponse {
+    let mut response = AppResponse::new();
+    let level = args.get_int("level").unwrap_or(0) as usize;
+    response.push_command(SystemCommand::SetCrtParameter(CrtParameter::Distortion(level)));
+    response
+}
+
+fn joy(shell: &mut Shell, args: &Args) -> AppResponse {
+    let mut response = AppResponse::new();
+    let player = args.get_int("player");
+    let button = args.get_text("button").and_then(JoystickButton::from_name);
+    let key = args.get_text("key");
+
+    match (player, button, key) {
+        (None, _, _) => response.push_command(SystemCommand::ListJoystickMappings),
+        (Some(player), Some(button), Some(key)) => match Key::from_name(key) {
+            Some(key) => {
+                response.push_command(SystemCommand::MapJoystick(player as usize - 1, button, key))
+            }
+            None => shell.print(&format!("Unknown key '{}'", key)),
+        },
+        _ => shell.print("Usage: joy <player> <button> <key>"),
+    }
+    response
+}
+
+fn reboot(_shell: &mut Shell, args: &Args) -> AppResponse {
+    let kind = match args.get_text("kind") {
+        Some("cold") => RebootKind::Cold,
+        _ => RebootKind::Warm,
+    };
+    let mut response = AppResponse::new();
+    response.push_command(SystemCommand::Reboot(kind));
+    response
+}
+
+fn quit(shell: &mut Shell, _args: &Args) -> AppResponse {
+    shell.print("Quit command received; stopping.");
+    let mut response = AppResponse::new();
+    response.push_command(SystemCommand::Quit);
+    response
 }
 
 impl FantasyCpcApp for Shell {
@@ -118,69 +995,142 @@ impl FantasyCpcApp for Shell {
     fn init_app(&mut self, _system_clock: &Clock, display_controller: &mut DisplayController) {
         display_controller.set_brightness(255);
         display_controller.clear(BLUE);
-        self.terminal.set_coordinates((0, 0));
-        self.terminal.set_size((TEXT_COLUMNS, TEXT_ROWS));
-        self.terminal.clear();
-        self.terminal.push_string(SPLASH);
-        self.terminal.push_string(SHELL_START_MESSAGE);
-        self.terminal.push_char('>');
-    }
-
-    fn update_app(
-        &mut self,
-        inputs: Option<&WinitInputHelper>,
-        _clock: &Clock,
-    ) -> Option<AppResponse> {
+        self.windows = WindowManager::new();
+        self.terminal_mut().set_coordinates((0, 0));
+        self.terminal_mut().set_size((TEXT_COLUMNS, TEXT_ROWS));
+        self.terminal_mut().push_string(SPLASH);
+        self.terminal_mut().push_string(SHELL_START_MESSAGE);
+        self.terminal_mut().push_char('>');
+        if let Some(file_system) = self.app_params.get_file_system() {
+            self.editor_mut()
+                .load_history(&file_system.borrow(), HISTORY_FILE);
+        }
+
+        self.autoexec_pending = true;
+    }
+
+    fn update_app(&mut self, inputs: Option<&InputState>, _clock: &Clock) -> Option<AppResponse> {
         inputs?;
 
         if self.clear_text_layer {
-            self.terminal.clear();
-            self.terminal.push_char('>');
+            self.terminal_mut().clear();
+            self.terminal_mut().push_char('>');
             self.clear_text_layer = false;
         }
 
-        if !inputs.unwrap().text().is_empty() {
-            match inputs.unwrap().text().first() {
-                Some(TextChar::Char(c)) => {
-                    if *c == unicode::ESCAPE {
-                        self.terminal.push_char('\u{000D}');
-                        self.terminal
-                            .push_string("Type 'quit' or 'exit' to quit Fantasy CPC.");
-                        self.terminal.push_char('\u{000D}');
-                        self.terminal.push_char('>');
-                        self.command.clear();
-                    } else {
-                        self.command.push(*c);
-                        self.terminal.push_text_layer_char(
-                            self.get_text_layer_char_from_style(
-                                self.style_a_char(*c, Style::Default),
-                            ),
-                        );
+        if self.autoexec_pending {
+            self.autoexec_pending = false;
+            return Some(self.run_autoexec());
+        }
+
+        let inputs = inputs.unwrap();
+        let previous_line = self.editor().get_line().to_vec();
+        let previous_cursor_offset = self.editor().get_cursor_offset();
+
+        // The prompt, the line and the cursor stay on the screen, the terminal
+        // doesn't keep the characters of the rows that scroll out
+        let (columns, rows) = self.terminal().get_size();
+        self.editor_mut().set_max_length(columns * rows - 2);
+
+        for text_char in inputs.text() {
+            match text_char {
+                TextChar::Char(unicode::ESCAPE) => {
+                    self.terminal_mut().cursor_offset = 0;
+                    self.terminal_mut().push_char('\u{000D}');
+                    self.terminal_mut()
+                        .push_string("Type 'quit' or 'exit' to quit Fantasy CPC.");
+                    self.terminal_mut().push_char('\u{000D}');
+                    self.terminal_mut().push_char('>');
+                    self.editor_mut().clear();
+                    return None;
+                }
+                // Tab, Delete and Ctrl+letter are handled as keys below
+                TextChar::Char(c) if c.is_control() => (),
+                TextChar::Char(c) => {
+                    for c in self.composer.push(c) {
+                        self.editor_mut().insert(c);
                     }
                 }
-                Some(TextChar::Back) => {
-                    if !self.command.is_empty() {
-                        self.command.pop();
-                        self.terminal.push_char(unicode::BACKSPACE);
+                // Erases the dead key if one is waiting for its letter
+                TextChar::Back => {
+                    if self.composer.cancel().is_none() {
+                        self.editor_mut().backspace();
                     }
                 }
-                None => (),
             }
         }
 
-        if inputs.unwrap().key_pressed_os(VirtualKeyCode::Return) {
-            let response = self.interpret_command(self.command.iter().cloned().collect::<String>());
-            let message_string = response.get_message().clone();
-            if let Some(content) = message_string {
-                self.terminal.push_char('\u{000D}');
-                self.terminal.push_string(&content);
-                self.terminal.push_char('\u{000D}');
-                self.terminal.push_char('>');
-            } else {
-                self.terminal.push_char('\u{000D}');
-                self.terminal.push_char('>');
+        if inputs.held_control() {
+            if inputs.key_pressed_os(Key::A) {
+                self.editor_mut().move_home();
+            }
+            if inputs.key_pressed_os(Key::E) {
+                self.editor_mut().move_end();
+            }
+            if inputs.key_pressed_os(Key::K) {
+                self.editor_mut().kill_to_end();
+            }
+            if inputs.key_pressed_os(Key::U) {
+                self.editor_mut().kill_to_start();
+            }
+        }
+
+        if inputs.key_pressed_os(Key::Left) {
+            self.editor_mut().move_left();
+        }
+        if inputs.key_pressed_os(Key::Right) {
+            self.editor_mut().move_right();
+        }
+        if inputs.key_pressed_os(Key::Home) {
+            self.editor_mut().move_home();
+        }
+        if inputs.key_pressed_os(Key::End) {
+            self.editor_mut().move_end();
+        }
+        if inputs.key_pressed_os(Key::Delete) {
+            self.editor_mut().delete();
+        }
+        if inputs.key_pressed_os(Key::Up) {
+            self.editor_mut().history_up();
+        }
+        if inputs.key_pressed_os(Key::Down) {
+            self.editor_mut().history_down();
+        }
+
+        if self.editor().get_line() != previous_line
+            || self.editor().get_cursor_offset() != previous_cursor_offset
+        {
+            self.terminal_mut().scroll_to_bottom();
+            self.refresh_command_line(previous_line.len());
+        }
+
+        self.scroll_terminal(inputs);
+
+        // A click on a window brings it to the top
+        let mouse = inputs.get_mouse();
+        if let (true, Some((x, y))) = (mouse.is_pressed(MouseButton::Left), mouse.text_position) {
+            if let Some(id) = self.windows.get_window_at(x, y) {
+                self.windows.focus(id);
             }
-            self.command.clear();
+        }
+
+        if inputs.held_control() && inputs.key_pressed_os(Key::Tab) {
+            self.windows.focus_next();
+        } else if inputs.key_pressed_os(Key::Tab) {
+            self.complete_command_line();
+            let line_len = self.editor().get_line().len();
+            self.refresh_command_line(line_len);
+        }
+
+        if inputs.key_pressed_os(Key::Return) {
+            let window_id = self.windows.get_focused().id;
+            self.terminal_mut().scroll_to_bottom();
+            self.terminal_mut().cursor_offset = 0;
+            let command = self.editor_mut().take_line();
+            self.terminal_mut().record(&command);
+            self.terminal_mut().push_char('\u{000D}');
+            let response = self.interpret_command(command);
+            self.print_prompt(window_id);
             return Some(response);
         }
 
@@ -188,6 +1138,69 @@ impl FantasyCpcApp for Shell {
     }
 
     fn draw_app(&mut self, _clock: &Clock, display_controller: &mut DisplayController) {
-        self.terminal.render(display_controller);
+        self.windows.render(display_controller);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs one frame of the shell with the inputs, then starts the next one.
+    fn run_frame(shell: &mut Shell, inputs: &mut InputState) -> Option<AppResponse> {
+        let response = shell.update_app(Some(inputs), &Clock::new());
+        inputs.next_frame();
+        response
+    }
+
+    fn typed_line(shell: &Shell) -> String {
+        shell.editor().get_line().iter().collect()
+    }
+
+    #[test]
+    fn typing_edits_the_command_line() {
+        let mut shell = Shell::new();
+        let mut inputs = InputState::new();
+
+        inputs.type_text("lsx\u{8}");
+        run_frame(&mut shell, &mut inputs);
+        assert_eq!(typed_line(&shell), "ls");
+
+        inputs.press_key(Key::Home);
+        run_frame(&mut shell, &mut inputs);
+        inputs.release_key(Key::Home);
+        inputs.type_text("c");
+        run_frame(&mut shell, &mut inputs);
+        assert_eq!(typed_line(&shell), "cls");
+        assert_eq!(shell.editor().get_cursor_offset(), 2);
+    }
+
+    #[test]
+    fn dead_keys_compose_across_frames() {
+        let mut shell = Shell::new();
+        let mut inputs = InputState::new();
+
+        inputs.type_text("caf´");
+        run_frame(&mut shell, &mut inputs);
+        assert_eq!(typed_line(&shell), "caf");
+        inputs.type_text("e");
+        run_frame(&mut shell, &mut inputs);
+        assert_eq!(typed_line(&shell), "café");
+    }
+
+    #[test]
+    fn command_line_stays_on_the_screen() {
+        let mut shell = Shell::new();
+        let mut inputs = InputState::new();
+
+        // The prompt and the cursor take a cell each
+        let max_length = TEXT_COLUMNS * TEXT_ROWS - 2;
+        inputs.type_text(&"x".repeat(max_length + 10));
+        run_frame(&mut shell, &mut inputs);
+        assert_eq!(shell.editor().get_line().len(), max_length);
+
+        inputs.press_key(Key::Home);
+        run_frame(&mut shell, &mut inputs);
+        assert_eq!(shell.editor().get_cursor_offset(), max_length);
     }
 }
@@ -1,22 +1,66 @@
-use fantasy_cpc_display_controller::{DisplayController, text_layer::text_layer::TextLayerChar};
+use std::{collections::VecDeque, ops::Range};
 
+use fantasy_cpc_display_controller::{
+    text_layer::text_layer::{text_coord_to_frame_coord, TextLayerChar},
+    DisplayController,
+};
+
+use super::ansi::{ansi_color, nearest_color, AnsiAction, AnsiParser};
 use crate::{
     color_palettes::{TRUE_BLUE, YELLOW},
     config::{TEXT_COLUMNS, TEXT_ROWS},
 };
 
-/// The terminal is the text window in which the Shell is displayed
+/// Pixels per frame the text moves up when smooth scrolling, for each row waiting to scroll out
+const SMOOTH_SCROLL_SPEED: usize = 2;
+
+/// Columns between two tab stops
+const TAB_WIDTH: usize = 8;
+
+/// Rows kept in the scrollback once they have scrolled out of the screen
+const MAX_SCROLLBACK_ROWS: usize = 1000;
+
+/// The terminal is the text window in which the Shell is displayed.
+/// The buffer only holds what's on screen, rows that scroll out are moved to the
+/// scrollback, where they can be viewed again with scroll_up() and scroll_down().
+///
+/// Characters are added at the end of the buffer, until an ANSI/VT100 sequence moves the
+/// cursor or erases part of the screen: the buffer then becomes the cells of the screen,
+/// written at write_position, until there's nothing left but blank cells after it.
 pub struct Terminal {
     screen_coordinates: (usize, usize),
     screen_size: (usize, usize),
-    max_buffer_size: usize,
     pub default_color: usize,
     pub default_bkg_color: usize,
     pub cursor: char,
-    pub _show_border: bool,
-    pub _show_title_bar: bool,
+    /// Number of characters between the cursor and the end of the buffer,
+    /// the cursor blinks over the character it's on when not at the end.
+    pub cursor_offset: usize,
+    /// Draws a box around the terminal, its title in the top side
+    pub show_border: bool,
+    /// Shows the title above the terminal, in the border when there is one
+    pub show_title_bar: bool,
+    pub title: String,
+    pub smooth_scroll: bool,
     buffer: Vec<TextLayerChar>,
     formatted_buffer: Vec<TextLayerChar>,
+    scrolled_out_rows: Vec<TextLayerChar>,
+    scroll_progress: usize,
+    scrollback: VecDeque<Vec<TextLayerChar>>,
+    /// Rows the view is moved up in the scrollback, 0 when showing the bottom
+    scrollback_position: usize,
+    parser: AnsiParser,
+    /// Colors and effects given to the characters pushed, set with ESC [ ... m
+    style: TextLayerChar,
+    /// Where characters are written when the cursor has been moved, None to add them at the end
+    write_position: Option<usize>,
+    /// Index of the cursor in the formatted buffer
+    cursor_cell: usize,
+    saved_cursor: (usize, usize),
+    /// End of a UTF-8 character cut in two between calls to push_bytes()
+    pending_bytes: Vec<u8>,
+    /// Characters pushed since the last take_transcript(), None when not recorded
+    transcript: Option<String>,
 }
 
 impl Terminal {
@@ -24,46 +68,124 @@ impl Terminal {
         Terminal {
             screen_coordinates: (0, 0),
             screen_size: (TEXT_COLUMNS, TEXT_ROWS),
-            max_buffer_size: 1000,
             default_color: YELLOW,
             default_bkg_color: TRUE_BLUE,
             cursor: '\u{25AE}', // filled square
-            _show_border: false,
-            _show_title_bar: false,
+            cursor_offset: 0,
+            show_border: false,
+            show_title_bar: false,
+            title: String::new(),
+            smooth_scroll: true,
             buffer: Vec::new(),
             formatted_buffer: Vec::new(),
+            scrolled_out_rows: Vec::new(),
+            scroll_progress: 0,
+            scrollback: VecDeque::new(),
+            scrollback_position: 0,
+            parser: AnsiParser::new(),
+            style: TextLayerChar {
+                c: ' ',
+                color: YELLOW,
+                bkg_color: TRUE_BLUE,
+                swap: false,
+                blink: false,
+                shadowed: false,
+            },
+            write_position: None,
+            cursor_cell: 0,
+            saved_cursor: (0, 0),
+            pending_bytes: Vec::new(),
+            transcript: None,
         }
     }
 
     pub fn clear(&mut self) {
         self.buffer.clear();
         self.formatted_buffer.clear();
+        self.scrolled_out_rows.clear();
+        self.scroll_progress = 0;
+        self.scrollback.clear();
+        self.scrollback_position = 0;
+        self.write_position = None;
+    }
+
+    /// Moves the view up in the scrollback, towards older rows.
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.scrollback_position = (self.scrollback_position + rows).min(self.scrollback.len());
+    }
+
+    /// Moves the view down in the scrollback, towards the prompt.
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.scrollback_position = self.scrollback_position.saturating_sub(rows);
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.scrollback_position = 0;
+    }
+
+    /// Rows shown by a page up or down, one row less than the screen to keep some context
+    pub fn get_page_rows(&self) -> usize {
+        self.screen_size.1 - 1
     }
 
     /// Size in columns (x) and rows (y), used by format_buffer() and
     /// the text layer renderer to format and display the console on screen
-    pub fn _get_size(&self) -> (usize, usize) {
+    pub fn get_size(&self) -> (usize, usize) {
         self.screen_size
     }
 
     /// Top-Left corner, used by the text layer renderer to show the console at the right place on the screen
-    pub fn _get_coordinates(&self) -> (usize, usize) {
+    pub fn get_coordinates(&self) -> (usize, usize) {
         self.screen_coordinates
     }
 
+    /// True if the cell of the text layer is in the terminal, its border or its title bar.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        let (left, top, right, bottom) = self.get_frame_margins();
+        let (columns, rows) = self.screen_size;
+        let (column, row) = self.screen_coordinates;
+        (column - left..column + columns + right).contains(&x)
+            && (row - top..row + rows + bottom).contains(&y)
+    }
+
+    /// Cells taken by the border and the title bar around the terminal,
+    /// left, top, right and bottom.
+    fn get_frame_margins(&self) -> (usize, usize, usize, usize) {
+        match (self.show_border, self.show_title_bar) {
+            (true, _) => (1, 1, 1, 1),
+            (false, true) => (0, 1, 0, 0),
+            (false, false) => (0, 0, 0, 0),
+        }
+    }
+
+    /// Size of the inside of the terminal, the border and title bar are drawn around it.
+    /// Set the border and title bar before, so that the whole window fits on the screen.
     pub fn set_size(&mut self, size: (usize, usize)) {
-        let col_count = size.0.clamp(10, TEXT_COLUMNS);
-        let row_count = size.1.clamp(3, TEXT_ROWS);
-        self.screen_size = (col_count, row_count)
+        let (left, top, right, bottom) = self.get_frame_margins();
+        let col_count = size.0.clamp(10, TEXT_COLUMNS - left - right);
+        let row_count = size.1.clamp(3, TEXT_ROWS - top - bottom);
+        self.screen_size = (col_count, row_count);
+        self.set_coordinates(self.screen_coordinates);
     }
 
+    /// Top-Left corner of the inside of the terminal, the border and title bar are drawn around it.
     pub fn set_coordinates(&mut self, xy_coord: (usize, usize)) {
-        let x = xy_coord.0.clamp(0, TEXT_COLUMNS - self.screen_size.0);
-        let y = xy_coord.1.clamp(0, TEXT_ROWS - self.screen_size.1);
+        let (left, top, right, bottom) = self.get_frame_margins();
+        let x = xy_coord
+            .0
+            .clamp(left, TEXT_COLUMNS - self.screen_size.0 - right);
+        let y = xy_coord
+            .1
+            .clamp(top, TEXT_ROWS - self.screen_size.1 - bottom);
         self.screen_coordinates = (x, y);
     }
 
+    pub fn is_full_screen(&self) -> bool {
+        self.screen_size == (TEXT_COLUMNS, TEXT_ROWS)
+    }
+
     pub fn pop_char(&mut self) {
+        self.stop_positioning();
         if self.buffer.last().is_some() {
             self.buffer.pop();
         }
@@ -71,18 +193,224 @@ impl Terminal {
     }
 
     /// Add a char to the consoles's buffer
-    /// will convert it to a TextLayerChar with the console's default
-    /// attributes and then call push_text_layer_char()
+    /// ANSI/VT100 escape sequences are interpreted, other characters are converted
+    /// to a TextLayerChar with the current style and passed to push_text_layer_char()
     pub fn push_char(&mut self, c: char) {
-        let text_layer_char = TextLayerChar {
-            c,
+        if let Some(transcript) = self.transcript.as_mut() {
+            transcript.push(c);
+        }
+
+        match self.parser.feed(c) {
+            Some(AnsiAction::Print(c)) => {
+                let text_layer_char = TextLayerChar { c, ..self.style };
+                self.push_text_layer_char(text_layer_char);
+            }
+            Some(AnsiAction::Csi { params, final_char }) => self.execute_csi(&params, final_char),
+            Some(AnsiAction::SaveCursor) => self.saved_cursor = self.get_cursor_position(),
+            Some(AnsiAction::RestoreCursor) => {
+                let (column, row) = self.saved_cursor;
+                self.move_cursor_to(column, row);
+            }
+            Some(AnsiAction::Reset) => {
+                self.reset_style();
+                self.clear();
+            }
+            None => (),
+        }
+    }
+
+    /// Control characters act like on a real terminal instead of the shell's
+    /// line editing, for programs made for one.
+    pub fn set_tty_mode(&mut self, tty: bool) {
+        self.parser.set_tty(tty);
+    }
+
+    fn reset_style(&mut self) {
+        self.style = TextLayerChar {
+            c: ' ',
             color: self.default_color,
             bkg_color: self.default_bkg_color,
             swap: false,
             blink: false,
             shadowed: false,
         };
-        self.push_text_layer_char(text_layer_char);
+    }
+
+    /// Control sequences, ESC [ params final_char
+    fn execute_csi(&mut self, params: &[u16], final_char: char) {
+        let (columns, rows) = self.screen_size;
+        let param = |index: usize| params.get(index).copied().unwrap_or(0) as usize;
+        // Moves and positions count from 1, 0 or no parameter means 1
+        let count = param(0).max(1);
+
+        match final_char {
+            'm' => self.set_graphic_rendition(params),
+            'A' | 'B' | 'C' | 'D' | 'E' | 'F' | 'G' | 'I' | 'd' => {
+                let (column, row) = self.get_cursor_position();
+                let (column, row) = match final_char {
+                    'A' => (column, row.saturating_sub(count)),
+                    'B' => (column, row + count),
+                    'C' => (column + count, row),
+                    'D' => (column.saturating_sub(count), row),
+                    'E' => (0, row + count),
+                    'F' => (0, row.saturating_sub(count)),
+                    'G' => (count - 1, row),
+                    'I' => ((column / TAB_WIDTH + count) * TAB_WIDTH, row),
+                    _ => (column, count - 1),
+                };
+                self.move_cursor_to(column, row);
+            }
+            'H' | 'f' => self.move_cursor_to(param(1).max(1) - 1, count - 1),
+            'J' => {
+                let (column, row) = self.get_cursor_position();
+                let position = row * columns + column;
+                match param(0) {
+                    0 => self.erase(position..columns * rows),
+                    1 => self.erase(0..position + 1),
+                    _ => self.erase(0..columns * rows),
+                }
+            }
+            'K' => {
+                let (column, row) = self.get_cursor_position();
+                let row_start = row * columns;
+                match param(0) {
+                    0 => self.erase(row_start + column..row_start + columns),
+                    1 => self.erase(row_start..row_start + column + 1),
+                    _ => self.erase(row_start..row_start + columns),
+                }
+            }
+            's' => self.saved_cursor = self.get_cursor_position(),
+            'u' => {
+                let (column, row) = self.saved_cursor;
+                self.move_cursor_to(column, row);
+            }
+            _ => (),
+        }
+    }
+
+    /// ESC [ ... m, colors are mapped to the palette, bold is shown shadowed,
+    /// blink and reverse use the text layer's blink and swap.
+    fn set_graphic_rendition(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            self.reset_style();
+            return;
+        }
+
+        let mut index = 0;
+        while index < params.len() {
+            match params[index] {
+                0 => self.reset_style(),
+                1 => self.style.shadowed = true,
+                22 => self.style.shadowed = false,
+                5 | 6 => self.style.blink = true,
+                25 => self.style.blink = false,
+                7 => self.style.swap = true,
+                27 => self.style.swap = false,
+                code @ 30..=37 => self.style.color = ansi_color(code - 30),
+                39 => self.style.color = self.default_color,
+                code @ 40..=47 => self.style.bkg_color = ansi_color(code - 40),
+                49 => self.style.bkg_color = self.default_bkg_color,
+                code @ 90..=97 => self.style.color = ansi_color(code - 90 + 8),
+                code @ 100..=107 => self.style.bkg_color = ansi_color(code - 100 + 8),
+                // 256 colors: 38;5;n, RGB: 38;2;r;g;b, 48 for the background
+                code @ (38 | 48) => {
+                    let color = match params.get(index + 1) {
+                        Some(5) => {
+                            index += 2;
+                            params.get(index).map(|color| ansi_color(*color))
+                        }
+                        Some(2) => {
+                            index += 4;
+                            match params.get(index - 2..=index) {
+                                Some([r, g, b]) => Some(nearest_color(
+                                    (*r).min(255) as u8,
+                                    (*g).min(255) as u8,
+                                    (*b).min(255) as u8,
+                                )),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    match (code, color) {
+                        (38, Some(color)) => self.style.color = color,
+                        (_, Some(color)) => self.style.bkg_color = color,
+                        _ => (),
+                    }
+                }
+                _ => (),
+            }
+            index += 1;
+        }
+    }
+
+    /// Column and row of the cursor on the screen.
+    fn get_cursor_position(&mut self) -> (usize, usize) {
+        self.flatten();
+        let position = self.write_position.unwrap_or(0);
+        (position % self.screen_size.0, position / self.screen_size.0)
+    }
+
+    fn move_cursor_to(&mut self, column: usize, row: usize) {
+        self.flatten();
+        let column = column.min(self.screen_size.0 - 1);
+        let row = row.min(self.screen_size.1 - 1);
+        self.write_position = Some(row * self.screen_size.0 + column);
+        self.format_buffer();
+        self.settle_write_position();
+    }
+
+    /// Blanks cells of the screen with the current background color.
+    fn erase(&mut self, cells: Range<usize>) {
+        self.flatten();
+        let blank = TextLayerChar {
+            c: ' ',
+            swap: false,
+            blink: false,
+            shadowed: false,
+            ..self.style
+        };
+        let end = cells.end.min(self.buffer.len());
+        let start = cells.start.min(end);
+        self.buffer[start..end].fill(blank);
+        self.format_buffer();
+        self.settle_write_position();
+    }
+
+    /// Turns the buffer into the cells of the screen so that the cursor can move around.
+    fn flatten(&mut self) {
+        if self.write_position.is_some() {
+            return;
+        }
+        self.cursor_offset = 0;
+        self.format_buffer();
+        self.buffer = self.formatted_buffer.clone();
+        self.buffer[self.cursor_cell] = self.get_empty_cell();
+        self.write_position = Some(self.cursor_cell);
+    }
+
+    /// Goes back to adding characters at the end of the buffer once there are only
+    /// blank cells after the write position.
+    fn settle_write_position(&mut self) {
+        if let Some(position) = self.write_position {
+            let empty_cell = self.get_empty_cell();
+            if self.buffer[position.min(self.buffer.len())..]
+                .iter()
+                .all(|cell| *cell == empty_cell)
+            {
+                self.buffer.truncate(position);
+                self.write_position = None;
+                self.format_buffer();
+            }
+        }
+    }
+
+    /// Drops what's after the write position, for the line being typed that is edited
+    /// from the end of the buffer.
+    fn stop_positioning(&mut self) {
+        if let Some(position) = self.write_position.take() {
+            self.buffer.truncate(position);
+        }
     }
 
     /// Add the whole content of a &str to the consoles's buffer
@@ -93,22 +421,101 @@ impl Terminal {
         }
     }
 
+    /// Add UTF-8 text received from a program or a connection, a character
+    /// cut in two is completed by the next call. Invalid bytes show as U+FFFD.
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        self.pending_bytes.extend_from_slice(bytes);
+        let pending_bytes = std::mem::take(&mut self.pending_bytes);
+        let mut rest = pending_bytes.as_slice();
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    self.push_string(valid);
+                    break;
+                }
+                Err(error) => {
+                    let (valid, invalid) = rest.split_at(error.valid_up_to());
+                    self.push_string(std::str::from_utf8(valid).unwrap_or_default());
+                    match error.error_len() {
+                        Some(invalid_len) => {
+                            self.push_char(char::REPLACEMENT_CHARACTER);
+                            rest = &invalid[invalid_len..];
+                        }
+                        None => {
+                            self.pending_bytes = invalid.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts or stops keeping a copy of the characters pushed, escape sequences
+    /// included, for take_transcript().
+    pub fn set_recording(&mut self, recording: bool) {
+        self.transcript = recording.then(String::new);
+    }
+
+    /// Adds text to the transcript that is already on screen, like the line
+    /// being typed that is drawn with replace_last_chars().
+    pub fn record(&mut self, text: &str) {
+        if let Some(transcript) = self.transcript.as_mut() {
+            transcript.push_str(text);
+        }
+    }
+
+    /// The characters pushed since the last call, empty when not recording.
+    pub fn take_transcript(&mut self) -> String {
+        self.transcript.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     /// Pushes a TextLayerChar into the console's buffer (Vec<TextLayerChar>)
     /// if the character received is BACKSPACE, will pop the last character instead.
+    /// When the cursor has been moved, the character is written at its position.
     pub fn push_text_layer_char(&mut self, text_layer_char: TextLayerChar) {
-        match text_layer_char.c {
-            unicode::BACKSPACE => {
+        let columns = self.screen_size.0;
+
+        match (self.write_position, text_layer_char.c) {
+            (None, unicode::BACKSPACE) => {
                 self.pop_char();
             }
-            _ => {
+            (None, _) => {
                 self.buffer.push(text_layer_char);
             }
+            (Some(position), unicode::ENTER) => {
+                let next_row = (position / columns + 1) * columns;
+                if next_row >= self.buffer.len() {
+                    let empty_cell = self.get_empty_cell();
+                    self.buffer.resize(next_row + columns, empty_cell);
+                }
+                self.write_position = Some(next_row);
+            }
+            (Some(position), unicode::BACKSPACE) => {
+                if position % columns > 0 {
+                    self.buffer[position - 1] = self.get_empty_cell();
+                    self.write_position = Some(position - 1);
+                }
+            }
+            (Some(position), _) => {
+                match self.buffer.get_mut(position) {
+                    Some(cell) => *cell = text_layer_char,
+                    None => self.buffer.push(text_layer_char),
+                }
+                self.write_position = Some(position + 1);
+            }
         }
 
-        if self.buffer.len() > self.max_buffer_size {
-            self.buffer.remove(0);
-        }
+        self.format_buffer();
+        self.settle_write_position();
+    }
 
+    /// Replaces the last count characters of the buffer with the ones passed as
+    /// parameter, formatting the buffer only once. Used to redraw the line being edited.
+    pub fn replace_last_chars(&mut self, count: usize, text_layer_chars: &[TextLayerChar]) {
+        self.stop_positioning();
+        self.buffer.truncate(self.buffer.len().saturating_sub(count));
+        self.buffer.extend_from_slice(text_layer_chars);
         self.format_buffer();
     }
 
@@ -158,7 +565,20 @@ impl Terminal {
     fn format_buffer(&mut self) {
         self.formatted_buffer.clear();
 
-        for console_char in &self.buffer {
+        let cursor_index = match (self.write_position, self.cursor_offset) {
+            (Some(position), _) => Some(position),
+            (None, 0) => None,
+            (None, offset) => self.buffer.len().checked_sub(offset),
+        };
+
+        // Index in the buffer of the first character of each row
+        let mut row_starts: Vec<usize> = Vec::new();
+
+        for (index, console_char) in self.buffer.iter().enumerate() {
+            if self.formatted_buffer.len().is_multiple_of(self.screen_size.0) {
+                row_starts.push(index);
+            }
+
             match console_char.c {
                 unicode::ENTER => {
                     for _i in
@@ -167,14 +587,67 @@ impl Terminal {
                         self.formatted_buffer.push(self.get_empty_cell())
                     }
                 }
+                _ if Some(index) == cursor_index => {
+                    // A moved cursor on an empty cell is shown like at the end of the buffer
+                    let c = match (self.write_position, console_char.c) {
+                        (Some(_), ' ') => self.cursor,
+                        (_, c) => c,
+                    };
+                    self.cursor_cell = self.formatted_buffer.len();
+                    self.formatted_buffer.push(TextLayerChar {
+                        c,
+                        blink: true,
+                        ..*console_char
+                    })
+                }
                 _ => self.formatted_buffer.push(*console_char),
             }
         }
-        self.formatted_buffer.push(self.get_cursor());
 
-        // pop first line if rendered buffer is bigger than screen_size
-        while self.formatted_buffer.len() > (self.screen_size.0 * self.screen_size.1) {
-            self.formatted_buffer.drain(0..self.screen_size.0);
+        if cursor_index.is_none_or(|index| index >= self.buffer.len()) {
+            self.cursor_cell = self.formatted_buffer.len();
+            self.formatted_buffer.push(self.get_cursor());
+        } else if self.write_position.is_none() {
+            self.formatted_buffer.push(self.get_empty_cell());
+        }
+
+        // Rows above the screen go to the scrollback, and their characters leave the buffer
+        let screen_len = self.screen_size.0 * self.screen_size.1;
+        if self.formatted_buffer.len() > screen_len {
+            let drained_row_count = self.formatted_buffer.len().div_ceil(self.screen_size.0)
+                - self.screen_size.1;
+            let drained_rows: Vec<TextLayerChar> = self
+                .formatted_buffer
+                .drain(0..drained_row_count * self.screen_size.0)
+                .collect();
+            let first_kept_char = row_starts
+                .get(drained_row_count)
+                .copied()
+                .unwrap_or(self.buffer.len());
+            self.buffer.drain(0..first_kept_char);
+            self.cursor_cell = self
+                .cursor_cell
+                .saturating_sub(drained_row_count * self.screen_size.0);
+            if let Some(position) = self.write_position.as_mut() {
+                *position = position.saturating_sub(first_kept_char);
+            }
+
+            for row in drained_rows.chunks(self.screen_size.0) {
+                self.scrollback.push_back(row.to_vec());
+            }
+            while self.scrollback.len() > MAX_SCROLLBACK_ROWS {
+                self.scrollback.pop_front();
+            }
+
+            // Keeps showing the same rows when looking at the scrollback
+            if self.scrollback_position > 0 {
+                self.scroll_up(drained_row_count);
+            }
+
+            // keep the rows that just scrolled out to make them glide out of the screen
+            if self.smooth_scroll {
+                self.scrolled_out_rows.extend_from_slice(&drained_rows);
+            }
         }
 
         // fill the rest with empty cells
@@ -183,10 +656,215 @@ impl Terminal {
         }
     }
 
-    pub fn render(&mut self, dc: &mut DisplayController) {
-        for (index, tlchar) in self.get_formatted_buffer().iter().enumerate() {
+    /// Moves the rows that scrolled out a few pixels up, and drops them once they
+    /// are completely out of the screen.
+    fn update_smooth_scroll(&mut self) {
+        let pending_rows = self.scrolled_out_rows.len() / self.screen_size.0;
+        self.scroll_progress += SMOOTH_SCROLL_SPEED * pending_rows;
+
+        while self.scroll_progress >= 8 && !self.scrolled_out_rows.is_empty() {
+            self.scrolled_out_rows.drain(0..self.screen_size.0);
+            self.scroll_progress -= 8;
+        }
+
+        if self.scrolled_out_rows.is_empty() {
+            self.scroll_progress = 0;
+        }
+    }
+
+    /// Writes a cell of the inside of the terminal on the text layer.
+    fn put_cell(
+        &self,
+        dc: &mut DisplayController,
+        column: usize,
+        row: usize,
+        tlchar: TextLayerChar,
+    ) {
+        let (x, y) = self.screen_coordinates;
+        dc.get_text_layer_mut()
+            .insert_text_layer_char_xy(x + column, y + row, tlchar);
+    }
+
+    /// Draws the border and the title bar around the terminal.
+    /// The title is highlighted when the terminal has the focus.
+    fn render_frame(&self, dc: &mut DisplayController, focused: bool) {
+        let (left, top, _, _) = self.get_frame_margins();
+        if top == 0 {
+            return;
+        }
+
+        let (x, y) = self.screen_coordinates;
+        let (columns, rows) = self.screen_size;
+        let frame_x = x - left;
+        let frame_width = columns + 2 * left;
+        let frame_cell = |c: char| TextLayerChar {
+            c,
+            ..self.get_empty_cell()
+        };
+        let title_cell = |c: char| TextLayerChar {
+            c,
+            swap: focused,
+            ..self.get_empty_cell()
+        };
+
+        let mut top_row: Vec<TextLayerChar> = Vec::with_capacity(frame_width);
+        if self.show_border {
+            top_row.push(frame_cell('\u{256D}'));
+            top_row.push(frame_cell('\u{2500}'));
+        }
+        if self.show_title_bar {
+            let side_cells = if self.show_border { 7 } else { 2 };
+            let title: String = self
+                .title
+                .chars()
+                .take(frame_width.saturating_sub(side_cells))
+                .collect();
+            let title = title.trim_end();
+            if self.show_border {
+                top_row.push(frame_cell('\u{2524}'));
+            }
+            top_row.extend(format!(" {} ", title).chars().map(title_cell));
+            if self.show_border {
+                top_row.push(frame_cell('\u{251C}'));
+            }
+        }
+        let fill = if self.show_border {
+            frame_cell('\u{2500}')
+        } else {
+            title_cell(' ')
+        };
+        while top_row.len() < frame_width - left {
+            top_row.push(fill);
+        }
+        if self.show_border {
+            top_row.push(frame_cell('\u{256E}'));
+        }
+
+        let text_layer = dc.get_text_layer_mut();
+        for (column, tlchar) in top_row.into_iter().enumerate() {
+            text_layer.insert_text_layer_char_xy(frame_x + column, y - 1, tlchar);
+        }
+
+        if !self.show_border {
+            return;
+        }
+        for row in y..y + rows {
+            text_layer.insert_text_layer_char_xy(frame_x, row, frame_cell('\u{2502}'));
+            text_layer.insert_text_layer_char_xy(x + columns, row, frame_cell('\u{2502}'));
+        }
+        text_layer.insert_text_layer_char_xy(frame_x, y + rows, frame_cell('\u{2570}'));
+        for column in x..x + columns {
+            text_layer.insert_text_layer_char_xy(column, y + rows, frame_cell('\u{2500}'));
+        }
+        text_layer.insert_text_layer_char_xy(x + columns, y + rows, frame_cell('\u{256F}'));
+    }
+
+    /// A cell of the formatted buffer, the cursor only shows when the terminal has the focus.
+    fn get_cell(&self, index: usize, focused: bool) -> TextLayerChar {
+        let tlchar = self.formatted_buffer[index];
+        if focused || index != self.cursor_cell {
+            return tlchar;
+        }
+
+        let c = match tlchar.c {
+            c if c == self.cursor => ' ',
+            c => c,
+        };
+        TextLayerChar {
+            c,
+            blink: false,
+            ..tlchar
+        }
+    }
+
+    /// The screen moved up in the scrollback, with a status bar at the bottom.
+    fn render_scrollback(&self, dc: &mut DisplayController) {
+        let (columns, rows) = self.screen_size;
+        let start = self.scrollback.len() - self.scrollback_position;
+        let screen_rows = self
+            .scrollback
+            .iter()
+            .skip(start)
+            .map(|row| row.as_slice())
+            .chain(self.formatted_buffer.chunks(columns))
+            .take(rows - 1);
+
+        let empty_cell = self.get_empty_cell();
+        let mut row_count = 0;
+        for (row_index, row) in screen_rows.enumerate() {
+            for column in 0..columns {
+                let tlchar = row.get(column).copied().unwrap_or(empty_cell);
+                self.put_cell(dc, column, row_index, tlchar);
+            }
+            row_count += 1;
+        }
+
+        let status = format!(
+            "{:^width$}",
+            format!("{} more rows, Shift+PgDn", self.scrollback_position),
+            width = columns
+        );
+        for (column, c) in status.chars().take(columns).enumerate() {
+            let tlchar = TextLayerChar {
+                c,
+                swap: true,
+                ..empty_cell
+            };
+            self.put_cell(dc, column, row_count, tlchar);
+        }
+    }
+
+    /// Draws the terminal on the text layer, at its coordinates.
+    /// Only a full screen terminal on top scrolls smoothly, since that moves the whole text layer.
+    pub fn render(&mut self, dc: &mut DisplayController, focused: bool) {
+        self.render_frame(dc, focused);
+
+        if self.scrollback_position > 0 {
+            self.scrolled_out_rows.clear();
+            self.scroll_progress = 0;
+            dc.get_text_layer_mut().set_scroll_offset(0, 0);
+            self.render_scrollback(dc);
+            return;
+        }
+
+        if !self.is_full_screen() || !focused {
+            self.scrolled_out_rows.clear();
+        }
+        self.update_smooth_scroll();
+
+        if self.scrolled_out_rows.is_empty() {
+            dc.get_text_layer_mut().set_scroll_offset(0, 0);
+            let columns = self.screen_size.0;
+            for index in 0..self.get_formatted_buffer().len() {
+                let tlchar = self.get_cell(index, focused);
+                self.put_cell(dc, index % columns, index / columns, tlchar);
+            }
+            return;
+        }
+
+        // While gliding, the rows that scrolled out are shown above the current content
+        // and the whole text layer is moved up, the bottom rows slide in blank.
+        let (area_x, area_y) = text_coord_to_frame_coord(0, 0);
+        dc.push_clip_rect(
+            area_x as isize,
+            area_y as isize,
+            TEXT_COLUMNS * 8,
+            TEXT_ROWS * 8,
+        );
+        dc.clear_viewport(self.default_bkg_color);
+        dc.pop_viewport();
+
+        let screen_len = self.screen_size.0 * self.screen_size.1;
+        let gliding_buffer = self
+            .scrolled_out_rows
+            .iter()
+            .chain(self.formatted_buffer.iter())
+            .take(screen_len);
+        for (index, tlchar) in gliding_buffer.enumerate() {
             dc.get_text_layer_mut()
                 .insert_text_layer_char(index, *tlchar);
         }
+        dc.get_text_layer_mut()
+            .set_scroll_offset(0, -(self.scroll_progress as isize));
     }
 }
@@ -0,0 +1,136 @@
+use fantasy_cpc_display_controller::DisplayController;
+
+use super::{line_editor::LineEditor, terminal::Terminal};
+
+/// Size of the inside of a new window
+const DEFAULT_WINDOW_SIZE: (usize, usize) = (30, 12);
+
+/// Each new window is placed that many cells right and down from the previous one
+const CASCADE_STEP: usize = 2;
+const CASCADE_POSITIONS: usize = 4;
+
+/// A terminal on the screen with its own command line.
+/// The terminal records what's printed in it, for the network console.
+pub struct Window {
+    /// Stays the same when the window changes place in the z-order
+    pub id: usize,
+    pub terminal: Terminal,
+    pub editor: LineEditor,
+}
+
+/// Text mode windows drawn on top of each other in the text layer.
+/// The windows are kept in z-order: the last one is on top and has the focus.
+/// There is always at least one window, the full screen one opened at start.
+pub struct WindowManager {
+    windows: Vec<Window>,
+    next_id: usize,
+}
+
+impl WindowManager {
+    pub fn new() -> WindowManager {
+        let mut terminal = Terminal::new();
+        terminal.title = String::from("Console");
+        terminal.set_recording(true);
+
+        WindowManager {
+            windows: vec![Window {
+                id: 0,
+                terminal,
+                editor: LineEditor::new(),
+            }],
+            next_id: 1,
+        }
+    }
+
+    pub fn get_focused(&self) -> &Window {
+        self.windows.last().expect("There is always a window")
+    }
+
+    pub fn get_focused_mut(&mut self) -> &mut Window {
+        self.windows.last_mut().expect("There is always a window")
+    }
+
+    pub fn get_window_mut(&mut self, id: usize) -> Option<&mut Window> {
+        self.windows.iter_mut().find(|window| window.id == id)
+    }
+
+    /// The windows from the one on top to the one at the bottom.
+    pub fn get_windows(&self) -> impl Iterator<Item = &Window> {
+        self.windows.iter().rev()
+    }
+
+    pub fn get_windows_mut(&mut self) -> impl Iterator<Item = &mut Window> {
+        self.windows.iter_mut().rev()
+    }
+
+    /// Id of the window on top at that cell of the text layer.
+    pub fn get_window_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.get_windows()
+            .find(|window| window.terminal.contains(x, y))
+            .map(|window| window.id)
+    }
+
+    /// Opens a window with a border and a title on top of the others, and gives it the focus.
+    /// Windows are cascaded from the top left corner of the screen.
+    pub fn open(&mut self, title: &str) -> &mut Window {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let offset = 1 + CASCADE_STEP * ((id - 1) % CASCADE_POSITIONS);
+        let mut terminal = Terminal::new();
+        terminal.show_border = true;
+        terminal.show_title_bar = true;
+        terminal.title = match title {
+            "" => format!("Shell {}", id),
+            _ => title.to_string(),
+        };
+        terminal.smooth_scroll = false;
+        terminal.set_recording(true);
+        terminal.set_size(DEFAULT_WINDOW_SIZE);
+        terminal.set_coordinates((offset, offset));
+
+        // The windows share the history, a command typed in one can be recalled in the others
+        let history = self.get_focused().editor.get_history();
+        self.windows.push(Window {
+            id,
+            terminal,
+            editor: LineEditor::with_history(history),
+        });
+        self.get_focused_mut()
+    }
+
+    /// Closes the window that has the focus, the one below gets it.
+    /// The last window can't be closed, returns false then.
+    pub fn close_focused(&mut self) -> bool {
+        if self.windows.len() == 1 {
+            return false;
+        }
+        self.windows.pop();
+        true
+    }
+
+    /// Sends the window on top to the bottom, the next one gets the focus.
+    pub fn focus_next(&mut self) {
+        self.windows.rotate_right(1);
+    }
+
+    /// Brings a window to the top and gives it the focus, returns false if there's no such window.
+    pub fn focus(&mut self, id: usize) -> bool {
+        let Some(index) = self.windows.iter().position(|window| window.id == id) else {
+            return false;
+        };
+        let window = self.windows.remove(index);
+        self.windows.push(window);
+        true
+    }
+
+    /// Draws the windows from the bottom to the top, the text layer is cleared first
+    /// so that what's not covered by a window shows the background.
+    pub fn render(&mut self, dc: &mut DisplayController) {
+        dc.clear_text_layer();
+        let focused_index = self.windows.len() - 1;
+        for (index, window) in self.windows.iter_mut().enumerate() {
+            window.terminal.render(dc, index == focused_index);
+        }
+    }
+}
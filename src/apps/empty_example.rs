@@ -1,4 +1,4 @@
-use fantasy_cpc_app::{AppResponse, FantasyCpcApp, FantasyCppAppDefaultParams};
+use fantasy_cpc_app::{input::InputState, AppResponse, FantasyCpcApp, FantasyCppAppDefaultParams};
 use fantasy_cpc_clock::Clock;
 use fantasy_cpc_display_controller::DisplayController;
 
@@ -21,11 +21,7 @@ impl FantasyCpcApp for Empty {
 
     fn init_app(&mut self, _system_clock: &Clock, _display_controller: &mut DisplayController) {}
 
-    fn update_app(
-        &mut self,
-        _inputs: Option<&winit_input_helper::WinitInputHelper>,
-        _clock: &Clock,
-    ) -> Option<AppResponse> {
+    fn update_app(&mut self, _inputs: Option<&InputState>, _clock: &Clock) -> Option<AppResponse> {
         None
     }
 
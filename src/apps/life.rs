@@ -1,6 +1,14 @@
 use std::time::Instant;
 
-use fantasy_cpc_app::{AppResponse, AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams};
+use fantasy_cpc_app::{
+    input::{InputState, Key},
+    ui::{
+        form::Form,
+        widget::{Response, Theme},
+        widgets::{Button, Label, Slider},
+    },
+    AppResponse, AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams,
+};
 use fantasy_cpc_clock::Clock;
 use fantasy_cpc_display_controller::{
     color_palettes::*,
@@ -8,8 +16,6 @@ use fantasy_cpc_display_controller::{
     DisplayController,
 };
 use rand::Rng;
-use winit::event::VirtualKeyCode;
-use winit_input_helper::WinitInputHelper;
 
 pub struct Life {
     app_params: FantasyCppAppDefaultParams,
@@ -21,6 +27,7 @@ pub struct Life {
     welcome_screen: bool,
     game: bool,
     menu: bool,
+    team_menu: Form,
     alive: bool,
     team_a_color: usize,
     team_b_color: usize,
@@ -75,6 +82,7 @@ impl Life {
             welcome_screen: true,
             game: false,
             menu: false,
+            team_menu: Life::build_menu(8, 28),
             team_a_color: 8,
             team_b_color: 28,
             random_game_mode: true,
@@ -137,17 +145,17 @@ impl Life {
     *************************************************************************************************************
     **************************************************************************************************************/
 
-    fn update_welcome_screen(&mut self, inputs: Option<&WinitInputHelper>) {
+    fn update_welcome_screen(&mut self, inputs: Option<&InputState>) {
         if inputs.is_none() {
             return;
         }
         let user_inputs = inputs.unwrap();
 
-        if user_inputs.key_pressed(VirtualKeyCode::Escape) {
+        if user_inputs.key_pressed(Key::Escape) {
             self.get_app_params().change_status(AppStatus::Stopped)
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key1) {
+        if user_inputs.key_pressed(Key::Key1) {
             self.welcome_screen = false;
             self.menu = false;
             self.game = true;
@@ -155,9 +163,10 @@ impl Life {
             self.restart_sim();
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key2) {
+        if user_inputs.key_pressed(Key::Key2) {
             self.welcome_screen = false;
             self.menu = true;
+            self.team_menu = Life::build_menu(self.team_a_color, self.team_b_color);
             self.game = false;
             self.random_game_mode = false;
             self.restart_sim();
@@ -290,12 +299,12 @@ impl Life {
     *************************************************************************************************************
     **************************************************************************************************************/
 
-    fn update_game(&mut self, inputs: Option<&WinitInputHelper>) {
-        if inputs.is_some() && inputs.unwrap().key_pressed(VirtualKeyCode::C) {
+    fn update_game(&mut self, inputs: Option<&InputState>) {
+        if inputs.is_some() && inputs.unwrap().key_pressed(Key::C) {
             self.restart_sim();
         }
 
-        if inputs.is_some() && inputs.unwrap().key_pressed(VirtualKeyCode::Escape) {
+        if inputs.is_some() && inputs.unwrap().key_pressed(Key::Escape) {
             self.get_app_params().set_initialized(false);
         }
 
@@ -379,91 +388,86 @@ impl Life {
     *************************************************************************************************************
     **************************************************************************************************************/
 
-    fn update_menu(&mut self, inputs: Option<&WinitInputHelper>) {
+    /// Colors of the teams, with a star in each color next to its slider
+    fn build_menu(team_a_color: usize, team_b_color: usize) -> Form {
+        let mut menu = Form::new(Theme::new(BLUE, BLACK, ORANGE));
+        menu.add("title", Label::new(5, 3, "Combat mode"));
+
+        let mut team_a = Slider::new(5, 5, "Team A", 1.0, 30.0, 1.0);
+        team_a.set_value(team_a_color as f32);
+        menu.add("team a", team_a);
+
+        let mut team_b = Slider::new(5, 7, "Team B", 1.0, 30.0, 1.0);
+        team_b.set_value(team_b_color as f32);
+        menu.add("team b", team_b);
+
+        menu.add("start", Button::new(5, 10, "Start"));
+        menu
+    }
+
+    fn update_menu(&mut self, inputs: Option<&InputState>) {
         if inputs.is_none() {
             return;
         }
         let user_inputs = inputs.unwrap();
 
-        if user_inputs.key_pressed(VirtualKeyCode::Escape) {
+        if user_inputs.key_pressed(Key::Escape) {
             self.welcome_screen = true;
             self.menu = false;
             self.game = false;
+            return;
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Left) {
-            self.team_a_color -= 1;
-        }
-
-        if user_inputs.key_pressed(VirtualKeyCode::Right) {
-            self.team_a_color += 1;
-        }
-
-        if user_inputs.key_pressed(VirtualKeyCode::Up) {
-            self.team_b_color += 1;
-        }
-
-        if user_inputs.key_pressed(VirtualKeyCode::Down) {
-            self.team_b_color -= 1;
-        }
-
-        if user_inputs.key_pressed(VirtualKeyCode::Return) {
-            self.welcome_screen = false;
-            self.menu = false;
-            self.game = true;
-        }
-
-        if self.team_a_color == 0 || self.team_a_color >= 31 {
-            self.team_a_color = 1
-        }
-        if self.team_b_color == 0 || self.team_b_color >= 31 {
-            self.team_b_color = 1
+        let event = self.team_menu.update(user_inputs);
+        let value = |id| {
+            self.team_menu
+                .get::<Slider>(id)
+                .map_or(1.0, |slider| slider.get_value())
+        };
+
+        match event.map(|event| (event.id, event.response)) {
+            Some(("team a", Response::Changed)) => self.team_a_color = value("team a") as usize,
+            Some(("team b", Response::Changed)) => self.team_b_color = value("team b") as usize,
+            Some(("start", Response::Activated)) => {
+                self.welcome_screen = false;
+                self.menu = false;
+                self.game = true;
+            }
+            // ENTER on a slider also starts the game
+            None if user_inputs.key_pressed(Key::Return) => {
+                self.welcome_screen = false;
+                self.menu = false;
+                self.game = true;
+            }
+            _ => (),
         }
     }
 
     fn draw_menu(&mut self, display_controller: &mut DisplayController) {
         display_controller.get_text_layer_mut().clear();
         display_controller.clear(BLACK);
-        display_controller.get_text_layer_mut().insert_string_xy(
-            5,
-            5,
-            "Team A : ",
-            Some(BLUE),
-            Some(BLACK),
-            false,
-            false,
-            false,
-        );
-        display_controller.get_text_layer_mut().insert_string_xy(
-            5,
-            7,
-            "Team B : ",
-            Some(BLUE),
-            Some(BLACK),
-            false,
-            false,
-            false,
-        );
-        display_controller.get_text_layer_mut().insert_string_xy(
-            14,
-            5,
-            "🯆",
-            Some(self.team_a_color),
-            Some(BLACK),
-            false,
-            false,
-            false,
-        );
-        display_controller.get_text_layer_mut().insert_string_xy(
-            14,
-            7,
-            "🯆",
-            Some(self.team_b_color),
-            Some(BLACK),
-            false,
-            false,
-            false,
-        );
+        self.team_menu.draw(display_controller.get_text_layer_mut());
+
+        // A star in the color of each team, after its slider
+        for (id, color) in [
+            ("team a", self.team_a_color),
+            ("team b", self.team_b_color),
+        ] {
+            let Some(slider) = self.team_menu.get::<Slider>(id) else {
+                continue;
+            };
+            let (x, y) = slider.get_position();
+            display_controller.get_text_layer_mut().insert_string_xy(
+                x + slider.get_width() + 1,
+                y,
+                "🯆",
+                Some(color),
+                Some(BLACK),
+                false,
+                false,
+                false,
+            );
+        }
     }
 }
 
@@ -589,11 +593,7 @@ impl FantasyCpcApp for Life {
         self.menu = false;
     }
 
-    fn update_app(
-        &mut self,
-        inputs: Option<&winit_input_helper::WinitInputHelper>,
-        _clock: &Clock,
-    ) -> Option<AppResponse> {
+    fn update_app(&mut self, inputs: Option<&InputState>, _clock: &Clock) -> Option<AppResponse> {
         if self.welcome_screen {
             self.update_welcome_screen(inputs);
         } else if self.game {
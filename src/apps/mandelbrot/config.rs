@@ -7,4 +7,6 @@ pub const Y_COORD: f64 = -0.15901460025672767;
 pub const EMPTY_RATIO_TRIGGER: f64 = 0.15;
 pub const ANTI_EMPTY_RATIO_TRIGGER: f64 = EMPTY_RATIO_TRIGGER * 0.65;
 pub const EMPTY_RATIO_DELTA_TRIGGER: f64 = 0.001;
-pub const RANGE_DIVIDER_AKA_SPEED: f64 = 100.0;
\ No newline at end of file
+pub const RANGE_DIVIDER_AKA_SPEED: f64 = 100.0;
+/// Part of the screen crossed in one frame while the joystick is pushed
+pub const JOYSTICK_MOVE_RATIO: f64 = 0.01;
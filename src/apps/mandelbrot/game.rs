@@ -1,9 +1,15 @@
-use fantasy_cpc_app::{AppResponse, FantasyCpcApp, FantasyCppAppDefaultParams};
+use fantasy_cpc_app::{
+    input::{InputState, Key},
+    joystick::JoystickButton,
+    AppResponse, FantasyCpcApp, FantasyCppAppDefaultParams,
+};
 use fantasy_cpc_clock::Clock;
-use fantasy_cpc_display_controller::{DisplayController, color_palettes::BLACK, config::{OVERSCAN_V, VIRTUAL_HEIGHT, OVERSCAN_H, VIRTUAL_WIDTH}};
+use fantasy_cpc_display_controller::{
+    color_palettes::BLACK,
+    config::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
+    DisplayController,
+};
 use rand::Rng;
-use winit::event::VirtualKeyCode;
-use winit_input_helper::WinitInputHelper;
 
 use super::{
     config::*,
@@ -91,11 +97,7 @@ impl FantasyCpcApp for Mandelbrot {
         self.palette_rotation = false;
     }
 
-    fn update_app(
-        &mut self,
-        inputs: Option<&WinitInputHelper>,
-        _clock: &Clock,
-    ) -> Option<AppResponse> {
+    fn update_app(&mut self, inputs: Option<&InputState>, _clock: &Clock) -> Option<AppResponse> {
         inputs?;
 
         let user_inputs = inputs.unwrap();
@@ -104,68 +106,70 @@ impl FantasyCpcApp for Mandelbrot {
         //                 choosing default scenarios
         /*---------------------------------------------------------- */
 
-        if user_inputs.key_pressed(VirtualKeyCode::Space) {
+        let joystick = user_inputs.get_joystick(0);
+
+        if joystick.is_pressed(JoystickButton::Fire1) {
             self.pause = !self.pause;
             println!("x: {}, y: {}", self.mandel_x_center, self.mandel_y_center);
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key1) {
+        if user_inputs.key_pressed(Key::Key1) {
             self.reset();
             self.mandel_x_center = X_COORD;
             self.mandel_y_center = Y_COORD;
             self.current_theme = 2; //tree
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key2) {
+        if user_inputs.key_pressed(Key::Key2) {
             self.reset();
             self.mandel_x_center = -0.749089134879074;
             self.mandel_y_center = 0.04575273713964573;
             self.current_theme = 1; //cool
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key3) {
+        if user_inputs.key_pressed(Key::Key3) {
             self.reset();
             self.mandel_x_center = -1.254716173206939;
             self.mandel_y_center = -0.03269356495238624;
             self.current_theme = 3; //canyon
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key4) {
+        if user_inputs.key_pressed(Key::Key4) {
             self.reset();
             self.mandel_x_center = 0.26781837605081366;
             self.mandel_y_center = -0.003918849643395729;
             self.current_theme = 0; //warm
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key5) {
+        if user_inputs.key_pressed(Key::Key5) {
             self.reset();
             self.mandel_x_center = -0.10971550489778131;
             self.mandel_y_center = 0.8945121343911098;
             self.current_theme = 2; //tree
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key6) {
+        if user_inputs.key_pressed(Key::Key6) {
             self.reset();
             self.mandel_x_center = -1.403277422173161;
             self.mandel_y_center = -0.00022314715329581908;
             self.current_theme = 2; //tree
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key7) {
+        if user_inputs.key_pressed(Key::Key7) {
             self.reset();
             self.mandel_x_center = -0.19827338980477996;
             self.mandel_y_center = -1.100975539162933;
             self.current_theme = 3; //canyon
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key8) {
+        if user_inputs.key_pressed(Key::Key8) {
             self.reset();
             self.mandel_x_center = -1.9425557680573255;
             self.mandel_y_center = 0.0;
             self.current_theme = 1; //cool
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Key9) {
+        if user_inputs.key_pressed(Key::Key9) {
             self.reset();
             self.mandel_x_center = 0.35069648168066503;
             self.mandel_y_center = -0.06386659763624122;
@@ -176,15 +180,15 @@ impl FantasyCpcApp for Mandelbrot {
         //                      Rendering controls
         /*---------------------------------------------------------- */
 
-        if user_inputs.key_pressed(VirtualKeyCode::Slash) {
+        if user_inputs.key_pressed(Key::Slash) {
             self.reverse = !self.reverse;
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::R) {
+        if user_inputs.key_pressed(Key::R) {
             self.reset();
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Comma) {
+        if user_inputs.key_pressed(Key::Comma) {
             self.max_iteration -= 10;
             if self.max_iteration <= MIN_ITER {
                 self.max_iteration = MIN_ITER
@@ -192,43 +196,32 @@ impl FantasyCpcApp for Mandelbrot {
             println!("max_iteration: {}", self.max_iteration);
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::Period) {
+        if user_inputs.key_pressed(Key::Period) {
             self.max_iteration += 10;
             println!("max_iteration: {}", self.max_iteration);
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::P) {
+        if user_inputs.key_pressed(Key::P) {
             self.swap_palette();
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::F) {
+        if user_inputs.key_pressed(Key::F) {
             self.fuzzy = !self.fuzzy;
         }
 
-        if user_inputs.key_pressed(VirtualKeyCode::X) {
+        if user_inputs.key_pressed(Key::X) {
             self.palette_rotation = !self.palette_rotation;
         }
 
         /*---------------------------------------------------------- */
         //                      Movement controls
         /*---------------------------------------------------------- */
-        if user_inputs.key_pressed_os(VirtualKeyCode::Up) {
-            self.mandel_y_center -= self.mandel_y_range / 50.0;
-            println!("x: {}, y: {}", self.mandel_x_center, self.mandel_y_center);
-        }
-
-        if user_inputs.key_pressed_os(VirtualKeyCode::Down) {
-            self.mandel_y_center += self.mandel_y_range / 50.0;
-            println!("x: {}, y: {}", self.mandel_x_center, self.mandel_y_center);
-        }
-
-        if user_inputs.key_pressed_os(VirtualKeyCode::Left) {
-            self.mandel_x_center -= self.mandel_x_range / 50.0;
-            println!("x: {}, y: {}", self.mandel_x_center, self.mandel_y_center);
-        }
-
-        if user_inputs.key_pressed_os(VirtualKeyCode::Right) {
-            self.mandel_x_center += self.mandel_x_range / 50.0;
+        // The joystick of player 1 moves the view, diagonals included
+        if joystick.get_x() != 0 || joystick.get_y() != 0 {
+            self.mandel_x_center +=
+                joystick.get_x() as f64 * self.mandel_x_range * JOYSTICK_MOVE_RATIO;
+            self.mandel_y_center +=
+                joystick.get_y() as f64 * self.mandel_y_range * JOYSTICK_MOVE_RATIO;
             println!("x: {}, y: {}", self.mandel_x_center, self.mandel_y_center);
         }
 
@@ -266,11 +259,19 @@ impl FantasyCpcApp for Mandelbrot {
         let mut iteration: usize;
         let mut random = rand::thread_rng();
 
+        // The picture is drawn in a viewport inside the overscan, but computed in frame
+        // coordinates so that it stays in place when the overscan changes
+        let (area_x, area_y, area_width, area_height) =
+            display_controller.get_bezel().get_inner_area();
+        display_controller.push_viewport(area_x as isize, area_y as isize, area_width, area_height);
+
         // Mandelbrot algorithm from Wikipedia : https://en.wikipedia.org/wiki/Plotting_algorithms_for_the_Mandelbrot_set
-        for py in OVERSCAN_V..VIRTUAL_HEIGHT - OVERSCAN_V {
-            for px in OVERSCAN_H..VIRTUAL_WIDTH - OVERSCAN_H {
-                x0 = ((px as f64 * self.mandel_x_range) / VIRTUAL_WIDTH as f64) + mandel_x_min;
-                y0 = ((py as f64 * self.mandel_y_range) / VIRTUAL_HEIGHT as f64) + mandel_y_min;
+        for py in 0..area_height {
+            for px in 0..area_width {
+                x0 = (((area_x + px) as f64 * self.mandel_x_range) / VIRTUAL_WIDTH as f64)
+                    + mandel_x_min;
+                y0 = (((area_y + py) as f64 * self.mandel_y_range) / VIRTUAL_HEIGHT as f64)
+                    + mandel_y_min;
                 x2 = 0.0;
                 y2 = 0.0;
 
@@ -318,6 +319,7 @@ impl FantasyCpcApp for Mandelbrot {
                 display_controller.set_pixel(px as isize, py as isize, color);
             }
         }
+        display_controller.pop_viewport();
 
         // Increasing the amount of details the deeper we get, to keep the screen filled
         let empty_ratio: f64 = max_iteration_count as f64 / (VIRTUAL_WIDTH * VIRTUAL_HEIGHT) as f64;
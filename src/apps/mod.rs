@@ -1,7 +1,12 @@
+pub mod basic;
 pub mod empty_example;
 pub mod cli;
 pub mod life;
 pub mod weather_app;
 pub mod mandelbrot;
 pub mod boot;
-pub mod raycaster;
\ No newline at end of file
+pub mod raycaster;
+pub mod script;
+pub mod telnet;
+#[cfg(unix)]
+pub mod pty;
\ No newline at end of file
@@ -0,0 +1,130 @@
+use fantasy_cpc_app::{
+    input::InputState, AppResponse, AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams,
+    SystemCommand,
+};
+use fantasy_cpc_clock::Clock;
+use fantasy_cpc_display_controller::{
+    config::{TEXT_COLUMNS, TEXT_ROWS},
+    DisplayController,
+};
+
+use super::session::PtySession;
+use crate::apps::cli::{keyboard::get_typed_bytes, terminal::Terminal};
+
+/// Program started when none is given
+const DEFAULT_PROGRAM: &str = "/bin/sh";
+
+/// Runs a program of the host computer, like bash or vim, in a full screen terminal.
+/// "run pty" starts the user's shell, "run pty /usr/bin/htop" starts that program.
+/// The program sees a 40x30 VT100, ESC is sent to it, the app stops when the program
+/// ends or is killed from the shell, F12 switches back to the shell without stopping it.
+pub struct PtyApp {
+    app_params: FantasyCppAppDefaultParams,
+    terminal: Terminal,
+    program: Option<String>,
+    session: Option<PtySession>,
+    error: Option<String>,
+}
+
+impl PtyApp {
+    pub fn new() -> PtyApp {
+        let mut terminal = Terminal::new();
+        terminal.smooth_scroll = false;
+        terminal.set_tty_mode(true);
+
+        PtyApp {
+            app_params: FantasyCppAppDefaultParams::new(String::from("pty"), false),
+            terminal,
+            program: None,
+            session: None,
+            error: None,
+        }
+    }
+
+    fn get_program(&self) -> String {
+        self.program
+            .clone()
+            .or_else(|| std::env::var("SHELL").ok())
+            .unwrap_or_else(|| DEFAULT_PROGRAM.to_string())
+    }
+
+    /// Shows what the program wrote, sends it what was typed, and checks if it's still running.
+    fn exchange(&mut self, inputs: Option<&InputState>) -> Result<Option<String>, String> {
+        let Some(session) = self.session.as_mut() else {
+            return Ok(None);
+        };
+
+        let output = session.read().map_err(|error| error.to_string())?;
+        self.terminal.push_bytes(&output);
+
+        if let Some(status) = session.try_wait().map_err(|error| error.to_string())? {
+            return Ok(Some(format!("'{}' {}", self.get_program(), status)));
+        }
+
+        if let Some(inputs) = inputs {
+            let bytes = get_typed_bytes(inputs);
+            if !bytes.is_empty() {
+                session.write(&bytes).map_err(|error| error.to_string())?;
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl FantasyCpcApp for PtyApp {
+    fn get_app_params(&mut self) -> &mut FantasyCppAppDefaultParams {
+        &mut self.app_params
+    }
+
+    fn init_app(&mut self, _system_clock: &Clock, display_controller: &mut DisplayController) {
+        display_controller.clear_text_layer();
+        self.terminal.clear();
+        self.error = None;
+
+        // Starting again ends the program of the previous run, if it's still there
+        self.session = None;
+        let program = self.get_program();
+        match PtySession::spawn(&program, TEXT_COLUMNS, TEXT_ROWS) {
+            Ok(session) => self.session = Some(session),
+            Err(error) => self.error = Some(format!("{}: {}", program, error)),
+        }
+    }
+
+    fn update_app(&mut self, inputs: Option<&InputState>, _clock: &Clock) -> Option<AppResponse> {
+        if *self.app_params.get_status() == AppStatus::Stopped {
+            return None;
+        }
+
+        let message = match self.exchange(inputs) {
+            Ok(message) => message,
+            Err(error) => Some(error),
+        };
+
+        let message = self.error.take().or(message)?;
+        self.session = None;
+        self.program = None;
+        self.app_params.change_status(AppStatus::Stopped);
+
+        let mut response = AppResponse::new();
+        response.push_command(SystemCommand::PrintToTerminal(message));
+        Some(response)
+    }
+
+    fn draw_app(&mut self, _clock: &Clock, display_controller: &mut DisplayController) {
+        if *self.app_params.get_status() == AppStatus::Stopped {
+            display_controller.clear_text_layer();
+            return;
+        }
+
+        self.terminal.render(display_controller, true);
+    }
+
+    fn stop_app(&mut self) {
+        self.session = None;
+    }
+
+    fn open_file(&mut self, path: &str) {
+        self.program = Some(path.to_string());
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        unix::process::CommandExt,
+    },
+    process::{Child, Command, ExitStatus, Stdio},
+    ptr,
+};
+
+/// Bytes read from the program at most in one call to read(), so that a program
+/// writing without pause doesn't freeze the machine
+const MAX_READ_SIZE: usize = 16 * 1024;
+
+/// Terminal type given to the program, the escape sequences of a VT100 are the ones
+/// the Terminal understands best
+const TERM: &str = "vt100";
+
+/// A host program running on a pseudo-terminal.
+/// What the program writes is read with read(), keyboard input is sent with write().
+/// The program is killed when the session is dropped.
+pub struct PtySession {
+    master: File,
+    child: Child,
+}
+
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    match result {
+        -1 => Err(io::Error::last_os_error()),
+        result => Ok(result),
+    }
+}
+
+fn set_flags(fd: RawFd, get: libc::c_int, set: libc::c_int, flags: libc::c_int) -> io::Result<()> {
+    unsafe {
+        let current = check(libc::fcntl(fd, get))?;
+        check(libc::fcntl(fd, set, current | flags))?;
+    }
+    Ok(())
+}
+
+impl PtySession {
+    /// Starts the program on a new pseudo-terminal of that many columns and rows.
+    pub fn spawn(program: &str, columns: usize, rows: usize) -> io::Result<PtySession> {
+        let mut master: libc::c_int = 0;
+        let mut slave: libc::c_int = 0;
+        let mut size = libc::winsize {
+            ws_row: rows as u16,
+            ws_col: columns as u16,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+
+        // The file descriptors are owned right away to be closed on every error
+        let (master, slave) = unsafe {
+            check(libc::openpty(
+                &mut master,
+                &mut slave,
+                ptr::null_mut(),
+                ptr::null_mut::<libc::termios>(),
+                ptr::addr_of_mut!(size),
+            ))?;
+            (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave))
+        };
+
+        // The program only gets the slave side, as its stdin, stdout and stderr
+        set_flags(
+            master.as_raw_fd(),
+            libc::F_GETFD,
+            libc::F_SETFD,
+            libc::FD_CLOEXEC,
+        )?;
+        set_flags(
+            slave.as_raw_fd(),
+            libc::F_GETFD,
+            libc::F_SETFD,
+            libc::FD_CLOEXEC,
+        )?;
+        set_flags(
+            master.as_raw_fd(),
+            libc::F_GETFL,
+            libc::F_SETFL,
+            libc::O_NONBLOCK,
+        )?;
+
+        let mut command = Command::new(program);
+        command
+            .env("TERM", TERM)
+            .env("COLUMNS", columns.to_string())
+            .env("LINES", rows.to_string())
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+
+        // The program leads its own session with the pseudo-terminal as controlling
+        // terminal, so that Ctrl+C and job control reach it
+        unsafe {
+            command.pre_exec(|| {
+                check(libc::setsid())?;
+                check(libc::ioctl(0, libc::TIOCSCTTY as _, 0))?;
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+
+        Ok(PtySession {
+            master: File::from(master),
+            child,
+        })
+    }
+
+    /// Bytes written by the program since the last call, empty if there are none.
+    pub fn read(&mut self) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; MAX_READ_SIZE];
+        let count = match self.master.read(&mut buffer) {
+            Ok(count) => count,
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => 0,
+            // Linux reports EIO once the program has closed the terminal
+            Err(error) if error.raw_os_error() == Some(libc::EIO) => 0,
+            Err(error) => return Err(error),
+        };
+        buffer.truncate(count);
+        Ok(buffer)
+    }
+
+    /// Sends keyboard input to the program.
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.master.write_all(bytes)
+    }
+
+    /// Returns the exit status once the program has ended.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
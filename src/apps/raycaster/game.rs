@@ -5,7 +5,18 @@ use super::{
     player::Player,
     renderer::{Renderer, GAME_SCALE},
 };
-use fantasy_cpc_app::{AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams};
+use fantasy_cpc_app::{
+    input::{InputState, Key},
+    joystick::JoystickButton,
+    mouse::MouseMode,
+    ui::{
+        form::Form,
+        message_box::MessageBox,
+        widget::{Response, Theme, Widget},
+        widgets::{Button, Label, Slider},
+    },
+    AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams,
+};
 use fantasy_cpc_clock::Clock;
 use fantasy_cpc_display_controller::{
     color_palettes::{BLACK, YELLOW},
@@ -13,20 +24,26 @@ use fantasy_cpc_display_controller::{
 };
 use fast_math::atan2;
 use std::f32::consts::PI;
-use winit::event::VirtualKeyCode;
-use winit_input_helper::WinitInputHelper;
 
 const PLAYER_SPEED: isize = 8;
 
+/// Radians turned per unit of mouse movement
+const MOUSE_SENSITIVITY: f32 = 0.003;
+
+/// Columns taken by the names of the settings in the pause menu
+const MENU_LABEL_WIDTH: usize = 16;
+
 pub struct Raycaster {
     app_params: FantasyCppAppDefaultParams,
     map: Map,
     renderer: Renderer,
     player: Player,
     monster: Monster,
-    show_menu: bool,
+    /// Shown when the game is paused with ESC
+    menu: Option<Form>,
+    /// Asks before leaving the game from the pause menu
+    quit_box: Option<MessageBox>,
     draw_minimap: bool,
-    menu_item_selected: usize,
 }
 
 impl Raycaster {
@@ -40,49 +57,54 @@ impl Raycaster {
                 direction: 0.0,
             },
             monster: Monster::new(),
-            show_menu: false,
+            menu: None,
+            quit_box: None,
             draw_minimap: false,
             renderer: Renderer::new(),
-            menu_item_selected: 0,
         }
     }
 
     //***************************************************************************************************************** */
     //                                                    GAME
     //***************************************************************************************************************** */
-    pub fn update_game(&mut self, inputs: Option<&WinitInputHelper>, _clock: &Clock) {
+    pub fn update_game(&mut self, inputs: Option<&InputState>, _clock: &Clock) {
         if inputs.is_none() {
             return;
         }
 
         let inputs = inputs.unwrap();
 
-        if inputs.key_pressed(VirtualKeyCode::Escape) {
-            self.show_menu = true;
+        if inputs.key_pressed(Key::Escape) {
+            self.menu = Some(Raycaster::build_menu(&self.renderer));
             return;
         }
 
-        if inputs.key_pressed(VirtualKeyCode::M) {
+        if inputs.key_pressed(Key::M) {
             self.draw_minimap = !self.draw_minimap;
         }
 
-        if inputs.key_held(VirtualKeyCode::Left) {
-            self.player.direction -= 0.05;
+        // The joystick of player 1 turns and moves, the keys are set in the shell with "joy"
+        let joystick = inputs.get_joystick(0);
 
-            if self.player.direction < -PI {
-                self.player.direction += 2.0 * PI;
-            }
+        if joystick.is_held(JoystickButton::Left) {
+            self.player.direction -= 0.05;
         }
 
-        if inputs.key_held(VirtualKeyCode::Right) {
+        if joystick.is_held(JoystickButton::Right) {
             self.player.direction += 0.05;
+        }
 
-            if self.player.direction > PI {
-                self.player.direction -= 2.0 * PI;
-            }
+        // The mouse is captured while playing, moving it sideways turns
+        self.player.direction += inputs.get_mouse().delta.0 * MOUSE_SENSITIVITY;
+
+        if self.player.direction < -PI {
+            self.player.direction += 2.0 * PI;
+        }
+        if self.player.direction > PI {
+            self.player.direction -= 2.0 * PI;
         }
 
-        if inputs.key_held(VirtualKeyCode::Up) {
+        if joystick.is_held(JoystickButton::Up) {
             let move_to = cast_ray(
                 self.player.x,
                 self.player.y,
@@ -101,7 +123,7 @@ impl Raycaster {
             }
         }
 
-        if inputs.key_held(VirtualKeyCode::Down) {
+        if joystick.is_held(JoystickButton::Down) {
             let move_to = cast_ray(
                 self.player.x,
                 self.player.y,
@@ -131,133 +153,97 @@ impl Raycaster {
     //***************************************************************************************************************** */
     //                                                    MENU
     //***************************************************************************************************************** */
-    pub fn update_menu(&mut self, inputs: Option<&WinitInputHelper>, _clock: &Clock) {
-        if inputs.is_none() {
-            return;
-        }
-
-        let inputs = inputs.unwrap();
+    /// The pause menu, its sliders start at the current settings of the renderer.
+    fn build_menu(renderer: &Renderer) -> Form {
+        let mut menu = Form::new(Theme::new(YELLOW, BLACK, YELLOW));
+        menu.add("title", Label::new(2, 8, "PAUSE"));
+
+        let mut fov = Slider::new(2, 10, "Fov", 0.5, 3.0, 0.1);
+        fov.set_label_width(MENU_LABEL_WIDTH);
+        fov.set_decimals(1);
+        fov.set_value(renderer.fov);
+        menu.add("fov", fov);
+
+        let mut wall_height = Slider::new(2, 11, "Wall height", 10.0, 1000.0, 10.0);
+        wall_height.set_label_width(MENU_LABEL_WIDTH);
+        wall_height.set_value(renderer.wall_height as f32);
+        menu.add("wall height", wall_height);
+
+        let mut render_distance = Slider::new(2, 12, "Render distance", 100.0, 5000.0, 10.0);
+        render_distance.set_label_width(MENU_LABEL_WIDTH);
+        render_distance.set_value(renderer.render_distance as f32);
+        menu.add("render distance", render_distance);
+
+        menu.add("resume", Button::new(2, 14, "Resume"));
+        menu.add("quit", Button::new(2, 15, "Quit game"));
+        menu
+    }
 
-        if inputs.key_pressed(VirtualKeyCode::Escape) {
-            self.show_menu = false;
+    pub fn update_menu(&mut self, inputs: Option<&InputState>, _clock: &Clock) {
+        let Some(inputs) = inputs else {
             return;
-        }
-
-        if inputs.key_pressed(VirtualKeyCode::Return) {
-            if self.menu_item_selected == 4 {
-                self.get_app_params().change_status(AppStatus::Stopped);
-            } else {
-                self.show_menu = false;
+        };
+
+        if let Some(quit_box) = self.quit_box.as_mut() {
+            if inputs.key_pressed(Key::Escape) {
+                self.quit_box = None;
+            } else if quit_box.handle_input(inputs) == Response::Activated {
+                if quit_box.get_selected() == 0 {
+                    self.menu = None;
+                    self.get_app_params().change_status(AppStatus::Stopped);
+                }
+                self.quit_box = None;
             }
             return;
         }
 
-        if inputs.key_pressed_os(VirtualKeyCode::Left) {
-            match self.menu_item_selected {
-                0 => {
-                    self.renderer.fov -= 0.1;
-                    self.renderer.distortion_compensation();
-                }
-                1 => self.renderer.wall_height -= 10,
-                2 => self.renderer.render_distance -= 10,
-                _ => (),
-            }
+        if inputs.key_pressed(Key::Escape) {
+            self.menu = None;
+            return;
         }
 
-        if inputs.key_pressed_os(VirtualKeyCode::Right) {
-            match self.menu_item_selected {
-                0 => {
-                    self.renderer.fov += 0.1;
-                    self.renderer.distortion_compensation();
-                }
-                1 => self.renderer.wall_height += 10,
-                2 => self.renderer.render_distance += 10,
-                _ => (),
+        let Some(menu) = self.menu.as_mut() else {
+            return;
+        };
+        let Some(event) = menu.update(inputs) else {
+            return;
+        };
+
+        let value = |id| {
+            menu.get::<Slider>(id)
+                .map_or(0.0, |slider| slider.get_value())
+        };
+        match (event.id, event.response) {
+            ("fov", Response::Changed) => {
+                self.renderer.fov = value("fov");
+                self.renderer.distortion_compensation();
             }
-        }
-
-        if inputs.key_pressed_os(VirtualKeyCode::Up) && self.menu_item_selected > 0 {
-            self.menu_item_selected -= 1
-        }
-
-        if inputs.key_pressed_os(VirtualKeyCode::Down) && self.menu_item_selected < 4 {
-            self.menu_item_selected += 1
+            ("wall height", Response::Changed) => {
+                self.renderer.wall_height = value("wall height") as isize
+            }
+            ("render distance", Response::Changed) => {
+                self.renderer.render_distance = value("render distance") as isize
+            }
+            ("resume", Response::Activated) => self.menu = None,
+            ("quit", Response::Activated) => {
+                self.quit_box = Some(MessageBox::new(
+                    "Quit",
+                    "Leave the game and go back to the shell ?",
+                    &["Yes", "No"],
+                ));
+            }
+            _ => (),
         }
     }
 
     pub fn draw_menu(&mut self, _clock: &Clock, dc: &mut DisplayController) {
-        dc.get_text_layer_mut().clear();
-        dc.get_text_layer_mut().insert_string_xy(
-            2,
-            10,
-            "fov: ",
-            Some(YELLOW),
-            Some(BLACK),
-            false,
-            self.menu_item_selected == 0,
-            false,
-        );
-        dc.get_text_layer_mut().insert_string_xy(
-            2,
-            11,
-            "Wall height: ",
-            Some(YELLOW),
-            Some(BLACK),
-            false,
-            self.menu_item_selected == 1,
-            false,
-        );
-        dc.get_text_layer_mut().insert_string_xy(
-            2,
-            12,
-            "Render distance: ",
-            Some(YELLOW),
-            Some(BLACK),
-            false,
-            self.menu_item_selected == 2,
-            false,
-        );
-        dc.get_text_layer_mut().insert_string_xy(
-            2,
-            15,
-            "Quit game",
-            Some(YELLOW),
-            Some(BLACK),
-            false,
-            self.menu_item_selected == 4,
-            false,
-        );
+        if let Some(menu) = &self.menu {
+            menu.draw(dc.get_text_layer_mut());
 
-        dc.get_text_layer_mut().insert_string_xy(
-            7,
-            10,
-            &self.renderer.fov.to_string(),
-            Some(YELLOW),
-            Some(BLACK),
-            false,
-            self.menu_item_selected == 0,
-            false,
-        );
-        dc.get_text_layer_mut().insert_string_xy(
-            15,
-            11,
-            &self.renderer.wall_height.to_string(),
-            Some(YELLOW),
-            Some(BLACK),
-            false,
-            self.menu_item_selected == 1,
-            false,
-        );
-        dc.get_text_layer_mut().insert_string_xy(
-            19,
-            12,
-            &self.renderer.render_distance.to_string(),
-            Some(YELLOW),
-            Some(BLACK),
-            false,
-            self.menu_item_selected == 2,
-            false,
-        );
+            if let Some(quit_box) = &self.quit_box {
+                quit_box.draw(dc.get_text_layer_mut(), menu.get_theme(), true);
+            }
+        }
     }
 }
 
@@ -273,7 +259,8 @@ impl FantasyCpcApp for Raycaster {
     ) {
         display_controller.get_text_layer_mut().clear();
         self.map.walls.clear();
-        self.show_menu = false;
+        self.menu = None;
+        self.quit_box = None;
         self.draw_minimap = false;
         self.map.transform_map_into_list_of_walls();
         self.renderer.distortion_compensation();
@@ -286,15 +273,22 @@ impl FantasyCpcApp for Raycaster {
 
     fn update_app(
         &mut self,
-        inputs: Option<&winit_input_helper::WinitInputHelper>,
+        inputs: Option<&InputState>,
         clock: &fantasy_cpc_clock::Clock,
     ) -> Option<fantasy_cpc_app::AppResponse> {
-        if self.show_menu {
+        if self.menu.is_some() {
             self.update_menu(inputs, clock);
         } else {
             self.update_game(inputs, clock);
         }
 
+        // The pointer comes back in the pause menu
+        let mouse_mode = match self.menu {
+            Some(_) => MouseMode::Pointer,
+            None => MouseMode::Relative,
+        };
+        self.app_params.set_mouse_mode(mouse_mode);
+
         None
     }
 
@@ -305,7 +299,7 @@ impl FantasyCpcApp for Raycaster {
     ) {
         // Menu
         display_controller.get_text_layer_mut().clear();
-        if self.show_menu {
+        if self.menu.is_some() {
             self.draw_menu(clock, display_controller);
         }
 
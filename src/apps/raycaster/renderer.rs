@@ -2,7 +2,7 @@ use std::f32::consts::PI;
 
 use fantasy_cpc_display_controller::{
     color_palettes::*,
-    config::{OVERSCAN_H, OVERSCAN_V, VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
+    config::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
     DisplayController,
 };
 
@@ -290,6 +290,10 @@ impl Renderer {
         player: &Player,
         monster: &Monster,
     ) {
+        // The minimap is drawn inside the overscan
+        let (x, y, width, height) = dc.get_bezel().get_inner_area();
+        dc.push_viewport(x as isize, y as isize, width, height);
+
         // Draw player and view cone
         let player_coord = convert_map_coord_to_minimap_coord(player.x, player.y);
         dc.circle(player_coord.0, player_coord.1, 2, GREEN, Some(GREEN));
@@ -315,13 +319,15 @@ impl Renderer {
         //Draw  mini map
         for wall in map.walls.chunks_exact(1) {
             if wall[0].texture == 1 {
-                let x1 = wall[0].x1 / GAME_SCALE * MINIMAP_SCALE + OVERSCAN_H as isize;
-                let y1 = wall[0].y1 / GAME_SCALE * MINIMAP_SCALE + OVERSCAN_V as isize;
-                let x2 = wall[0].x2 / GAME_SCALE * MINIMAP_SCALE + OVERSCAN_H as isize;
-                let y2 = wall[0].y2 / GAME_SCALE * MINIMAP_SCALE + OVERSCAN_V as isize;
+                let x1 = wall[0].x1 / GAME_SCALE * MINIMAP_SCALE;
+                let y1 = wall[0].y1 / GAME_SCALE * MINIMAP_SCALE;
+                let x2 = wall[0].x2 / GAME_SCALE * MINIMAP_SCALE;
+                let y2 = wall[0].y2 / GAME_SCALE * MINIMAP_SCALE;
                 dc.line(x1, y1, x2, y2, WHITE);
             }
         }
+
+        dc.pop_viewport();
     }
 
     pub fn render(
@@ -468,8 +474,8 @@ impl Renderer {
 
 fn convert_map_coord_to_minimap_coord(x: isize, y: isize) -> (isize, isize) {
     (
-        (x as f32 / GAME_SCALE as f32 * MINIMAP_SCALE as f32) as isize + OVERSCAN_H as isize,
-        (y as f32 / GAME_SCALE as f32 * MINIMAP_SCALE as f32) as isize + OVERSCAN_V as isize,
+        (x as f32 / GAME_SCALE as f32 * MINIMAP_SCALE as f32) as isize,
+        (y as f32 / GAME_SCALE as f32 * MINIMAP_SCALE as f32) as isize,
     )
 }
 
@@ -0,0 +1,536 @@
+use std::{cell::RefCell, rc::Rc};
+
+use fantasy_cpc_app::{
+    input::{InputState, Key, TextChar},
+    joystick::{Joystick, JoystickButton, JOYSTICK_COUNT},
+};
+use fantasy_cpc_clock::Clock;
+use fantasy_cpc_display_controller::{
+    color_palettes::PALETE_SIZE,
+    config::{TEXT_COLUMNS, TEXT_ROWS, VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
+    sprite::{Sprite, SpriteSize},
+    DisplayController,
+};
+use rand::Rng;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FLOAT, INT};
+
+use crate::sound::play;
+
+/// Highest frequency of sound(), in Hz, beyond what can be heard
+const MAX_SOUND_FREQUENCY: FLOAT = 20000.0;
+
+/// Longest sound(), in seconds
+const MAX_SOUND_DURATION: FLOAT = 10.0;
+
+/// Keys that scripts can test, by their name ("Left", "Space", "A"...).
+/// Digits can also be named "0" to "9".
+const KEYS: &[Key] = &[
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::Left,
+    Key::Right,
+    Key::Up,
+    Key::Down,
+    Key::Space,
+    Key::Return,
+    Key::Back,
+    Key::Tab,
+    Key::LShift,
+    Key::RShift,
+    Key::LControl,
+    Key::RControl,
+    Key::LAlt,
+    Key::RAlt,
+];
+
+/// Drawing asked by the script, done on the display controller when the app is drawn
+enum DrawCommand {
+    Clear(usize),
+    Pixel(isize, isize, usize),
+    Line(isize, isize, isize, isize, usize),
+    Rect(isize, isize, isize, isize, usize, Option<usize>),
+    Circle(isize, isize, usize, usize, Option<usize>),
+    Text(usize, usize, String, usize, Option<usize>),
+    ClearText,
+}
+
+/// A sprite created by the script, put on the sprite layer every frame
+struct ScriptSprite {
+    id: String,
+    size: usize,
+    image: Vec<usize>,
+    x: isize,
+    y: isize,
+    visible: bool,
+}
+
+/// What the script functions and the script host share: the inputs of the frame,
+/// the drawing to do, the sprites and the messages for the shell.
+pub struct ScriptContext {
+    draw_commands: Vec<DrawCommand>,
+    sprites: Vec<ScriptSprite>,
+    keys_held: Vec<Key>,
+    keys_pressed: Vec<Key>,
+    joysticks: [Joystick; JOYSTICK_COUNT],
+    typed_text: String,
+    frame: INT,
+    time: FLOAT,
+    messages: Vec<String>,
+    quit: bool,
+}
+
+pub type ScriptContextHandle = Rc<RefCell<ScriptContext>>;
+
+impl ScriptContext {
+    pub fn new() -> ScriptContext {
+        ScriptContext {
+            draw_commands: Vec::new(),
+            sprites: Vec::new(),
+            keys_held: Vec::new(),
+            keys_pressed: Vec::new(),
+            joysticks: [Joystick::default(); JOYSTICK_COUNT],
+            typed_text: String::new(),
+            frame: 0,
+            time: 0.0,
+            messages: Vec::new(),
+            quit: false,
+        }
+    }
+
+    pub fn into_handle(self) -> ScriptContextHandle {
+        Rc::new(RefCell::new(self))
+    }
+
+    /// Forgets everything the previous script did.
+    pub fn reset(&mut self) {
+        *self = ScriptContext::new();
+    }
+
+    /// Takes the inputs of the frame, None in the background where scripts see no keys.
+    /// Drawing left from a frame that wasn't drawn is dropped.
+    pub fn start_frame(&mut self, inputs: Option<&InputState>, clock: &Clock) {
+        self.draw_commands.clear();
+        self.frame += 1;
+        self.time = clock.total_running_time.as_secs_f64();
+        self.keys_held.clear();
+        self.keys_pressed.clear();
+        self.joysticks = [Joystick::default(); JOYSTICK_COUNT];
+        self.typed_text.clear();
+
+        if let Some(inputs) = inputs {
+            for key in KEYS {
+                if inputs.key_held(*key) {
+                    self.keys_held.push(*key);
+                }
+                if inputs.key_pressed(*key) {
+                    self.keys_pressed.push(*key);
+                }
+            }
+            for (player, joystick) in self.joysticks.iter_mut().enumerate() {
+                *joystick = *inputs.get_joystick(player);
+            }
+            for text_char in inputs.text() {
+                if let TextChar::Char(c) = text_char {
+                    if !c.is_control() {
+                        self.typed_text.push(c);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Messages printed by the script since the last call.
+    pub fn take_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+    }
+
+    pub fn get_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Does the drawing asked since the start of the frame and puts the visible sprites
+    /// on the sprite layer.
+    pub fn draw(&mut self, display_controller: &mut DisplayController) {
+        for command in self.draw_commands.drain(..) {
+            match command {
+                DrawCommand::Clear(color) => display_controller.clear(color),
+                DrawCommand::Pixel(x, y, color) => display_controller.set_pixel(x, y, color),
+                DrawCommand::Line(x1, y1, x2, y2, color) => {
+                    display_controller.line(x1, y1, x2, y2, color)
+                }
+                DrawCommand::Rect(x, y, width, height, color, fill) => {
+                    display_controller.square(x, y, width, height, color, fill)
+                }
+                DrawCommand::Circle(x, y, radius, color, fill) => {
+                    display_controller.circle(x, y, radius, color, fill)
+                }
+                DrawCommand::Text(x, y, text, color, bkg_color) => display_controller
+                    .get_text_layer_mut()
+                    .insert_string_xy(x, y, &text, Some(color), bkg_color, false, false, false),
+                DrawCommand::ClearText => display_controller.clear_text_layer(),
+            }
+        }
+
+        let sprites = display_controller.get_sprites_mut();
+        for sprite in self.sprites.iter().filter(|sprite| sprite.visible) {
+            let mut layer_sprite = Sprite::new(sprite.id.clone());
+            layer_sprite.size = match sprite.size {
+                8 => SpriteSize::_8x8,
+                16 => SpriteSize::_16x16,
+                _ => SpriteSize::_32x32,
+            };
+            layer_sprite.image = sprite.image.clone();
+            layer_sprite.pos_x = sprite.x;
+            layer_sprite.pos_y = sprite.y;
+            sprites.push(layer_sprite);
+        }
+    }
+
+    fn push(&mut self, command: DrawCommand) {
+        self.draw_commands.push(command);
+    }
+
+    fn find_sprite(&mut self, id: &str) -> Result<&mut ScriptSprite, Box<EvalAltResult>> {
+        self.sprites
+            .iter_mut()
+            .find(|sprite| sprite.id == id)
+            .ok_or_else(|| format!("No sprite named '{}'", id).into())
+    }
+}
+
+/// Colors wrap around the palette, like pens on the CPC
+fn to_color(color: INT) -> usize {
+    color.rem_euclid(PALETE_SIZE as INT) as usize
+}
+
+fn to_float(value: Dynamic) -> Result<FLOAT, Box<EvalAltResult>> {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|value| value as FLOAT))
+        .map_err(|type_name| format!("Number expected, got {}", type_name).into())
+}
+
+fn find_key(name: &str) -> Result<Key, Box<EvalAltResult>> {
+    Key::from_name(name)
+        .filter(|key| KEYS.contains(key))
+        .ok_or_else(|| format!("Unknown key '{}'", name).into())
+}
+
+/// The joystick of player 1 or 2, and one of its buttons: "up", "down", "left", "right",
+/// "fire1" or "fire2".
+fn find_joystick_button(
+    context: &ScriptContext,
+    player: INT,
+    name: &str,
+) -> Result<(Joystick, JoystickButton), Box<EvalAltResult>> {
+    let joystick = match player {
+        1..=2 => context.joysticks[player as usize - 1],
+        _ => return Err(format!("Unknown joystick {}", player).into()),
+    };
+    let button = JoystickButton::from_name(name)
+        .ok_or_else(|| format!("Unknown joystick button '{}'", name))?;
+    Ok((joystick, button))
+}
+
+/// Makes the machine available to scripts: screen, text, shapes, sprites, keyboard and sound.
+/// Script output from print() goes to the shell.
+pub fn register_api(engine: &mut Engine, context: &ScriptContextHandle) {
+    let output = context.clone();
+    engine.on_print(move |text| output.borrow_mut().messages.push(text.to_string()));
+
+    // Screen, coordinates are pixels of the frame, (0, 0) being the top left corner
+    engine.register_fn("width", || VIRTUAL_WIDTH as INT);
+    engine.register_fn("height", || VIRTUAL_HEIGHT as INT);
+
+    let c = context.clone();
+    engine.register_fn("cls", move |color: INT| {
+        c.borrow_mut().push(DrawCommand::Clear(to_color(color)))
+    });
+
+    let c = context.clone();
+    engine.register_fn("pixel", move |x: INT, y: INT, color: INT| {
+        c.borrow_mut()
+            .push(DrawCommand::Pixel(x as isize, y as isize, to_color(color)))
+    });
+
+    let c = context.clone();
+    engine.register_fn(
+        "line",
+        move |x1: INT, y1: INT, x2: INT, y2: INT, color: INT| {
+            c.borrow_mut().push(DrawCommand::Line(
+                x1 as isize,
+                y1 as isize,
+                x2 as isize,
+                y2 as isize,
+                to_color(color),
+            ))
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn(
+        "rect",
+        move |x: INT, y: INT, width: INT, height: INT, color: INT| {
+            c.borrow_mut().push(DrawCommand::Rect(
+                x as isize,
+                y as isize,
+                width as isize,
+                height as isize,
+                to_color(color),
+                None,
+            ))
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn(
+        "fill_rect",
+        move |x: INT, y: INT, width: INT, height: INT, color: INT| {
+            let color = to_color(color);
+            c.borrow_mut().push(DrawCommand::Rect(
+                x as isize,
+                y as isize,
+                width as isize,
+                height as isize,
+                color,
+                Some(color),
+            ))
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn("circle", move |x: INT, y: INT, radius: INT, color: INT| {
+        c.borrow_mut().push(DrawCommand::Circle(
+            x as isize,
+            y as isize,
+            radius.max(0) as usize,
+            to_color(color),
+            None,
+        ))
+    });
+
+    let c = context.clone();
+    engine.register_fn(
+        "fill_circle",
+        move |x: INT, y: INT, radius: INT, color: INT| {
+            let color = to_color(color);
+            c.borrow_mut().push(DrawCommand::Circle(
+                x as isize,
+                y as isize,
+                radius.max(0) as usize,
+                color,
+                Some(color),
+            ))
+        },
+    );
+
+    // Text layer, coordinates are columns and rows. Text stays until clear_text() is called.
+    engine.register_fn("columns", || TEXT_COLUMNS as INT);
+    engine.register_fn("rows", || TEXT_ROWS as INT);
+
+    let c = context.clone();
+    engine.register_fn(
+        "print_at",
+        move |x: INT, y: INT, text: &str, color: INT| {
+            if (0..TEXT_COLUMNS as INT).contains(&x) && (0..TEXT_ROWS as INT).contains(&y) {
+                c.borrow_mut().push(DrawCommand::Text(
+                    x as usize,
+                    y as usize,
+                    text.to_string(),
+                    to_color(color),
+                    None,
+                ))
+            }
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn(
+        "print_at",
+        move |x: INT, y: INT, text: &str, color: INT, bkg_color: INT| {
+            if (0..TEXT_COLUMNS as INT).contains(&x) && (0..TEXT_ROWS as INT).contains(&y) {
+                c.borrow_mut().push(DrawCommand::Text(
+                    x as usize,
+                    y as usize,
+                    text.to_string(),
+                    to_color(color),
+                    Some(to_color(bkg_color)),
+                ))
+            }
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn("clear_text", move || {
+        c.borrow_mut().push(DrawCommand::ClearText)
+    });
+
+    // Sprites, 8, 16 or 32 pixels wide, pen 0 is transparent
+    let c = context.clone();
+    engine.register_fn(
+        "sprite",
+        move |id: &str, size: INT, pixels: Array| -> Result<(), Box<EvalAltResult>> {
+            if ![8, 16, 32].contains(&size) {
+                return Err(format!("Sprite size must be 8, 16 or 32, not {}", size).into());
+            }
+            let size = size as usize;
+            let mut image: Vec<usize> = pixels
+                .iter()
+                .map(|pixel| pixel.as_int().map(to_color).unwrap_or(0))
+                .collect();
+            image.resize(size * size, 0);
+
+            let mut context = c.borrow_mut();
+            context.sprites.retain(|sprite| sprite.id != id);
+            context.sprites.push(ScriptSprite {
+                id: id.to_string(),
+                size,
+                image,
+                x: 0,
+                y: 0,
+                visible: true,
+            });
+            Ok(())
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn(
+        "move_sprite",
+        move |id: &str, x: INT, y: INT| -> Result<(), Box<EvalAltResult>> {
+            let mut context = c.borrow_mut();
+            let sprite = context.find_sprite(id)?;
+            sprite.x = x as isize;
+            sprite.y = y as isize;
+            Ok(())
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn(
+        "show_sprite",
+        move |id: &str, visible: bool| -> Result<(), Box<EvalAltResult>> {
+            c.borrow_mut().find_sprite(id)?.visible = visible;
+            Ok(())
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn("remove_sprite", move |id: &str| {
+        c.borrow_mut().sprites.retain(|sprite| sprite.id != id)
+    });
+
+    // Keyboard, only the app in the foreground sees the keys
+    let c = context.clone();
+    engine.register_fn(
+        "key_held",
+        move |name: &str| -> Result<bool, Box<EvalAltResult>> {
+            let key = find_key(name)?;
+            Ok(c.borrow().keys_held.contains(&key))
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn(
+        "key_pressed",
+        move |name: &str| -> Result<bool, Box<EvalAltResult>> {
+            let key = find_key(name)?;
+            Ok(c.borrow().keys_pressed.contains(&key))
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn("inkey", move || c.borrow().typed_text.clone());
+
+    // Joysticks, the keys that move them are set in the shell with "joy"
+    let c = context.clone();
+    engine.register_fn(
+        "joy_held",
+        move |player: INT, name: &str| -> Result<bool, Box<EvalAltResult>> {
+            let (joystick, button) = find_joystick_button(&c.borrow(), player, name)?;
+            Ok(joystick.is_held(button))
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn(
+        "joy_pressed",
+        move |player: INT, name: &str| -> Result<bool, Box<EvalAltResult>> {
+            let (joystick, button) = find_joystick_button(&c.borrow(), player, name)?;
+            Ok(joystick.is_pressed(button))
+        },
+    );
+
+    // Sound, a square wave
+    engine.register_fn(
+        "sound",
+        |frequency: Dynamic, duration: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            let frequency = to_float(frequency)?;
+            let duration = to_float(duration)?;
+            if !(frequency > 0.0 && frequency <= MAX_SOUND_FREQUENCY) {
+                return Err(format!("Sound frequency out of range: {}", frequency).into());
+            }
+            if !(duration > 0.0 && duration <= MAX_SOUND_DURATION) {
+                return Err(format!("Sound duration out of range: {}", duration).into());
+            }
+            play::play(
+                60.0,
+                vec![(Some(frequency as f32), duration as f32)],
+                Vec::new(),
+            );
+            Ok(())
+        },
+    );
+
+    // Misc
+    let c = context.clone();
+    engine.register_fn("frame", move || c.borrow().frame);
+
+    let c = context.clone();
+    engine.register_fn("time", move || c.borrow().time);
+
+    engine.register_fn("rnd", |max: INT| {
+        if max <= 0 {
+            0
+        } else {
+            rand::thread_rng().gen_range(0..max)
+        }
+    });
+
+    let c = context.clone();
+    engine.register_fn("quit", move || c.borrow_mut().quit = true);
+}
@@ -0,0 +1,183 @@
+use fantasy_cpc_app::{
+    input::InputState, AppResponse, AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams,
+    SystemCommand,
+};
+use fantasy_cpc_clock::Clock;
+use fantasy_cpc_display_controller::DisplayController;
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+
+use super::api::{register_api, ScriptContext, ScriptContextHandle};
+
+/// Operations a script can do in one call before it's stopped, so that an endless loop
+/// doesn't freeze the machine
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// A loaded script, with its global variables and the state shared by its callbacks
+struct Cartridge {
+    ast: AST,
+    scope: Scope<'static>,
+    state: Dynamic,
+}
+
+/// Runs Rhai scripts from the disc, started with "run game.rhai" in the shell.
+/// The script's top level code runs once when it's loaded, then the host calls
+/// its init(), update() and draw() functions if they are defined.
+/// Those functions see the same object as "this", to keep the state of the game
+/// between frames, ex: this.x += 1;
+/// ESC stops the script.
+pub struct ScriptHost {
+    app_params: FantasyCppAppDefaultParams,
+    engine: Engine,
+    context: ScriptContextHandle,
+    path: Option<String>,
+    cartridge: Option<Cartridge>,
+    error: Option<String>,
+}
+
+impl ScriptHost {
+    pub fn new() -> ScriptHost {
+        let context = ScriptContext::new().into_handle();
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        register_api(&mut engine, &context);
+
+        ScriptHost {
+            app_params: FantasyCppAppDefaultParams::new(String::from("script"), true),
+            engine,
+            context,
+            path: None,
+            cartridge: None,
+            error: None,
+        }
+    }
+
+    /// Reads and compiles the script, and runs its top level code.
+    fn load(&self) -> Result<Cartridge, String> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or("No script to run, use run <file.rhai>")?;
+        let file_system = self.app_params.get_file_system().ok_or("No disc")?;
+        let source = file_system
+            .borrow()
+            .read_to_string(path)
+            .map_err(|error| format!("{}: {}", path, error))?;
+
+        let ast = self
+            .engine
+            .compile(&source)
+            .map_err(|error| format!("{}: {}", path, error))?;
+        let mut scope = Scope::new();
+        self.engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|error| format!("{}: {}", path, error))?;
+
+        Ok(Cartridge {
+            ast,
+            scope,
+            state: Dynamic::from_map(Map::new()),
+        })
+    }
+
+    /// Calls a function of the script if it defines it.
+    fn call(&mut self, name: &str) -> Result<(), String> {
+        let Some(cartridge) = self.cartridge.as_mut() else {
+            return Ok(());
+        };
+
+        let defined = cartridge
+            .ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.is_empty());
+        if !defined {
+            return Ok(());
+        }
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut cartridge.state);
+        self.engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut cartridge.scope,
+                &cartridge.ast,
+                name,
+                (),
+            )
+            .map(|_| ())
+            .map_err(|error| format!("{}(): {}", name, error))
+    }
+}
+
+impl FantasyCpcApp for ScriptHost {
+    fn get_app_params(&mut self) -> &mut FantasyCppAppDefaultParams {
+        &mut self.app_params
+    }
+
+    fn init_app(&mut self, _system_clock: &Clock, display_controller: &mut DisplayController) {
+        display_controller.clear_text_layer();
+        self.context.borrow_mut().reset();
+        self.error = None;
+
+        self.cartridge = match self.load() {
+            Ok(cartridge) => Some(cartridge),
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        };
+
+        if let Err(error) = self.call("init") {
+            self.error = Some(error);
+        }
+    }
+
+    fn update_app(&mut self, inputs: Option<&InputState>, clock: &Clock) -> Option<AppResponse> {
+        // Stopped with ESC
+        if *self.app_params.get_status() == AppStatus::Stopped {
+            return None;
+        }
+
+        if self.error.is_none() {
+            self.context.borrow_mut().start_frame(inputs, clock);
+
+            // draw() is called here so its errors can be reported, its drawing
+            // is done on the display in draw_app()
+            let result = self.call("update").and_then(|_| match inputs {
+                Some(_) => self.call("draw"),
+                None => Ok(()),
+            });
+            if let Err(error) = result {
+                self.error = Some(error);
+            }
+        }
+
+        let mut response = AppResponse::new();
+        for message in self.context.borrow_mut().take_messages() {
+            response.push_command(SystemCommand::PrintToTerminal(message));
+        }
+
+        if let Some(error) = self.error.take() {
+            response.push_command(SystemCommand::PrintToTerminal(error));
+            self.app_params.change_status(AppStatus::Stopped);
+        } else if self.context.borrow().get_quit() {
+            self.app_params.change_status(AppStatus::Stopped);
+        }
+
+        Some(response)
+    }
+
+    fn draw_app(&mut self, _clock: &Clock, display_controller: &mut DisplayController) {
+        if *self.app_params.get_status() == AppStatus::Stopped {
+            self.cartridge = None;
+            display_controller.clear_text_layer();
+            return;
+        }
+
+        self.context.borrow_mut().draw(display_controller);
+    }
+
+    fn open_file(&mut self, path: &str) {
+        self.path = Some(path.to_string());
+    }
+}
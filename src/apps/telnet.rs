@@ -0,0 +1,217 @@
+use fantasy_cpc_app::{
+    input::{InputState, Key},
+    AppResponse, AppStatus, FantasyCpcApp, FantasyCppAppDefaultParams, SystemCommand,
+};
+use fantasy_cpc_clock::Clock;
+use fantasy_cpc_display_controller::DisplayController;
+
+use crate::{
+    apps::cli::{keyboard::get_typed_bytes, terminal::Terminal},
+    network::connection::TcpConnection,
+};
+
+/// Port used when the address doesn't give one
+const TELNET_PORT: u16 = 23;
+
+/// Telnet commands, see RFC 854
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+/// Where the telnet decoder is in the stream received
+enum TelnetState {
+    Data,
+    /// After IAC
+    Command,
+    /// After IAC and WILL, WONT, DO or DONT, waiting for the option
+    Option(u8),
+    /// Between IAC SB and IAC SE
+    SubNegotiation,
+    SubNegotiationCommand,
+}
+
+/// Connects to a BBS or any line based server, "run telnet localhost:2323".
+/// What the server sends is shown in a VT100 terminal, keys are sent as they are typed,
+/// ENTER as CR LF. Telnet options are all refused, the server echoes what's typed.
+/// Ctrl+] disconnects.
+pub struct Telnet {
+    app_params: FantasyCppAppDefaultParams,
+    terminal: Terminal,
+    address: Option<String>,
+    connection: Option<TcpConnection>,
+    state: TelnetState,
+    error: Option<String>,
+}
+
+impl Telnet {
+    pub fn new() -> Telnet {
+        let mut terminal = Terminal::new();
+        terminal.smooth_scroll = false;
+        terminal.set_tty_mode(true);
+
+        Telnet {
+            app_params: FantasyCppAppDefaultParams::new(String::from("telnet"), false),
+            terminal,
+            address: None,
+            connection: None,
+            state: TelnetState::Data,
+            error: None,
+        }
+    }
+
+    /// Removes the telnet commands from the bytes received, and returns the
+    /// answers refusing the options the server asks for.
+    fn decode(&mut self, bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut data = Vec::with_capacity(bytes.len());
+        let mut answers = Vec::new();
+
+        for byte in bytes.iter().copied() {
+            self.state = match (&self.state, byte) {
+                (TelnetState::Data, IAC) => TelnetState::Command,
+                (TelnetState::Data, _) => {
+                    data.push(byte);
+                    TelnetState::Data
+                }
+                (TelnetState::Command, IAC) => {
+                    data.push(IAC);
+                    TelnetState::Data
+                }
+                (TelnetState::Command, WILL..=DONT) => TelnetState::Option(byte),
+                (TelnetState::Command, SB) => TelnetState::SubNegotiation,
+                (TelnetState::Command, _) => TelnetState::Data,
+                (TelnetState::Option(command), option) => {
+                    match *command {
+                        DO => answers.extend_from_slice(&[IAC, WONT, option]),
+                        WILL => answers.extend_from_slice(&[IAC, DONT, option]),
+                        _ => (),
+                    }
+                    TelnetState::Data
+                }
+                (TelnetState::SubNegotiation, IAC) => TelnetState::SubNegotiationCommand,
+                (TelnetState::SubNegotiation, _) => TelnetState::SubNegotiation,
+                (TelnetState::SubNegotiationCommand, SE) => TelnetState::Data,
+                (TelnetState::SubNegotiationCommand, _) => TelnetState::SubNegotiation,
+            };
+        }
+
+        (data, answers)
+    }
+
+    /// Shows what the server sent and sends it what was typed.
+    /// Returns a message once the connection is over.
+    fn exchange(&mut self, inputs: Option<&InputState>) -> Result<Option<String>, String> {
+        let Some(connection) = self.connection.as_mut() else {
+            return Ok(None);
+        };
+
+        let received = connection.read().map_err(|error| error.to_string())?;
+        let closed = connection.is_closed();
+        let (data, answers) = self.decode(&received);
+        self.terminal.push_bytes(&data);
+
+        let Some(connection) = self.connection.as_mut() else {
+            return Ok(None);
+        };
+        if closed {
+            return Ok(Some(String::from("Connection closed by the server")));
+        }
+        if !answers.is_empty() {
+            connection
+                .write(&answers)
+                .map_err(|error| error.to_string())?;
+        }
+
+        if let Some(inputs) = inputs {
+            if inputs.held_control() && inputs.key_pressed_os(Key::RBracket) {
+                return Ok(Some(String::from("Disconnected")));
+            }
+
+            let mut bytes = Vec::new();
+            for byte in get_typed_bytes(inputs) {
+                match byte {
+                    b'\r' => bytes.extend_from_slice(b"\r\n"),
+                    IAC => bytes.extend_from_slice(&[IAC, IAC]),
+                    byte => bytes.push(byte),
+                }
+            }
+            if !bytes.is_empty() {
+                connection
+                    .write(&bytes)
+                    .map_err(|error| error.to_string())?;
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl FantasyCpcApp for Telnet {
+    fn get_app_params(&mut self) -> &mut FantasyCppAppDefaultParams {
+        &mut self.app_params
+    }
+
+    fn init_app(&mut self, _system_clock: &Clock, display_controller: &mut DisplayController) {
+        display_controller.clear_text_layer();
+        self.terminal.clear();
+        self.state = TelnetState::Data;
+        self.error = None;
+        self.connection = None;
+
+        let Some(address) = self.address.clone() else {
+            self.error = Some(String::from("Usage: run telnet <host:port>"));
+            return;
+        };
+        let address = match address.contains(':') {
+            true => address,
+            false => format!("{}:{}", address, TELNET_PORT),
+        };
+
+        self.terminal
+            .push_string(&format!("Connecting to {}...\r\n", address));
+        match TcpConnection::connect(&address) {
+            Ok(connection) => self.connection = Some(connection),
+            Err(error) => self.error = Some(format!("{}: {}", address, error)),
+        }
+    }
+
+    fn update_app(&mut self, inputs: Option<&InputState>, _clock: &Clock) -> Option<AppResponse> {
+        if *self.app_params.get_status() == AppStatus::Stopped {
+            return None;
+        }
+
+        let message = match self.exchange(inputs) {
+            Ok(message) => message,
+            Err(error) => Some(error),
+        };
+
+        let message = self.error.take().or(message)?;
+        self.connection = None;
+        self.address = None;
+        self.app_params.change_status(AppStatus::Stopped);
+
+        let mut response = AppResponse::new();
+        response.push_command(SystemCommand::PrintToTerminal(message));
+        Some(response)
+    }
+
+    fn draw_app(&mut self, _clock: &Clock, display_controller: &mut DisplayController) {
+        if *self.app_params.get_status() == AppStatus::Stopped {
+            display_controller.clear_text_layer();
+            return;
+        }
+
+        self.terminal.render(display_controller, true);
+    }
+
+    fn stop_app(&mut self) {
+        self.connection = None;
+    }
+
+    fn open_file(&mut self, path: &str) {
+        self.address = Some(path.to_string());
+    }
+}
@@ -1,5 +1,5 @@
 use chrono::{Local, Timelike};
-use fantasy_cpc_app::{FantasyCpcApp, FantasyCppAppDefaultParams};
+use fantasy_cpc_app::{input::InputState, FantasyCpcApp, FantasyCppAppDefaultParams};
 use fantasy_cpc_display_controller::{
     color_palettes::*,
     config::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH},
@@ -393,7 +393,7 @@ impl FantasyCpcApp for WeatherApp {
 
     fn update_app(
         &mut self,
-        _inputs: Option<&winit_input_helper::WinitInputHelper>,
+        _inputs: Option<&InputState>,
         clock: &fantasy_cpc_clock::Clock,
     ) -> Option<fantasy_cpc_app::AppResponse> {
         // let response = AppResponse::new();
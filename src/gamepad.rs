@@ -0,0 +1,53 @@
+use fantasy_cpc_app::joystick::{JoystickButton, JOYSTICK_BUTTON_COUNT, JOYSTICK_COUNT};
+use gilrs::{Axis, Button, Gamepad, Gilrs};
+
+/// How far a stick must be pushed to count as a direction, from 0.0 to 1.0
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// The host's gamepads, the first one connected is the joystick of player 1,
+/// the second one the joystick of player 2.
+pub struct Gamepads {
+    gilrs: Gilrs,
+}
+
+impl Gamepads {
+    /// None if the host's gamepads can't be read.
+    pub fn new() -> Option<Gamepads> {
+        match Gilrs::new() {
+            Ok(gilrs) => Some(Gamepads { gilrs }),
+            Err(error) => {
+                println!("Gamepads: {}", error);
+                None
+            }
+        }
+    }
+
+    /// The joystick buttons held on each gamepad: the d-pad or the left stick for the
+    /// directions, the bottom face button for fire 1 and the right one for fire 2.
+    pub fn read(&mut self) -> [[bool; JOYSTICK_BUTTON_COUNT]; JOYSTICK_COUNT] {
+        // Gilrs updates the state of the gamepads as its events are read
+        while self.gilrs.next_event().is_some() {}
+
+        let mut held = [[false; JOYSTICK_BUTTON_COUNT]; JOYSTICK_COUNT];
+        for (player, (_, gamepad)) in self.gilrs.gamepads().take(JOYSTICK_COUNT).enumerate() {
+            for button in JoystickButton::ALL {
+                held[player][button as usize] = is_held(&gamepad, button);
+            }
+        }
+        held
+    }
+}
+
+fn is_held(gamepad: &Gamepad, button: JoystickButton) -> bool {
+    let stick_x = gamepad.value(Axis::LeftStickX);
+    let stick_y = gamepad.value(Axis::LeftStickY);
+
+    match button {
+        JoystickButton::Up => gamepad.is_pressed(Button::DPadUp) || stick_y > STICK_THRESHOLD,
+        JoystickButton::Down => gamepad.is_pressed(Button::DPadDown) || stick_y < -STICK_THRESHOLD,
+        JoystickButton::Left => gamepad.is_pressed(Button::DPadLeft) || stick_x < -STICK_THRESHOLD,
+        JoystickButton::Right => gamepad.is_pressed(Button::DPadRight) || stick_x > STICK_THRESHOLD,
+        JoystickButton::Fire1 => gamepad.is_pressed(Button::South),
+        JoystickButton::Fire2 => gamepad.is_pressed(Button::East),
+    }
+}
@@ -0,0 +1,116 @@
+use fantasy_cpc_app::{
+    input::{InputState, Key, KeyState, TextChar},
+    mouse::Mouse,
+};
+use winit::event::VirtualKeyCode;
+use winit_input_helper::{self, WinitInputHelper};
+
+/// The host's key for each key of the machine's keyboard
+const KEY_CODES: [(Key, VirtualKeyCode); 80] = [
+    (Key::A, VirtualKeyCode::A),
+    (Key::B, VirtualKeyCode::B),
+    (Key::C, VirtualKeyCode::C),
+    (Key::D, VirtualKeyCode::D),
+    (Key::E, VirtualKeyCode::E),
+    (Key::F, VirtualKeyCode::F),
+    (Key::G, VirtualKeyCode::G),
+    (Key::H, VirtualKeyCode::H),
+    (Key::I, VirtualKeyCode::I),
+    (Key::J, VirtualKeyCode::J),
+    (Key::K, VirtualKeyCode::K),
+    (Key::L, VirtualKeyCode::L),
+    (Key::M, VirtualKeyCode::M),
+    (Key::N, VirtualKeyCode::N),
+    (Key::O, VirtualKeyCode::O),
+    (Key::P, VirtualKeyCode::P),
+    (Key::Q, VirtualKeyCode::Q),
+    (Key::R, VirtualKeyCode::R),
+    (Key::S, VirtualKeyCode::S),
+    (Key::T, VirtualKeyCode::T),
+    (Key::U, VirtualKeyCode::U),
+    (Key::V, VirtualKeyCode::V),
+    (Key::W, VirtualKeyCode::W),
+    (Key::X, VirtualKeyCode::X),
+    (Key::Y, VirtualKeyCode::Y),
+    (Key::Z, VirtualKeyCode::Z),
+    (Key::Key0, VirtualKeyCode::Key0),
+    (Key::Key1, VirtualKeyCode::Key1),
+    (Key::Key2, VirtualKeyCode::Key2),
+    (Key::Key3, VirtualKeyCode::Key3),
+    (Key::Key4, VirtualKeyCode::Key4),
+    (Key::Key5, VirtualKeyCode::Key5),
+    (Key::Key6, VirtualKeyCode::Key6),
+    (Key::Key7, VirtualKeyCode::Key7),
+    (Key::Key8, VirtualKeyCode::Key8),
+    (Key::Key9, VirtualKeyCode::Key9),
+    (Key::F1, VirtualKeyCode::F1),
+    (Key::F2, VirtualKeyCode::F2),
+    (Key::F3, VirtualKeyCode::F3),
+    (Key::F4, VirtualKeyCode::F4),
+    (Key::F5, VirtualKeyCode::F5),
+    (Key::F6, VirtualKeyCode::F6),
+    (Key::F7, VirtualKeyCode::F7),
+    (Key::F8, VirtualKeyCode::F8),
+    (Key::F9, VirtualKeyCode::F9),
+    (Key::F10, VirtualKeyCode::F10),
+    (Key::F11, VirtualKeyCode::F11),
+    (Key::F12, VirtualKeyCode::F12),
+    (Key::Escape, VirtualKeyCode::Escape),
+    (Key::Return, VirtualKeyCode::Return),
+    (Key::Tab, VirtualKeyCode::Tab),
+    (Key::Back, VirtualKeyCode::Back),
+    (Key::Space, VirtualKeyCode::Space),
+    (Key::Insert, VirtualKeyCode::Insert),
+    (Key::Delete, VirtualKeyCode::Delete),
+    (Key::Home, VirtualKeyCode::Home),
+    (Key::End, VirtualKeyCode::End),
+    (Key::PageUp, VirtualKeyCode::PageUp),
+    (Key::PageDown, VirtualKeyCode::PageDown),
+    (Key::Left, VirtualKeyCode::Left),
+    (Key::Right, VirtualKeyCode::Right),
+    (Key::Up, VirtualKeyCode::Up),
+    (Key::Down, VirtualKeyCode::Down),
+    (Key::LShift, VirtualKeyCode::LShift),
+    (Key::RShift, VirtualKeyCode::RShift),
+    (Key::LControl, VirtualKeyCode::LControl),
+    (Key::RControl, VirtualKeyCode::RControl),
+    (Key::LAlt, VirtualKeyCode::LAlt),
+    (Key::RAlt, VirtualKeyCode::RAlt),
+    (Key::Comma, VirtualKeyCode::Comma),
+    (Key::Period, VirtualKeyCode::Period),
+    (Key::Slash, VirtualKeyCode::Slash),
+    (Key::Backslash, VirtualKeyCode::Backslash),
+    (Key::Semicolon, VirtualKeyCode::Semicolon),
+    (Key::Apostrophe, VirtualKeyCode::Apostrophe),
+    (Key::Grave, VirtualKeyCode::Grave),
+    (Key::Minus, VirtualKeyCode::Minus),
+    (Key::Equals, VirtualKeyCode::Equals),
+    (Key::LBracket, VirtualKeyCode::LBracket),
+    (Key::RBracket, VirtualKeyCode::RBracket),
+];
+
+/// What the user did with the host's keyboard and mouse during the frame,
+/// as the apps see it.
+pub fn read_inputs(input: &WinitInputHelper, mouse: Mouse) -> InputState {
+    let mut inputs = InputState::new();
+
+    for (key, key_code) in KEY_CODES {
+        let state = KeyState {
+            held: input.key_held(key_code),
+            pressed: input.key_pressed(key_code),
+            released: input.key_released(key_code),
+            repeated: input.key_pressed_os(key_code),
+        };
+        inputs.set_key(key, state);
+    }
+
+    for text_char in input.text() {
+        inputs.type_char(match text_char {
+            winit_input_helper::TextChar::Char(c) => TextChar::Char(c),
+            winit_input_helper::TextChar::Back => TextChar::Back,
+        });
+    }
+
+    inputs.set_mouse(mouse);
+    inputs
+}
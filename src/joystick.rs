@@ -0,0 +1,69 @@
+use std::array;
+
+use fantasy_cpc_app::{
+    input::{InputState, Key},
+    joystick::{Joystick, JoystickButton, JoystickMapping, JOYSTICK_BUTTON_COUNT, JOYSTICK_COUNT},
+};
+
+#[cfg(feature = "gamepad")]
+use crate::gamepad::Gamepads;
+
+/// The machine's joysticks. They are moved with keys of the keyboard that the user can change,
+/// and with the host's gamepads when built with the "gamepad" feature.
+pub struct Joysticks {
+    mappings: [JoystickMapping; JOYSTICK_COUNT],
+    joysticks: [Joystick; JOYSTICK_COUNT],
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<Gamepads>,
+}
+
+impl Joysticks {
+    pub fn new() -> Joysticks {
+        Joysticks {
+            mappings: array::from_fn(JoystickMapping::new),
+            joysticks: [Joystick::default(); JOYSTICK_COUNT],
+            #[cfg(feature = "gamepad")]
+            gamepads: Gamepads::new(),
+        }
+    }
+
+    /// Back to the keys of JoystickMapping::new()
+    pub fn reset_mappings(&mut self) {
+        self.mappings = array::from_fn(JoystickMapping::new);
+    }
+
+    pub fn map_key(&mut self, player: usize, button: JoystickButton, key: Key) {
+        self.mappings[player].set_key(button, key);
+    }
+
+    /// The keys of each joystick, to be printed in the shell.
+    pub fn describe_mappings(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        for (player, mapping) in self.mappings.iter().enumerate() {
+            let keys: Vec<String> = JoystickButton::ALL
+                .iter()
+                .map(|button| format!("{}={:?}", button.get_name(), mapping.get_key(*button)))
+                .collect();
+            lines.push(format!("Joystick {}: {}", player + 1, keys.join(" ")));
+        }
+        lines.join("\u{000D}")
+    }
+
+    /// Moves the joysticks with the keys held and the gamepads, and gives them to the apps.
+    pub fn update(&mut self, inputs: &mut InputState) {
+        #[cfg(feature = "gamepad")]
+        let gamepads_held = match self.gamepads.as_mut() {
+            Some(gamepads) => gamepads.read(),
+            None => [[false; JOYSTICK_BUTTON_COUNT]; JOYSTICK_COUNT],
+        };
+        #[cfg(not(feature = "gamepad"))]
+        let gamepads_held = [[false; JOYSTICK_BUTTON_COUNT]; JOYSTICK_COUNT];
+
+        for (player, gamepad_held) in gamepads_held.into_iter().enumerate() {
+            let keys_held = self.mappings[player].read(inputs);
+            let held = array::from_fn(|button| keys_held[button] || gamepad_held[button]);
+            self.joysticks[player].update(held);
+            inputs.set_joystick(player, self.joysticks[player]);
+        }
+    }
+}
@@ -1,17 +1,19 @@
-use apps::{
-    boot::Boot, cli::shell::Shell, life::Life, mandelbrot::game::Mandelbrot,
-    raycaster::game::Raycaster, weather_app::WeatherApp,
-};
+use app_manager::AppManager;
 use crt_shader_renderer::CrtRenderer;
-use fantasy_cpc_app::{AppResponse, AppStatus, FantasyCpcApp};
+use fantasy_cpc_app::{mouse::MouseMode, CrtParameter, RebootKind, SystemCommand};
 use fantasy_cpc_display_controller::{config::*, *};
+use joystick::Joysticks;
+use fantasy_cpc_vfs::{
+    dsk_drive::DskDrive, host_drive::HostDrive, image_drive::ImageDrive, Drive, FileSystem, VfsError,
+};
+use network::console::{NetworkConsole, DEFAULT_CONSOLE_ADDRESS};
 use pixels::{Error, PixelsBuilder, SurfaceTexture};
 use rodio::Source;
 use shader_variables::ShaderVariables;
 use sound::play;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize, Position},
-    event::Event,
+    event::{DeviceEvent, Event},
     event_loop::{ControlFlow, EventLoop},
     window::{Fullscreen, WindowBuilder},
 };
@@ -19,11 +21,27 @@ use winit_input_helper::WinitInputHelper;
 
 use fantasy_cpc_clock::Clock;
 
+mod app_manager;
 mod apps;
 mod crt_shader_renderer;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod input;
+mod joystick;
+mod mouse;
+mod network;
 mod shader_variables;
 mod sound;
 
+/// Where the C: drive is stored on the host when no directory or disc image (.img) is given
+const DEFAULT_DISC: &str = "disc";
+
+/// Drive letters of the .dsk images, in the order they are given
+const FLOPPY_DRIVES: [char; 2] = ['A', 'B'];
+
+/// Vertical distortion of the CRT shader for each "dist" level, 0 is a flat screen
+const DISTORTION_LEVELS: [f32; 7] = [0.0, 32.0, 16.0, 8.0, 2.0, 1.0, 0.5];
+
 fn main() -> Result<(), Error> {
     // ************************************************ SOUND INIT ************************************************
     // First time sound is played, it takes a few seconds and gets de-sync'ed with the display
@@ -62,7 +80,8 @@ fn main() -> Result<(), Error> {
         }
     }
 
-    window.set_cursor_visible(true);
+    // The machine draws its own pointer
+    window.set_cursor_visible(false);
 
     // pixels set-up
     let mut pixels = {
@@ -88,7 +107,11 @@ fn main() -> Result<(), Error> {
 
     // The variables passed to the app.update(...) that is in focus
     // or to the shell if no other app is running.
+    // The mouse movement reported by the device, used when the mouse is captured in relative mode
     let mut mouse_move_delta: (f64, f64) = (0.0, 0.0);
+    let mut mouse_mode = MouseMode::Pointer;
+    // The keys of the joysticks can be changed from the shell
+    let mut joysticks = Joysticks::new();
 
     // Fantasy CPC graphics engine
     // Offers a text layer, console, sprite layer, background layers and tiles layers that can be accessed
@@ -96,6 +119,7 @@ fn main() -> Result<(), Error> {
     // Its render combines all the layers in its frame, applies the crt filter and sends it to
     // pixels to display the final image in the window.
     let mut display_controller: DisplayController = DisplayController::new();
+    mouse::init_mouse_pointer(&mut display_controller);
 
     // A crt renderer using pixels upscaler and a CRT shader in WGSL
     let mut shader_variables: ShaderVariables = ShaderVariables::new();
@@ -103,43 +127,59 @@ fn main() -> Result<(), Error> {
 
     // ****************************************************** APPS SETUP ***********************************************
 
-    // The Shell is the command line interpreter app.
-    // It is launched at startup after the boot animation.
-    // The winit event loop will update and render the shell by default if
-    // no other process is running or has the focus.
-    // The Shell uses the console as default output.
-    // When closing/quitting an app, it should always fall back to the shell.
-    let mut shell = Box::new(Shell::new());
-    shell.get_app_params().change_status(AppStatus::Running);
-
-    // ********* //
-    // The apps  //
-    // ********* //
-
-    // To be managed properly, apps must be added to that list.
-    // The main loop goes through the list and updates/renders the apps according to their statuses.
-    let mut app_list: Vec<Box<dyn FantasyCpcApp>> = Vec::new();
-
-    // BOOT APP, not really an app, just plays the animation at startup, and when "reboot" command is sent
-    let mut boot = Box::new(Boot::new());
-    boot.get_app_params().change_status(AppStatus::Running);
-    app_list.push(boot);
-
-    // CONWAY'S GAME OF LIFE, TEXT MODE
-    let life = Box::new(Life::new());
-    app_list.push(life);
+    // ****************************************************** STORAGE SETUP ********************************************
+
+    // Options start with --, the other arguments are discs
+    let (options, paths): (Vec<String>, Vec<String>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+
+    // The C: drive is stored in the directory or the disc image passed on the command line.
+    // CPC .dsk images on the command line go in the floppy drives A: and B:
+    let (floppies, discs): (Vec<String>, Vec<String>) = paths
+        .into_iter()
+        .partition(|arg| arg.to_lowercase().ends_with(".dsk"));
+    let disc = discs.first().cloned().unwrap_or(String::from(DEFAULT_DISC));
+    let mut file_system = FileSystem::new();
+    match mount_disc(&disc) {
+        Ok(drive) => file_system.mount('C', drive),
+        Err(error) => println!("Could not mount {}: {}", disc, error),
+    }
+    for (floppy, letter) in floppies.iter().zip(FLOPPY_DRIVES) {
+        match DskDrive::open(floppy) {
+            Ok(drive) => file_system.mount(letter, Box::new(drive)),
+            Err(error) => println!("Could not mount {}: {}", floppy, error),
+        }
+    }
+    let file_system = file_system.into_handle();
 
-    // WEATHER APP
-    let weather_app = Box::new(WeatherApp::new());
-    app_list.push(weather_app);
+    // Creates the apps and the shell, and decides which one is in the foreground
+    let mut app_manager = AppManager::new(file_system.clone());
 
-    // MANDELBROT
-    let mandelbrot = Box::new(Mandelbrot::new());
-    app_list.push(mandelbrot);
+    // ****************************************************** NETWORK SETUP ********************************************
 
-    // RAYCASTER
-    let raycaster = Box::new(Raycaster::new());
-    app_list.push(raycaster);
+    // The shell can be driven over TCP when asked with --console, on localhost unless another
+    // address is given with --console=0.0.0.0:6502 for example.
+    let console_address = options.iter().find_map(|option| match option.as_str() {
+        "--console" => Some(DEFAULT_CONSOLE_ADDRESS),
+        option => option.strip_prefix("--console="),
+    });
+    let mut network_console = match console_address {
+        None => None,
+        Some(address) => match NetworkConsole::bind(address) {
+            Ok(console) => {
+                println!("Remote console on {}", address);
+                Some(console)
+            }
+            Err(error) => {
+                println!(
+                    "Could not open the remote console on {}: {}",
+                    address, error
+                );
+                None
+            }
+        },
+    };
 
     // ****************************************************** MAIN WINIT EVENT LOOP ***********************************************
 
@@ -150,7 +190,7 @@ fn main() -> Result<(), Error> {
     //If no app is running/rendering, it defaults back to running/rendering the Console 0 and Shell.
     //It goes through app_list and updates all apps that have their update flag to true.
     //It goes through app_list and renders the apps that have their render flag and focus flag to true. Should be just one, so it stops at the first one it finds.
-    //It reads the messages returned by the apps displays them to Console 0 and interpets them.
+    //It executes the system commands returned by the apps.
     //TODO It sends messages to apps
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll; //Poll is synchronized with V-Sync
@@ -170,6 +210,20 @@ fn main() -> Result<(), Error> {
             }
         }
 
+        if let Event::LoopDestroyed = event {
+            app_manager.save_history();
+            return;
+        }
+
+        if let Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } = event
+        {
+            mouse_move_delta.0 += delta.0;
+            mouse_move_delta.1 += delta.1;
+        }
+
         if input.update(&event) {
             system_clock.update();
 
@@ -179,102 +233,83 @@ fn main() -> Result<(), Error> {
             }
 
             //Updating apps
-            let mut show_shell: bool = true;
-            let mut app_response: Option<AppResponse> = None;
-            //let app_inputs: AppInputs = AppInputs { keyboard_input, char_received, mouse_move_delta, system_clock };
-            for app in app_list.chunks_exact_mut(1) {
-                if *app[0].get_app_params().get_status() == AppStatus::Running {
-                    show_shell = false;
-                };
-
-                app_response =
-                    app[0].exec_app(Some(&input), &system_clock, &mut display_controller);
-            }
-
-            // If no app is in focus, run the shell
-            if show_shell {
-                app_response = shell.exec_app(Some(&input), &system_clock, &mut display_controller);
-            }
-
-            // Process app response
-            //TODO make smarter command line tokenizer and interpreter
-            //TODO make enum of available commands
-            //TODO add support for command line parameters
-            if let Some(response) = app_response {
-                //If app response contains a winit event, send it directly to winit's control flow
-                if let Some(app_event) = response.event {
-                    *control_flow = app_event
+            let mouse = mouse::read_mouse(
+                &input,
+                &window,
+                &pixels,
+                &shader_variables,
+                mouse_mode,
+                mouse_move_delta,
+            );
+            let mut inputs = input::read_inputs(&input, mouse);
+            joysticks.update(&mut inputs);
+            let mut system_commands =
+                app_manager.update(&inputs, &system_clock, &mut display_controller);
+
+            // The remote console gets what the shell printed, and sends commands to it
+            let shell_output = app_manager.take_shell_output();
+            if let Some(console) = network_console.as_mut() {
+                console.send(&shell_output);
+                for command in console.poll() {
+                    system_commands.extend(app_manager.run_remote_command(&command));
                 }
+            }
 
-                //If app_response contains a message, interpret it with command line interpreter.
-                //ex: when pressing enter in console app, the message will contain the command from the console
-                //that command can then be interpreted to run another app, or change settings.
-                if let Some(app_message) = response.message {
-                    println!("App message: {}", app_message);
-
-                    //Tests if message is name of an available app. If so, switches to that app.
-                    for app in app_list.chunks_exact_mut(1) {
-                        if app[0].get_app_params().get_name() == app_message {
-                            app[0].get_app_params().change_status(AppStatus::Running);
-                        }
-                    }
-
-                    //Reboot (resets app statuses to default state and plays boot animation)
-                    if app_message == "reboot" {
-                        // shell.set_state(AppStatus::Stopped);
-                        // shell.set_state(AppStatus::Running);
-                    }
-
-                    //Shader settings
-                    if app_message == "mode 0" {
-                        shader_variables.mode = 0.0;
-                    }
-
-                    if app_message == "mode 1" {
-                        shader_variables.mode = 1.0;
-                    }
-
-                    if app_message == "mode 2" {
-                        shader_variables.mode = 2.0;
-                    }
+            // Execute the commands sent by the apps
+            for command in system_commands {
+                println!("System command: {:?}", command);
 
-                    if app_message == "dist 0" {
-                        shader_variables.horiz_distortion = 0.0;
-                        shader_variables.vert_distortion = 0.0;
+                match command {
+                    SystemCommand::LaunchApp(name) => app_manager.launch(&name),
+                    SystemCommand::LaunchAppWithFile(name, path) => {
+                        app_manager.launch_with_file(&name, &path)
                     }
-
-                    if app_message == "dist 1" {
-                        shader_variables.horiz_distortion = 32.0 * (4.0 / 3.0);
-                        shader_variables.vert_distortion = 32.0;
+                    SystemCommand::StopApp(name) => app_manager.stop(&name),
+                    SystemCommand::BackgroundApp(name) => app_manager.background(&name),
+                    SystemCommand::ForegroundApp(name) => app_manager.foreground(&name),
+                    SystemCommand::ListApps => app_manager.list_apps(),
+                    SystemCommand::SetCrtParameter(CrtParameter::Mode(mode)) => {
+                        shader_variables.mode = mode as f32;
                     }
-
-                    if app_message == "dist 2" {
-                        shader_variables.horiz_distortion = 16.0 * (4.0 / 3.0);
-                        shader_variables.vert_distortion = 16.0;
+                    SystemCommand::SetCrtParameter(CrtParameter::Distortion(level)) => {
+                        let distortion = DISTORTION_LEVELS[level.min(DISTORTION_LEVELS.len() - 1)];
+                        shader_variables.horiz_distortion = distortion * (4.0 / 3.0);
+                        shader_variables.vert_distortion = distortion;
                     }
-
-                    if app_message == "dist 3" {
-                        shader_variables.horiz_distortion = 8.0 * (4.0 / 3.0);
-                        shader_variables.vert_distortion = 8.0;
+                    SystemCommand::MapJoystick(player, button, key) => {
+                        joysticks.map_key(player, button, key)
                     }
-
-                    if app_message == "dist 4" {
-                        shader_variables.horiz_distortion = 2.0 * (4.0 / 3.0);
-                        shader_variables.vert_distortion = 2.0;
+                    SystemCommand::ListJoystickMappings => {
+                        app_manager.print_message(&joysticks.describe_mappings())
                     }
-
-                    if app_message == "dist 5" {
-                        shader_variables.horiz_distortion = 1.0 * (4.0 / 3.0);
-                        shader_variables.vert_distortion = 1.0;
+                    //Reboot, stops everything, resets the display and plays the boot animation
+                    SystemCommand::Reboot(RebootKind::Warm) => {
+                        app_manager.warm_reboot();
+                        display_controller.reset();
                     }
-
-                    if app_message == "dist 6" {
-                        shader_variables.horiz_distortion = 0.5 * (4.0 / 3.0);
-                        shader_variables.vert_distortion = 0.5;
+                    SystemCommand::Reboot(RebootKind::Cold) => {
+                        app_manager.save_history();
+                        app_manager = AppManager::new(file_system.clone());
+                        shader_variables = ShaderVariables::new();
+                        joysticks.reset_mappings();
+                        display_controller.reset();
                     }
+                    SystemCommand::Quit => *control_flow = ControlFlow::Exit,
+                    SystemCommand::PrintToTerminal(text) => app_manager.print_message(&text),
                 }
             }
 
+            // Captures the mouse or shows the pointer, as the app in the foreground wants
+            let new_mouse_mode = app_manager.get_mouse_mode();
+            mouse::apply_mouse_mode(
+                &window,
+                &mut display_controller,
+                new_mouse_mode,
+                mouse_mode,
+                &mouse,
+            );
+            mouse_mode = new_mouse_mode;
+
             //Combine all the layers, render text, render sprites, etc...
             //into pixel's frame buffer
             display_controller.render(pixels.frame_mut());
@@ -288,3 +323,12 @@ fn main() -> Result<(), Error> {
         }
     });
 }
+
+/// Opens a disc image if the path ends with .img, or uses the path as a directory.
+fn mount_disc(path: &str) -> Result<Box<dyn Drive>, VfsError> {
+    if path.ends_with(".img") {
+        Ok(Box::new(ImageDrive::open(path)?))
+    } else {
+        Ok(Box::new(HostDrive::new(path)?))
+    }
+}
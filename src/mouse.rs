@@ -0,0 +1,147 @@
+use fantasy_cpc_app::mouse::{Mouse, MouseMode};
+use fantasy_cpc_display_controller::{
+    config::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    text_layer::text_layer::frame_coord_to_text_coord,
+    DisplayController,
+};
+use pixels::Pixels;
+use winit::{
+    dpi::PhysicalSize,
+    window::{CursorGrabMode, Window},
+};
+use winit_input_helper::WinitInputHelper;
+
+use crate::shader_variables::ShaderVariables;
+
+/// Image of the machine's pointer, an arrow with its tip in the top left corner
+const MOUSE_POINTER_IMAGE: &str = include_str!("../resources/sprites/mouse_cursor.txt");
+
+/// Gives the display controller its pointer image.
+pub fn init_mouse_pointer(display_controller: &mut DisplayController) {
+    if let Err(error) = display_controller
+        .get_mouse_pointer_mut()
+        .set_image_from_text(MOUSE_POINTER_IMAGE)
+    {
+        println!("Mouse pointer: {}", error);
+    }
+}
+
+/// Same curvature as warp() in crt_shader.wgsl: from a point of the window to the point
+/// of the picture that the shader draws there, both between 0.0 and 1.0.
+fn warp(position: (f32, f32), shader_variables: &ShaderVariables) -> (f32, f32) {
+    if shader_variables.horiz_distortion == 0.0 || shader_variables.vert_distortion == 0.0 {
+        return position;
+    }
+
+    let warp_x = 1.0 / shader_variables.horiz_distortion;
+    let warp_y = 1.0 / shader_variables.vert_distortion;
+    let x = position.0 * 2.0 - 1.0;
+    let y = position.1 * 2.0 - 1.0;
+    let warped_x = x * (1.0 + y * y * warp_x);
+    let warped_y = y * (1.0 + x * x * warp_y);
+    (warped_x * 0.5 + 0.5, warped_y * 0.5 + 0.5)
+}
+
+/// Pixel of the frame under a point of the window, through the CRT distortion and the upscaling.
+fn window_to_frame(
+    position: (f32, f32),
+    window_size: PhysicalSize<u32>,
+    pixels: &Pixels,
+    shader_variables: &ShaderVariables,
+) -> Option<(usize, usize)> {
+    if window_size.width == 0 || window_size.height == 0 {
+        return None;
+    }
+
+    let (x, y) = warp(
+        (
+            position.0 / window_size.width as f32,
+            position.1 / window_size.height as f32,
+        ),
+        shader_variables,
+    );
+
+    // Pixels works in the coordinates of its surface, stretched over the whole window
+    pixels
+        .window_pos_to_pixel((x * SCREEN_WIDTH as f32, y * SCREEN_HEIGHT as f32))
+        .ok()
+}
+
+/// The mouse as the machine sees it during this frame.
+/// relative_delta is the movement reported by the device, used in relative mode
+/// where the pointer is captured and doesn't move in the window.
+pub fn read_mouse(
+    input: &WinitInputHelper,
+    window: &Window,
+    pixels: &Pixels,
+    shader_variables: &ShaderVariables,
+    mode: MouseMode,
+    relative_delta: (f64, f64),
+) -> Mouse {
+    let mut mouse = Mouse {
+        wheel: input.scroll_diff(),
+        ..Mouse::default()
+    };
+    for button in 0..mouse.held.len() {
+        mouse.held[button] = input.mouse_held(button);
+        mouse.pressed[button] = input.mouse_pressed(button);
+        mouse.released[button] = input.mouse_released(button);
+    }
+
+    if mode == MouseMode::Relative {
+        mouse.delta = (relative_delta.0 as f32, relative_delta.1 as f32);
+        return mouse;
+    }
+
+    let window_size = window.inner_size();
+    let to_frame = |position| window_to_frame(position, window_size, pixels, shader_variables);
+    mouse.frame_position = input.mouse().and_then(to_frame);
+    mouse.text_position = mouse
+        .frame_position
+        .and_then(|(x, y)| frame_coord_to_text_coord(x, y));
+
+    let (diff_x, diff_y) = input.mouse_diff();
+    if let (Some(current), Some((x, y))) = (mouse.frame_position, input.mouse()) {
+        if let Some(previous) = to_frame((x - diff_x, y - diff_y)) {
+            mouse.delta = (
+                current.0 as f32 - previous.0 as f32,
+                current.1 as f32 - previous.1 as f32,
+            );
+        }
+    }
+
+    mouse
+}
+
+/// Captures or releases the mouse and shows the machine's pointer when the mode changes,
+/// and moves the pointer every frame.
+pub fn apply_mouse_mode(
+    window: &Window,
+    display_controller: &mut DisplayController,
+    mode: MouseMode,
+    previous_mode: MouseMode,
+    mouse: &Mouse,
+) {
+    if mode != previous_mode {
+        if mode == MouseMode::Relative {
+            // Locked isn't available on every platform, Confined is the next best thing
+            let grabbed = window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+            if let Err(error) = grabbed {
+                println!("Could not capture the mouse: {}", error);
+            }
+        } else if previous_mode == MouseMode::Relative {
+            let _ = window.set_cursor_grab(CursorGrabMode::None);
+        }
+    }
+
+    let pointer = display_controller.get_mouse_pointer_mut();
+    match (mode, mouse.frame_position) {
+        (MouseMode::Pointer, Some((x, y))) => {
+            pointer.set_position(x as isize, y as isize);
+            pointer.set_visible(true);
+        }
+        _ => pointer.set_visible(false),
+    }
+}
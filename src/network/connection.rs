@@ -0,0 +1,64 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// How long connect() waits for the other side, the machine is frozen meanwhile
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Bytes read at most in one call to read()
+const MAX_READ_SIZE: usize = 16 * 1024;
+
+/// An outgoing TCP connection for apps, like a modem dialing a BBS.
+/// Reading and writing never wait, so they can be done once per frame.
+pub struct TcpConnection {
+    stream: TcpStream,
+    closed: bool,
+}
+
+impl TcpConnection {
+    /// Connects to "host:port", ex: "localhost:2323".
+    pub fn connect(address: &str) -> io::Result<TcpConnection> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "Unknown host");
+        for socket_address in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    return Ok(TcpConnection {
+                        stream,
+                        closed: false,
+                    });
+                }
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Bytes received since the last call, empty if there are none.
+    pub fn read(&mut self) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; MAX_READ_SIZE];
+        let count = match self.stream.read(&mut buffer) {
+            Ok(0) => {
+                self.closed = true;
+                0
+            }
+            Ok(count) => count,
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => 0,
+            Err(error) => return Err(error),
+        };
+        buffer.truncate(count);
+        Ok(buffer)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stream.write_all(bytes)
+    }
+
+    /// True once the other side has closed the connection.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
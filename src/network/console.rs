@@ -0,0 +1,166 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+/// Address the console listens on when none is given, only reachable from the host itself
+pub const DEFAULT_CONSOLE_ADDRESS: &str = "127.0.0.1:6502";
+
+const WELCOME: &str = "Fantasy CPC remote console, one shell command per line\r\n>";
+
+/// Bytes read at most from a client in one call to poll()
+const MAX_READ_SIZE: usize = 4 * 1024;
+
+/// Longest line a client can send, it is disconnected beyond
+const MAX_LINE_SIZE: usize = 4 * 1024;
+
+/// Output kept for a client that doesn't read it, it is disconnected beyond
+const MAX_OUTPUT_SIZE: usize = 1024 * 1024;
+
+struct Client {
+    stream: TcpStream,
+    /// What was received after the last complete line
+    input: Vec<u8>,
+    /// What couldn't be sent yet without waiting
+    output: Vec<u8>,
+    /// No line received yet, the first one is checked before running anything
+    first_line: bool,
+    closed: bool,
+}
+
+/// True for the first line of an HTTP request, "POST / HTTP/1.1" for example. A web page
+/// can send one to localhost, the client is then dropped before its lines are run.
+fn is_http_request(line: &str) -> bool {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words[..] {
+        [method, _, version] => {
+            method.chars().all(|c| c.is_ascii_uppercase()) && version.starts_with("HTTP/")
+        }
+        _ => false,
+    }
+}
+
+impl Client {
+    /// Reads what the client sent, returns the complete lines.
+    fn receive(&mut self) -> Vec<String> {
+        let mut buffer = [0u8; MAX_READ_SIZE];
+        match self.stream.read(&mut buffer) {
+            Ok(0) => self.closed = true,
+            Ok(count) => self.input.extend_from_slice(&buffer[..count]),
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => (),
+            Err(_) => self.closed = true,
+        }
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.input.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.input.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            lines.push(line.trim_end_matches(['\r', '\n']).to_string());
+        }
+
+        if self.first_line && !lines.is_empty() {
+            self.first_line = false;
+            if is_http_request(&lines[0]) {
+                println!("Remote console: HTTP request refused");
+                self.closed = true;
+                return Vec::new();
+            }
+        }
+        if self.input.len() > MAX_LINE_SIZE {
+            self.closed = true;
+            return Vec::new();
+        }
+        lines
+    }
+
+    /// Sends as much of the pending output as possible without waiting.
+    fn flush(&mut self) {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => {
+                    self.closed = true;
+                    return;
+                }
+                Ok(count) => {
+                    self.output.drain(..count);
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => {
+                    self.closed = true;
+                    return;
+                }
+            }
+        }
+        if self.output.len() > MAX_OUTPUT_SIZE {
+            self.closed = true;
+        }
+    }
+}
+
+/// Drives the machine from another program over TCP, like a terminal plugged in
+/// the serial port. Every line received is run by the shell as if it was typed,
+/// and every client receives what the shell prints, escape sequences included.
+/// Try it with "nc localhost 6502" or "telnet localhost 6502".
+pub struct NetworkConsole {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+impl NetworkConsole {
+    pub fn bind(address: &str) -> io::Result<NetworkConsole> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(NetworkConsole {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    /// Accepts the new clients and returns the commands received since the last call.
+    pub fn poll(&mut self) -> Vec<String> {
+        while let Ok((stream, address)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_err() {
+                continue;
+            }
+            println!("Remote console: {} connected", address);
+            self.clients.push(Client {
+                stream,
+                input: Vec::new(),
+                output: WELCOME.as_bytes().to_vec(),
+                first_line: true,
+                closed: false,
+            });
+        }
+
+        let mut lines = Vec::new();
+        for client in self.clients.iter_mut() {
+            lines.extend(client.receive());
+            client.flush();
+        }
+        self.clients.retain(|client| !client.closed);
+        lines
+    }
+
+    /// Sends the shell's output to every client. ENTER becomes CR LF and the
+    /// machine's BACKSPACE becomes BS, like on a serial line.
+    pub fn send(&mut self, text: &str) {
+        if text.is_empty() || self.clients.is_empty() {
+            return;
+        }
+
+        let mut bytes = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                unicode::ENTER => bytes.extend_from_slice(b"\r\n"),
+                unicode::BACKSPACE => bytes.push(0x08),
+                c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+
+        for client in self.clients.iter_mut() {
+            client.output.extend_from_slice(&bytes);
+            client.flush();
+        }
+        self.clients.retain(|client| !client.closed);
+    }
+}
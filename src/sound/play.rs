@@ -51,9 +51,8 @@ pub fn play(bpm: f32, track_1: Vec<(Option<f32>, f32)>, track_2: Vec<(Option<f32
         channel_2.sleep_until_end();
     });
     
-    match handle {
-    Ok(v) => println!("working with version: {v:?}"),
-    Err(e) => println!("error parsing header: {e:?}"),
-}
+    if let Err(error) = handle {
+        println!("Sound: {}", error);
+    }
 
 }
\ No newline at end of file
@@ -0,0 +1,153 @@
+//! Accented characters typed with dead keys: the accent first, then the letter.
+
+/// Accents sent by the host for a dead key that it didn't compose itself.
+/// The ASCII ` ^ and ~ aren't dead keys, they are typed for themselves.
+pub const DEAD_KEYS: [char; 6] = ['´', 'ˋ', 'ˆ', '¨', '˜', '¸'];
+
+/// Dead key, letters and the accented letters, in the same order
+const ACCENTS: [(char, &str, &str); 6] = [
+    ('´', "aeiouyAEIOUY", "áéíóúýÁÉÍÓÚÝ"),
+    ('ˋ', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ('ˆ', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+    ('¨', "aeiouyAEIOUY", "äëïöüÿÄËÏÖÜŸ"),
+    ('˜', "anoANO", "ãñõÃÑÕ"),
+    ('¸', "cC", "çÇ"),
+];
+
+pub fn is_dead_key(c: char) -> bool {
+    DEAD_KEYS.contains(&c)
+}
+
+/// The dead key for an accent, the ASCII ` ^ and ~ give the same as the dead keys.
+fn dead_key(accent: char) -> char {
+    match accent {
+        '`' => 'ˋ',
+        '^' => 'ˆ',
+        '~' => '˜',
+        _ => accent,
+    }
+}
+
+/// The letter with the accent, ex: compose('´', 'e') is 'é'.
+/// None if the letter doesn't take that accent.
+pub fn compose(accent: char, letter: char) -> Option<char> {
+    let accent = dead_key(accent);
+    let (_, letters, accented) = ACCENTS.iter().find(|(dead, _, _)| *dead == accent)?;
+    let index = letters.chars().position(|l| l == letter)?;
+    accented.chars().nth(index)
+}
+
+/// The letter and its accent, the opposite of compose(): 'é' gives ('e', '´').
+pub fn decompose(c: char) -> Option<(char, char)> {
+    ACCENTS.iter().find_map(|(dead, letters, accented)| {
+        let index = accented.chars().position(|a| a == c)?;
+        Some((letters.chars().nth(index)?, *dead))
+    })
+}
+
+/// Turns the typed characters into accented ones: a dead key is kept
+/// until the next character, then both are composed.
+#[derive(Default)]
+pub struct Composer {
+    dead_key: Option<char>,
+}
+
+impl Composer {
+    pub fn new() -> Composer {
+        Composer { dead_key: None }
+    }
+
+    /// The dead key waiting for a letter, to show it to the user.
+    pub fn get_dead_key(&self) -> Option<char> {
+        self.dead_key
+    }
+
+    /// Takes a typed character and gives the ones to write.
+    /// A dead key followed by a space or the same dead key writes the accent alone,
+    /// followed by a letter that has no such accent it writes both.
+    pub fn push(&mut self, c: char) -> Vec<char> {
+        match self.dead_key.take() {
+            None if is_dead_key(c) => {
+                self.dead_key = Some(c);
+                Vec::new()
+            }
+            None => vec![c],
+            Some(dead_key) if c == ' ' || c == dead_key => vec![dead_key],
+            Some(dead_key) if is_dead_key(c) => {
+                self.dead_key = Some(c);
+                vec![dead_key]
+            }
+            Some(dead_key) => match compose(dead_key, c) {
+                Some(accented) => vec![accented],
+                None => vec![dead_key, c],
+            },
+        }
+    }
+
+    /// Forgets the dead key, when the user moves elsewhere or erases it.
+    pub fn cancel(&mut self) -> Option<char> {
+        self.dead_key.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_all(composer: &mut Composer, text: &str) -> String {
+        text.chars().flat_map(|c| composer.push(c)).collect()
+    }
+
+    #[test]
+    fn composes_accented_letters() {
+        assert_eq!(compose('´', 'e'), Some('é'));
+        assert_eq!(compose('¸', 'C'), Some('Ç'));
+        assert_eq!(compose('^', 'o'), Some('ô'));
+        assert_eq!(compose('´', 'x'), None);
+        assert_eq!(compose('x', 'e'), None);
+    }
+
+    #[test]
+    fn decomposes_what_compose_gives() {
+        for (dead_key, letters, _) in ACCENTS {
+            for letter in letters.chars() {
+                let accented = compose(dead_key, letter).unwrap();
+                assert_eq!(decompose(accented), Some((letter, dead_key)));
+            }
+        }
+        assert_eq!(decompose('e'), None);
+    }
+
+    #[test]
+    fn composer_waits_for_the_letter() {
+        let mut composer = Composer::new();
+        assert!(composer.push('¨').is_empty());
+        assert_eq!(composer.get_dead_key(), Some('¨'));
+        assert_eq!(composer.push('u'), vec!['ü']);
+        assert_eq!(composer.get_dead_key(), None);
+    }
+
+    #[test]
+    fn composer_writes_the_accent_alone() {
+        let mut composer = Composer::new();
+        assert_eq!(push_all(&mut composer, "´ a"), "´a");
+        assert_eq!(push_all(&mut composer, "´´"), "´");
+        assert_eq!(push_all(&mut composer, "´x"), "´x");
+        assert_eq!(push_all(&mut composer, "´ˋa"), "´à");
+    }
+
+    #[test]
+    fn composer_ignores_ascii_accents() {
+        let mut composer = Composer::new();
+        assert_eq!(push_all(&mut composer, "^a~n`e"), "^a~n`e");
+    }
+
+    #[test]
+    fn cancel_forgets_the_dead_key() {
+        let mut composer = Composer::new();
+        composer.push('˜');
+        assert_eq!(composer.cancel(), Some('˜'));
+        assert_eq!(composer.push('n'), vec!['n']);
+        assert_eq!(composer.cancel(), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Characters for the keys that don't type anything, so that they can travel with the text.
+//! They are in the private use area, at the same codes as the function keys of macOS.
+
+pub const UP: char = '\u{F700}';
+pub const DOWN: char = '\u{F701}';
+pub const LEFT: char = '\u{F702}';
+pub const RIGHT: char = '\u{F703}';
+pub const F1: char = '\u{F704}';
+pub const F2: char = '\u{F705}';
+pub const F3: char = '\u{F706}';
+pub const F4: char = '\u{F707}';
+pub const F5: char = '\u{F708}';
+pub const F6: char = '\u{F709}';
+pub const F7: char = '\u{F70A}';
+pub const F8: char = '\u{F70B}';
+pub const F9: char = '\u{F70C}';
+pub const F10: char = '\u{F70D}';
+pub const F11: char = '\u{F70E}';
+pub const F12: char = '\u{F70F}';
+pub const INSERT: char = '\u{F727}';
+pub const DELETE: char = '\u{F728}';
+pub const HOME: char = '\u{F729}';
+pub const END: char = '\u{F72B}';
+pub const PAGE_UP: char = '\u{F72C}';
+pub const PAGE_DOWN: char = '\u{F72D}';
+
+/// The character of a function key, F1 to F12.
+pub fn function_key(number: u8) -> Option<char> {
+    match number {
+        1..=12 => char::from_u32(F1 as u32 + number as u32 - 1),
+        _ => None,
+    }
+}
+
+/// True for the characters of the cursor, editing and function keys.
+pub fn is_special_key(c: char) -> bool {
+    ('\u{F700}'..='\u{F8FF}').contains(&c)
+}
+
+/// The control character typed with Ctrl: Ctrl+A is 1, Ctrl+Z is 26,
+/// Ctrl+[ is ESCAPE... None for keys that have no control character.
+pub fn control(c: char) -> Option<char> {
+    match c.to_ascii_uppercase() {
+        c @ '@'..='_' => char::from_u32(c as u32 - '@' as u32),
+        ' ' => Some('\u{0000}'),
+        '?' => Some('\u{007F}'),
+        _ => None,
+    }
+}
+
+/// The key typed with Ctrl to get a control character, the opposite of control().
+pub fn control_letter(c: char) -> Option<char> {
+    match c {
+        '\u{0000}'..='\u{001F}' => char::from_u32(c as u32 + '@' as u32),
+        '\u{007F}' => Some('?'),
+        _ => None,
+    }
+}
+
+/// What a VT100 terminal sends to the program when the key is typed.
+pub fn vt100_sequence(c: char) -> Option<&'static str> {
+    let sequence = match c {
+        crate::ENTER => "\r",
+        crate::TAB => "\t",
+        crate::ESCAPE => "\u{001B}",
+        UP => "\u{001B}[A",
+        DOWN => "\u{001B}[B",
+        RIGHT => "\u{001B}[C",
+        LEFT => "\u{001B}[D",
+        HOME => "\u{001B}[H",
+        END => "\u{001B}[F",
+        INSERT => "\u{001B}[2~",
+        DELETE => "\u{001B}[3~",
+        PAGE_UP => "\u{001B}[5~",
+        PAGE_DOWN => "\u{001B}[6~",
+        F1 => "\u{001B}OP",
+        F2 => "\u{001B}OQ",
+        F3 => "\u{001B}OR",
+        F4 => "\u{001B}OS",
+        F5 => "\u{001B}[15~",
+        F6 => "\u{001B}[17~",
+        F7 => "\u{001B}[18~",
+        F8 => "\u{001B}[19~",
+        F9 => "\u{001B}[20~",
+        F10 => "\u{001B}[21~",
+        F11 => "\u{001B}[23~",
+        F12 => "\u{001B}[24~",
+        _ => return None,
+    };
+    Some(sequence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_keys() {
+        assert_eq!(function_key(1), Some(F1));
+        assert_eq!(function_key(12), Some(F12));
+        assert_eq!(function_key(0), None);
+        assert_eq!(function_key(13), None);
+        assert!(is_special_key(F5) && is_special_key(PAGE_DOWN));
+        assert!(!is_special_key('a') && !is_special_key(crate::ENTER));
+    }
+
+    #[test]
+    fn control_characters() {
+        assert_eq!(control('a'), Some('\u{0001}'));
+        assert_eq!(control('Z'), Some('\u{001A}'));
+        assert_eq!(control('['), Some(crate::ESCAPE));
+        assert_eq!(control(' '), Some('\u{0000}'));
+        assert_eq!(control('?'), Some('\u{007F}'));
+        assert_eq!(control('1'), None);
+        assert_eq!(control_letter('a'), None);
+    }
+
+    #[test]
+    fn control_letter_gives_back_the_key() {
+        for code in (0..0x20).chain([0x7F]) {
+            let c = char::from_u32(code).unwrap();
+            let letter = control_letter(c).unwrap();
+            assert_eq!(control(letter), Some(c));
+        }
+    }
+
+    #[test]
+    fn vt100_sequences() {
+        assert_eq!(vt100_sequence(UP), Some("\u{001B}[A"));
+        assert_eq!(vt100_sequence(F1), Some("\u{001B}OP"));
+        assert_eq!(vt100_sequence(F12), Some("\u{001B}[24~"));
+        assert_eq!(vt100_sequence(crate::ENTER), Some("\r"));
+        assert_eq!(vt100_sequence('a'), None);
+        for number in 1..=12 {
+            assert!(vt100_sequence(function_key(number).unwrap()).is_some());
+        }
+    }
+}
@@ -1,6 +1,11 @@
+pub mod compose;
+pub mod keys;
+
 pub const ENTER: char = '\u{000D}'; //Unicode for carriage return. 000A is line feed
 pub const ESCAPE: char = '\u{001B}';
 pub const TAB: char = '\u{0009}';
+/// Stands for a character that can't be displayed
+pub const REPLACEMENT_CHAR: char = '\u{FFFD}';
 #[cfg(target_os = "macos")]
 pub const BACKSPACE: char = '\u{9003}';
 #[cfg(target_os = "windows")]